@@ -6,7 +6,9 @@ use crossterm::{
     terminal::{enable_raw_mode, EnterAlternateScreen},
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
-use std::{io, panic};
+use std::io;
+use syservice::backend::{BackendKind, SharedBackend};
+use tui::component::editor::EditorView;
 use tui::compositor::{Compositor, CompositorContext};
 use tui::job::JobQueue;
 
@@ -15,19 +17,19 @@ pub struct Application {
     terminal: Terminal<CrosstermBackend<io::Stdout>>,
     compositor: Compositor,
     compositor_context: CompositorContext,
-    
+
     pub jobs: JobQueue, // 引用全局 JobQueue
 }
 impl Application {
-    pub fn new() -> Self {
+    pub fn new(backend: SharedBackend) -> Self {
         let mut stdout = io::stdout();
         execute!(stdout, EnterAlternateScreen).expect("Enter alternate screen error");
 
-        let backend = CrosstermBackend::new(stdout);
-        let mut terminal = Terminal::new(backend).expect("terminal initialization failed");
-        let mut compositor = Compositor::new();
+        let terminal_backend = CrosstermBackend::new(stdout);
+        let terminal = Terminal::new(terminal_backend).expect("terminal initialization failed");
+        let compositor = Compositor::new();
         enable_raw_mode().expect("Enter raw mode error");
-        let mut cx = CompositorContext::new();
+        let cx = CompositorContext::with_backend(backend);
         Self {
             terminal,
             compositor,
@@ -38,6 +40,17 @@ impl Application {
 
     pub(crate) async fn run(&mut self) {
         let mut input_stream = crossterm::event::EventStream::new();
+        self.compositor_context.refresh_notebooks();
+        match self.compositor_context.backend.kind() {
+            BackendKind::Kernel => {
+                tui::kernel_event::spawn(self.compositor_context.backend.profile());
+            }
+            BackendKind::Filesystem => {
+                if let Some(editor_view) = self.compositor.find::<EditorView>() {
+                    editor_view.set_status_msg("内核未连接, 只读模式");
+                }
+            }
+        }
         use futures_util::StreamExt;
         // 主循环
         loop {
@@ -51,11 +64,15 @@ impl Application {
                             self.exit_app();
                             break;
                         }
-                        self.compositor.handle_event(key.clone(), &mut self.compositor_context);
+                        self.compositor.handle_event(key, &mut self.compositor_context);
                     }
                 }
                 Some(callback) = self.jobs.callbacks.recv() => {
-                    self.jobs.handle_callback(&mut self.compositor, Ok(Some(callback)));
+                    self.jobs.handle_callback(
+                        &mut self.compositor,
+                        &mut self.compositor_context,
+                        Ok(Some(callback)),
+                    );
                     // self.render().await;
                 }
             }
@@ -67,7 +84,8 @@ impl Application {
         // self.terminal.draw(pos).unwrap();
         self.terminal
             .draw(|f| {
-                self.compositor.render(f, f.size(), &mut self.compositor_context);
+                self.compositor
+                    .render(f, f.size(), &mut self.compositor_context);
             })
             .expect("rendering error");
     }
@@ -1,5 +1,9 @@
-use std::{io};
 use crate::application::Application;
+use std::io;
+use std::path::Path;
+use syservice::backend;
+use syservice::config::Config;
+use syservice::export;
 
 mod application;
 
@@ -9,10 +13,52 @@ fn main() -> io::Result<()> {
 
 #[tokio::main]
 async fn main_impl() -> io::Result<()> {
-    let mut app = Application::new();
+    let export = export_arg(std::env::args())?;
+    let profile = Config::load()
+        .and_then(|config| config.profile(profile_arg(std::env::args()).as_deref()))
+        .map_err(io::Error::other)?;
+    // 内核未运行时退回到直接读取 data 目录
+    let backend = backend::connect(profile).await.map_err(io::Error::other)?;
+    if let Some((notebook, out_dir)) = export {
+        let written = export::export_notebook(backend.as_ref(), &notebook, Path::new(&out_dir))
+            .await
+            .map_err(io::Error::other)?;
+        println!("exported {} pages to {}", written.len(), out_dir);
+        return Ok(());
+    }
+    let mut app = Application::new(backend);
     app.run().await;
     Ok(())
+}
 
-
+/// 读取启动参数中的 `--profile <name>` 或 `--profile=<name>`
+fn profile_arg(args: impl IntoIterator<Item = String>) -> Option<String> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--profile" || arg == "-p" {
+            return args.next();
+        }
+        if let Some(name) = arg.strip_prefix("--profile=") {
+            return Some(name.to_string());
+        }
+    }
+    None
 }
 
+/// `--export` 的用法
+const EXPORT_USAGE: &str = "usage: --export <notebook-id> <out-dir>";
+
+/// 读取启动参数中的 `--export <笔记本 ID> <目录>`, 导出为 HTML 后退出, 不启动界面.
+/// 缺少参数时返回错误, 而不是启动界面
+fn export_arg(args: impl IntoIterator<Item = String>) -> io::Result<Option<(String, String)>> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--export" {
+            return match args.next().zip(args.next()) {
+                Some(export) => Ok(Some(export)),
+                None => Err(io::Error::new(io::ErrorKind::InvalidInput, EXPORT_USAGE)),
+            };
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,301 @@
+//! 块属性接口 `/api/attr/*` 以及 IAL 属性的类型化视图
+use crate::client::SiyuanClient;
+use crate::error::ApiResult;
+use crate::lute::node::Node;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+pub const API_GET_BLOCK_ATTRS: &str = "/api/attr/getBlockAttrs";
+pub const API_SET_BLOCK_ATTRS: &str = "/api/attr/setBlockAttrs";
+
+/// 自定义属性的前缀
+pub const CUSTOM_PREFIX: &str = "custom-";
+
+const NAME: &str = "name";
+const ALIAS: &str = "alias";
+const MEMO: &str = "memo";
+const BOOKMARK: &str = "bookmark";
+const FOLD: &str = "fold";
+const UPDATED: &str = "updated";
+const STYLE: &str = "style";
+
+#[derive(Serialize)]
+struct SetBlockAttrs<'a> {
+    id: &'a str,
+    attrs: &'a HashMap<String, String>,
+}
+
+#[derive(Serialize)]
+struct GetBlockAttrs<'a> {
+    id: &'a str,
+}
+
+/// 获取块的全部属性
+pub async fn get_block_attrs(
+    client: &SiyuanClient,
+    id: &str,
+) -> ApiResult<HashMap<String, String>> {
+    client
+        .call(API_GET_BLOCK_ATTRS, &GetBlockAttrs { id })
+        .await
+}
+
+/// 设置块属性. 值为空字符串的属性会被内核删除.
+pub async fn set_block_attrs(
+    client: &SiyuanClient,
+    id: &str,
+    attrs: &HashMap<String, String>,
+) -> ApiResult<()> {
+    client
+        .call(API_SET_BLOCK_ATTRS, &SetBlockAttrs { id, attrs })
+        .await
+}
+
+pub async fn get_block_attributes(client: &SiyuanClient, id: &str) -> ApiResult<BlockAttributes> {
+    get_block_attrs(client, id)
+        .await
+        .map(|attrs| BlockAttributes::from_properties(&attrs))
+}
+
+/// 将 `attrs` 相对于 `before` 的变化写回内核, 被移除的属性会一并删除
+pub async fn set_block_attributes(
+    client: &SiyuanClient,
+    id: &str,
+    before: &BlockAttributes,
+    attrs: &BlockAttributes,
+) -> ApiResult<()> {
+    set_block_attrs(client, id, &attrs.changes_from(before)).await
+}
+
+/// 块属性(IAL)的类型化视图.
+///
+/// 未建模的内置属性(`id`, `title`, `type`, `icon` 等)保存在 `other` 中,
+/// 转换回 `Node::properties` 时原样写回.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockAttributes {
+    /// 命名
+    pub name: Option<String>,
+    /// 别名, 存储时以 `,` 分隔
+    pub alias: Vec<String>,
+    /// 备注
+    pub memo: Option<String>,
+    /// 书签
+    pub bookmark: Option<String>,
+    /// 是否折叠, 存储为 `fold="1"`
+    pub fold: bool,
+    /// 最后更新时间, 格式为 `20060102150405`
+    pub updated: Option<String>,
+    /// 内联 CSS 样式
+    pub style: Option<String>,
+    /// `custom-*` 属性, key 不包含 `custom-` 前缀
+    pub custom: BTreeMap<String, String>,
+    pub other: BTreeMap<String, String>,
+}
+
+impl BlockAttributes {
+    pub fn from_properties(properties: &HashMap<String, String>) -> Self {
+        let mut attrs = BlockAttributes::default();
+        for (key, value) in properties {
+            match key.as_str() {
+                NAME => attrs.name = Some(value.clone()),
+                ALIAS => {
+                    attrs.alias = value
+                        .split(',')
+                        .filter(|alias| !alias.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                }
+                MEMO => attrs.memo = Some(value.clone()),
+                BOOKMARK => attrs.bookmark = Some(value.clone()),
+                FOLD => attrs.fold = value == "1",
+                UPDATED => attrs.updated = Some(value.clone()),
+                STYLE => attrs.style = Some(value.clone()),
+                _ => match key.strip_prefix(CUSTOM_PREFIX) {
+                    Some(name) => {
+                        attrs.custom.insert(name.to_string(), value.clone());
+                    }
+                    None => {
+                        attrs.other.insert(key.clone(), value.clone());
+                    }
+                },
+            }
+        }
+        attrs
+    }
+
+    pub fn to_properties(&self) -> HashMap<String, String> {
+        let mut properties: HashMap<String, String> = self
+            .other
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        let mut insert = |key: &str, value: &Option<String>| {
+            if let Some(value) = value {
+                properties.insert(key.to_string(), value.clone());
+            }
+        };
+        insert(NAME, &self.name);
+        insert(MEMO, &self.memo);
+        insert(BOOKMARK, &self.bookmark);
+        insert(UPDATED, &self.updated);
+        insert(STYLE, &self.style);
+        if !self.alias.is_empty() {
+            properties.insert(ALIAS.to_string(), self.alias.join(","));
+        }
+        if self.fold {
+            properties.insert(FOLD.to_string(), "1".to_string());
+        }
+        for (name, value) in &self.custom {
+            properties.insert(format!("{}{}", CUSTOM_PREFIX, name), value.clone());
+        }
+        properties
+    }
+
+    pub fn from_node(node: &Node) -> Self {
+        node.properties
+            .as_ref()
+            .map(Self::from_properties)
+            .unwrap_or_default()
+    }
+
+    /// 覆盖 `node` 的属性
+    pub fn apply_to(&self, node: &mut Node) {
+        let properties = self.to_properties();
+        node.properties = (!properties.is_empty()).then_some(properties);
+    }
+
+    pub fn custom(&self, name: &str) -> Option<&str> {
+        self.custom.get(name).map(String::as_str)
+    }
+
+    /// 设置自定义属性, `name` 可以带或不带 `custom-` 前缀
+    pub fn set_custom(&mut self, name: &str, value: impl Into<String>) {
+        let name = name.strip_prefix(CUSTOM_PREFIX).unwrap_or(name);
+        self.custom.insert(name.to_string(), value.into());
+    }
+
+    /// 计算需要提交给 `setBlockAttrs` 的属性: 新增或修改的属性取新值,
+    /// 被移除的属性置为空字符串.
+    pub fn changes_from(&self, before: &BlockAttributes) -> HashMap<String, String> {
+        let before = before.to_properties();
+        let after = self.to_properties();
+        let mut changes: HashMap<String, String> = after
+            .iter()
+            .filter(|(key, value)| before.get(*key) != Some(*value))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        for key in before.keys() {
+            if !after.contains_key(key) {
+                changes.insert(key.clone(), String::new());
+            }
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub::StubServer;
+    use serde_json::json;
+
+    fn properties(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip_properties() {
+        let props = properties(&[
+            ("id", "20250512161452-b4yltcf"),
+            ("updated", "20250523153605"),
+            ("name", "intro"),
+            ("alias", "first,second"),
+            ("memo", "needs review"),
+            ("bookmark", "⭐"),
+            ("fold", "1"),
+            ("style", "color: red;"),
+            ("custom-review", "approved"),
+            ("custom-reviewer", "crowds"),
+            ("heading-fold", "1"),
+        ]);
+        let attrs = BlockAttributes::from_properties(&props);
+        assert_eq!(attrs.name.as_deref(), Some("intro"));
+        assert_eq!(attrs.alias, vec!["first", "second"]);
+        assert!(attrs.fold);
+        assert_eq!(attrs.custom("review"), Some("approved"));
+        assert_eq!(
+            attrs.other.get("id").map(String::as_str),
+            Some("20250512161452-b4yltcf")
+        );
+        assert_eq!(attrs.to_properties(), props);
+    }
+
+    #[test]
+    fn test_apply_to_node() {
+        let mut node: Node = serde_json::from_value(json!({
+            "ID": "20231026101149-10uol14",
+            "Type": "NodeParagraph",
+            "Properties": {"id": "20231026101149-10uol14", "updated": "20250512161508"}
+        }))
+        .unwrap();
+        let mut attrs = BlockAttributes::from_node(&node);
+        attrs.set_custom("custom-review", "pending");
+        attrs.apply_to(&mut node);
+
+        let props = node.properties.as_ref().unwrap();
+        assert_eq!(props.get("custom-review").unwrap(), "pending");
+        assert_eq!(props.get("id").unwrap(), "20231026101149-10uol14");
+        assert_eq!(BlockAttributes::from_node(&node), attrs);
+    }
+
+    #[test]
+    fn test_changes_from() {
+        let before = BlockAttributes::from_properties(&properties(&[
+            ("memo", "old"),
+            ("custom-review", "pending"),
+            ("id", "20231026101149-10uol14"),
+        ]));
+        let mut after = before.clone();
+        after.memo = None;
+        after.set_custom("review", "approved");
+        after.fold = true;
+
+        let changes = after.changes_from(&before);
+        assert_eq!(
+            changes,
+            properties(&[("memo", ""), ("custom-review", "approved"), ("fold", "1")])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_and_set_block_attrs() {
+        let server = StubServer::start()
+            .await
+            .route(
+                API_GET_BLOCK_ATTRS,
+                json!({"id": "20210912214605-uhi5gco", "custom-review": "pending", "updated": "20210912214925"}),
+            )
+            .route(API_SET_BLOCK_ATTRS, json!(null));
+        let client = server.client();
+
+        let before = get_block_attributes(&client, "20210912214605-uhi5gco")
+            .await
+            .unwrap();
+        assert_eq!(before.custom("review"), Some("pending"));
+
+        let mut after = before.clone();
+        after.set_custom("review", "approved");
+        set_block_attributes(&client, "20210912214605-uhi5gco", &before, &after)
+            .await
+            .unwrap();
+
+        let sent = server.requests();
+        assert_eq!(
+            sent[1].body,
+            json!({"id": "20210912214605-uhi5gco", "attrs": {"custom-review": "approved"}})
+        );
+    }
+}
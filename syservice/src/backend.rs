@@ -0,0 +1,167 @@
+//! 数据后端: 通过内核 HTTP 接口访问, 或在内核未运行时直接读取工作空间的 data 目录.
+//!
+//! UI 只依赖 [`Backend`], 启动时由 [`connect`] 根据内核能否连通自动选择实现.
+pub(crate) mod fs;
+pub(crate) mod kernel;
+
+pub use fs::FsBackend;
+pub use kernel::KernelBackend;
+
+use crate::client::SiyuanClient;
+use crate::config::Profile;
+use crate::domain::{BlockInfo, DocFile, Notebook};
+use crate::lute::node::Node;
+use crate::search::{FullTextSearch, FullTextSearchResult};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 探测内核是否在线的超时时间
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+    /// 思源内核 HTTP 接口
+    Kernel,
+    /// 直接读取 data 目录, 只读
+    Filesystem,
+}
+
+#[async_trait]
+pub trait Backend: Debug + Send + Sync {
+    fn kind(&self) -> BackendKind;
+
+    fn profile(&self) -> &Profile;
+
+    /// 列出全部笔记本(包括已关闭的)
+    async fn ls_notebooks(&self) -> Result<Vec<Notebook>>;
+
+    /// 列出笔记本中 `path` 下的直接子文档, `path` 为 `/` 时列出顶层文档
+    async fn list_docs(&self, notebook: &str, path: &str) -> Result<Vec<DocFile>>;
+
+    /// 全文搜索块
+    async fn search(&self, req: &FullTextSearch) -> Result<FullTextSearchResult>;
+
+    /// 批量查询块信息, 不存在的块不出现在结果中
+    async fn get_blocks(&self, ids: &[String]) -> Result<Vec<BlockInfo>>;
+
+    /// 读取文档, `file_path` 为相对 data 目录的路径, 例如 `<box>/<id>/<id>.sy`
+    async fn load_document(&self, file_path: &str) -> Result<Node>;
+}
+
+pub type SharedBackend = Arc<dyn Backend>;
+
+/// 内核能够连通时使用 [`KernelBackend`], 否则在 data 目录存在时退回 [`FsBackend`]
+pub async fn connect(profile: Profile) -> Result<SharedBackend> {
+    let kernel = KernelBackend::new(SiyuanClient::new(profile.clone())?);
+    let online = matches!(
+        tokio::time::timeout(PING_TIMEOUT, kernel.ping()).await,
+        Ok(Ok(_))
+    );
+    if online || !profile.data_dir.is_dir() {
+        return Ok(Arc::new(kernel));
+    }
+    Ok(Arc::new(FsBackend::new(profile)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{fixture_data_dir, MockKernel};
+
+    #[tokio::test]
+    async fn test_connect_prefers_kernel() {
+        let kernel = MockKernel::start(fixture_data_dir()).await.unwrap();
+        let backend = connect(kernel.profile()).await.unwrap();
+        assert_eq!(backend.kind(), BackendKind::Kernel);
+    }
+
+    #[tokio::test]
+    async fn test_connect_falls_back_to_filesystem() {
+        // 绑定后立即释放端口, 保证没有服务在监听
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let profile = Profile {
+            base_url,
+            data_dir: fixture_data_dir(),
+            ..Profile::default()
+        };
+        let backend = connect(profile).await.unwrap();
+        assert_eq!(backend.kind(), BackendKind::Filesystem);
+    }
+
+    /// 两种后端对同一工作空间应给出相同的结果
+    #[tokio::test]
+    async fn test_backends_agree() {
+        let kernel = MockKernel::start(fixture_data_dir()).await.unwrap();
+        let backends: Vec<SharedBackend> = vec![
+            Arc::new(KernelBackend::new(kernel.client())),
+            Arc::new(FsBackend::new(kernel.profile()).index_file(None)),
+        ];
+        for backend in backends {
+            let kind = backend.kind();
+            let notebooks = backend.ls_notebooks().await.unwrap();
+            assert_eq!(notebooks.len(), 1, "{:?}", kind);
+            assert_eq!(notebooks[0].name, "Fixture", "{:?}", kind);
+
+            let docs = backend.list_docs(&notebooks[0].id, "/").await.unwrap();
+            let titles: Vec<&str> = docs.iter().map(|doc| doc.title()).collect();
+            assert_eq!(titles, vec!["Draft", "Inbox"], "{:?}", kind);
+            assert_eq!(docs[0].sub_file_count, 1, "{:?}", kind);
+            assert_eq!(
+                (docs[1].name.as_str(), docs[1].name1.as_str()),
+                ("Inbox.sy", ""),
+                "{:?}",
+                kind
+            );
+
+            let children = backend
+                .list_docs(&notebooks[0].id, &docs[0].path)
+                .await
+                .unwrap();
+            assert_eq!(children[0].title(), "Rust notes", "{:?}", kind);
+
+            let result = backend
+                .search(&FullTextSearch::new("ownership"))
+                .await
+                .unwrap();
+            let ids: Vec<&str> = result.blocks().iter().map(|b| b.id.as_str()).collect();
+            assert_eq!(
+                ids,
+                vec!["20240107160850-h7ncw2q", "20240107160905-3xk1m0d"],
+                "{:?}",
+                kind
+            );
+            let block = &result.blocks()[1];
+            assert_eq!(block.h_path, "/Draft/Rust notes", "{:?}", kind);
+            assert_eq!(block.updated, "20240107160950", "{:?}", kind);
+            assert_eq!(
+                block.highlighted_content().text,
+                "Rust uses ownership to manage memory.",
+                "{:?}",
+                kind
+            );
+
+            let file_path = format!("{}{}", block.box_id, block.path);
+            let document = backend.load_document(&file_path).await.unwrap();
+            assert_eq!(document.id.as_deref(), Some(block.root_id.as_str()));
+
+            let ids = [
+                "20240101000000-missing".to_string(),
+                "20250512161442-im7tv6x".to_string(),
+                "20240301091510-m4n5b6v".to_string(),
+            ];
+            let mut infos = backend.get_blocks(&ids).await.unwrap();
+            infos.sort_by(|a, b| a.id.cmp(&b.id));
+            assert_eq!(infos.len(), 2, "{:?}", kind);
+            assert_eq!(infos[0].name, "todo", "{:?}", kind);
+            assert_eq!(infos[0].content, "Buy milk", "{:?}", kind);
+            assert_eq!(infos[1].block_type, "i", "{:?}", kind);
+            assert_eq!(infos[1].root_id, "20230620165438-1pqr39r", "{:?}", kind);
+            assert_eq!(infos[1].fcontent, "Parent1", "{:?}", kind);
+        }
+    }
+}
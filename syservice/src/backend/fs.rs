@@ -0,0 +1,228 @@
+use super::{Backend, BackendKind};
+use crate::config::Profile;
+use crate::doctree::{self, DocNode};
+use crate::domain::{BlockInfo, DocFile, Notebook, NotebookConf, SyBlockType};
+use crate::file::load_json_node;
+use crate::index::SearchIndex;
+use crate::lute::node::{is_node_id_pattern, Node, NodeType};
+use crate::search::{FullTextSearch, FullTextSearchResult};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const NOTEBOOK_CONF: &str = ".siyuan/conf.json";
+
+/// 直接读取工作空间 data 目录, 不依赖内核.
+///
+/// 搜索和查询块使用 [`SearchIndex`], 首次使用时加载索引文件,
+/// 之后每次使用前只重新索引修改过的文档.
+#[derive(Debug, Clone)]
+pub struct FsBackend {
+    profile: Profile,
+    /// 索引文件, 为 `None` 时只在内存中建立索引
+    index_file: Option<PathBuf>,
+    index: Arc<Mutex<Option<SearchIndex>>>,
+}
+
+impl FsBackend {
+    pub fn new(profile: Profile) -> Self {
+        let index_file = Some(SearchIndex::default_path(&profile.data_dir));
+        Self {
+            profile,
+            index_file,
+            index: Arc::default(),
+        }
+    }
+
+    pub fn index_file(mut self, index_file: Option<PathBuf>) -> Self {
+        self.index_file = index_file;
+        self
+    }
+
+    fn data_dir(&self) -> PathBuf {
+        self.profile.data_dir.clone()
+    }
+
+    /// 在阻塞线程中同步索引后执行 `f`
+    async fn with_index<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&SearchIndex) -> T + Send + 'static,
+    {
+        let data_dir = self.data_dir();
+        let index_file = self.index_file.clone();
+        let index = self.index.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut guard = match index.lock() {
+                Ok(guard) => guard,
+                Err(poisoned) => {
+                    // 上次使用时发生了 panic, 索引可能不完整, 重新加载
+                    let mut guard = poisoned.into_inner();
+                    *guard = None;
+                    index.clear_poison();
+                    guard
+                }
+            };
+            let index = guard.get_or_insert_with(|| match &index_file {
+                Some(path) => SearchIndex::load(path),
+                None => SearchIndex::new(),
+            });
+            index.sync(&data_dir)?;
+            if let (true, Some(path)) = (index.is_dirty(), &index_file) {
+                // 索引文件只是缓存, 保存失败不影响本次查询
+                let _ = index.save(path);
+            }
+            Ok(f(index))
+        })
+        .await?
+    }
+}
+
+#[async_trait]
+impl Backend for FsBackend {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Filesystem
+    }
+
+    fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    async fn ls_notebooks(&self) -> Result<Vec<Notebook>> {
+        let data_dir = self.data_dir();
+        tokio::task::spawn_blocking(move || {
+            let mut notebooks: Vec<Notebook> = read_notebooks(&data_dir)?
+                .iter()
+                .map(NotebookEntry::to_notebook)
+                .collect();
+            notebooks.sort_by(|a, b| a.sort.cmp(&b.sort).then_with(|| a.id.cmp(&b.id)));
+            Ok(notebooks)
+        })
+        .await?
+    }
+
+    async fn list_docs(&self, notebook: &str, path: &str) -> Result<Vec<DocFile>> {
+        let data_dir = self.data_dir();
+        let notebook = notebook.to_string();
+        let path = path.to_string();
+        tokio::task::spawn_blocking(move || {
+            let docs = doctree::list_children(&data_dir, &notebook, &path)?;
+            Ok(docs.iter().map(DocNode::to_doc_file).collect())
+        })
+        .await?
+    }
+
+    async fn search(&self, req: &FullTextSearch) -> Result<FullTextSearchResult> {
+        let req = req.clone();
+        self.with_index(move |index| index.search(&req)).await
+    }
+
+    async fn get_blocks(&self, ids: &[String]) -> Result<Vec<BlockInfo>> {
+        let ids = ids.to_vec();
+        self.with_index(move |index| ids.iter().filter_map(|id| index.block_info(id)).collect())
+            .await
+    }
+
+    async fn load_document(&self, file_path: &str) -> Result<Node> {
+        let profile = self.profile.clone();
+        let file_path = file_path.to_string();
+        tokio::task::spawn_blocking(move || load_json_node(&profile, &file_path)).await?
+    }
+}
+
+/// `data/<box>/.siyuan/conf.json` 中的笔记本
+pub(crate) struct NotebookEntry {
+    pub id: String,
+    pub conf: NotebookConf,
+}
+
+impl NotebookEntry {
+    pub fn to_notebook(&self) -> Notebook {
+        let extra = &self.conf.extra;
+        Notebook {
+            id: self.id.clone(),
+            name: self.conf.name.clone(),
+            icon: extra
+                .get("icon")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            sort: extra.get("sort").and_then(Value::as_i64).unwrap_or(0) as i32,
+            sort_mode: extra.get("sortMode").and_then(Value::as_i64).unwrap_or(0) as i32,
+            closed: self.conf.closed,
+        }
+    }
+}
+
+/// 读取 `data_dir` 下的全部笔记本, 按 id 排序
+pub(crate) fn read_notebooks(data_dir: &Path) -> Result<Vec<NotebookEntry>> {
+    let mut notebooks = Vec::new();
+    for (name, path) in sorted_entries(data_dir)? {
+        let conf_path = path.join(NOTEBOOK_CONF);
+        if !is_node_id_pattern(&name) || !conf_path.is_file() {
+            continue;
+        }
+        let conf: NotebookConf = serde_json::from_reader(BufReader::new(
+            File::open(&conf_path)
+                .with_context(|| format!("Failed to open {}", conf_path.display()))?,
+        ))
+        .with_context(|| format!("Failed to parse {}", conf_path.display()))?;
+        notebooks.push(NotebookEntry { id: name, conf });
+    }
+    Ok(notebooks)
+}
+
+pub(crate) fn sorted_entries(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            (
+                entry.file_name().to_string_lossy().to_string(),
+                entry.path(),
+            )
+        })
+        .collect();
+    entries.sort();
+    Ok(entries)
+}
+
+/// 读取 `.sy` 文件并设置节点类型
+pub(crate) fn read_doc(path: &Path) -> Result<Node> {
+    let mut node: Node = serde_json::from_reader(BufReader::new(
+        File::open(path).with_context(|| format!("Failed to open {}", path.display()))?,
+    ))
+    .with_context(|| format!("Failed to parse {}", path.display()))?;
+    node.set_node_type_for_tree();
+    Ok(node)
+}
+
+/// 节点对应的块类型, 非块节点返回 `None`
+pub(crate) fn block_type(node: &Node) -> Option<SyBlockType> {
+    let block_type = match node.node_type {
+        NodeType::NodeDocument => SyBlockType::Document,
+        NodeType::NodeHeading => SyBlockType::Title,
+        NodeType::NodeList => SyBlockType::List,
+        NodeType::NodeListItem => SyBlockType::ListItem,
+        NodeType::NodeBlockquote => SyBlockType::Quote,
+        NodeType::NodeSuperBlock => SyBlockType::SuperBlock,
+        NodeType::NodeParagraph => SyBlockType::Paragraph,
+        NodeType::NodeCodeBlock => SyBlockType::Code,
+        NodeType::NodeMathBlock => SyBlockType::Method,
+        NodeType::NodeTable => SyBlockType::Table,
+        NodeType::NodeAttributeView => SyBlockType::DataBlock,
+        NodeType::NodeBlockQueryEmbed => SyBlockType::QueryEmbed,
+        NodeType::NodeVideo => SyBlockType::Video,
+        NodeType::NodeAudio => SyBlockType::Audio,
+        NodeType::NodeWidget => SyBlockType::Widget,
+        NodeType::NodeIFrame => SyBlockType::IFrame,
+        NodeType::NodeHtmlBlock => SyBlockType::Html,
+        NodeType::NodeThematicBreak => SyBlockType::Tb,
+        _ => return None,
+    };
+    Some(block_type)
+}
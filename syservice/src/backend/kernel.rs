@@ -0,0 +1,85 @@
+use super::{Backend, BackendKind};
+use crate::client::SiyuanClient;
+use crate::config::Profile;
+use crate::domain::{BlockInfo, DocFile, ListDocsByPath, Notebook};
+use crate::error::ApiResult;
+use crate::filetree::list_docs_by_path;
+use crate::lute::node::Node;
+use crate::search::{full_text_search_block, FullTextSearch, FullTextSearchResult};
+use crate::sql::Query;
+use crate::{file, notebook, API_SQL_QUERY};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::json;
+
+pub const API_SYSTEM_VERSION: &str = "/api/system/version";
+
+/// 通过内核 HTTP 接口访问数据
+#[derive(Debug, Clone)]
+pub struct KernelBackend {
+    client: SiyuanClient,
+}
+
+impl KernelBackend {
+    pub fn new(client: SiyuanClient) -> Self {
+        Self { client }
+    }
+
+    pub fn client(&self) -> &SiyuanClient {
+        &self.client
+    }
+
+    /// 返回内核版本号, 用于探测内核是否在线
+    pub async fn ping(&self) -> ApiResult<String> {
+        self.client.call(API_SYSTEM_VERSION, &json!({})).await
+    }
+}
+
+#[async_trait]
+impl Backend for KernelBackend {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Kernel
+    }
+
+    fn profile(&self) -> &Profile {
+        self.client.profile()
+    }
+
+    async fn ls_notebooks(&self) -> Result<Vec<Notebook>> {
+        Ok(notebook::ls_notebooks(&self.client).await?)
+    }
+
+    async fn list_docs(&self, notebook: &str, path: &str) -> Result<Vec<DocFile>> {
+        let req = ListDocsByPath {
+            notebook: notebook.to_string(),
+            path: path.to_string(),
+            sort: None,
+            max_list_count: None,
+        };
+        Ok(list_docs_by_path(&self.client, &req).await?.files)
+    }
+
+    async fn search(&self, req: &FullTextSearch) -> Result<FullTextSearchResult> {
+        Ok(full_text_search_block(&self.client, req).await?)
+    }
+
+    async fn get_blocks(&self, ids: &[String]) -> Result<Vec<BlockInfo>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let stmt = Query::blocks()
+            .select(&["id", "root_id", "type", "name", "content", "fcontent"])
+            .any_of("id", ids)
+            // 没有 LIMIT 时内核会使用搜索设置中的默认条数
+            .limit(ids.len() as u32)
+            .build();
+        Ok(self
+            .client
+            .call(API_SQL_QUERY, &json!({ "stmt": stmt }))
+            .await?)
+    }
+
+    async fn load_document(&self, file_path: &str) -> Result<Node> {
+        Ok(file::get_json_node(&self.client, file_path).await?)
+    }
+}
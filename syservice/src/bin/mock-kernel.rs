@@ -0,0 +1,32 @@
+//! 本地内核替身: `mock-kernel [data_dir] [--listen 127.0.0.1:6806]`
+//!
+//! 未指定 data 目录时使用仓库自带的测试工作空间.
+use syservice::mock::{fixture_data_dir, MockKernel};
+
+const DEFAULT_LISTEN: &str = "127.0.0.1:6806";
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let mut data_dir = None;
+    let mut listen = DEFAULT_LISTEN.to_string();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--listen" | "-l" => {
+                listen = args
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("--listen requires an address"))?
+            }
+            _ => data_dir = Some(arg),
+        }
+    }
+    let data_dir = data_dir.map(Into::into).unwrap_or_else(fixture_data_dir);
+    let kernel = MockKernel::bind(&listen, &data_dir).await?;
+    println!(
+        "mock kernel serving {} at {}",
+        data_dir.display(),
+        kernel.base_url()
+    );
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
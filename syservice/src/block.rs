@@ -0,0 +1,205 @@
+//! 块操作接口 `/api/block/*`
+use crate::client::SiyuanClient;
+use crate::domain::*;
+use crate::error::ApiResult;
+
+pub const API_INSERT_BLOCK: &str = "/api/block/insertBlock";
+pub const API_PREPEND_BLOCK: &str = "/api/block/prependBlock";
+pub const API_APPEND_BLOCK: &str = "/api/block/appendBlock";
+pub const API_UPDATE_BLOCK: &str = "/api/block/updateBlock";
+pub const API_DELETE_BLOCK: &str = "/api/block/deleteBlock";
+pub const API_MOVE_BLOCK: &str = "/api/block/moveBlock";
+pub const API_GET_BLOCK_KRAMDOWN: &str = "/api/block/getBlockKramdown";
+
+/// 在 `next_id` 之前或 `previous_id` 之后插入块
+pub async fn insert_block(client: &SiyuanClient, req: &InsertBlock) -> ApiResult<Vec<Transaction>> {
+    client.call(API_INSERT_BLOCK, req).await
+}
+
+/// 插入为父块的第一个子块
+pub async fn prepend_block(client: &SiyuanClient, req: &ChildBlock) -> ApiResult<Vec<Transaction>> {
+    client.call(API_PREPEND_BLOCK, req).await
+}
+
+/// 插入为父块的最后一个子块
+pub async fn append_block(client: &SiyuanClient, req: &ChildBlock) -> ApiResult<Vec<Transaction>> {
+    client.call(API_APPEND_BLOCK, req).await
+}
+
+pub async fn update_block(client: &SiyuanClient, req: &UpdateBlock) -> ApiResult<Vec<Transaction>> {
+    client.call(API_UPDATE_BLOCK, req).await
+}
+
+pub async fn delete_block(client: &SiyuanClient, id: &str) -> ApiResult<Vec<Transaction>> {
+    let req = BlockId { id: id.to_string() };
+    client.call(API_DELETE_BLOCK, &req).await
+}
+
+pub async fn move_block(client: &SiyuanClient, req: &MoveBlock) -> ApiResult<Vec<Transaction>> {
+    client.call(API_MOVE_BLOCK, req).await
+}
+
+/// 获取块的 kramdown 源码(包含 IAL)
+pub async fn get_block_kramdown(client: &SiyuanClient, id: &str) -> ApiResult<BlockKramdown> {
+    let req = BlockId { id: id.to_string() };
+    client.call(API_GET_BLOCK_KRAMDOWN, &req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ApiError;
+    use crate::stub::StubServer;
+    use serde_json::json;
+
+    fn insert_transaction(action: &str, id: &str) -> serde_json::Value {
+        json!([{
+            "doOperations": [{
+                "action": action,
+                "data": format!("<div data-node-id=\"{}\"></div>", id),
+                "id": id,
+                "parentID": "",
+                "previousID": "20211229114650-vrek5x6",
+                "retData": null
+            }],
+            "undoOperations": null
+        }])
+    }
+
+    #[tokio::test]
+    async fn test_insert_block() {
+        let server = StubServer::start().await.route(
+            API_INSERT_BLOCK,
+            insert_transaction("insert", "20211230115020-g02dfx0"),
+        );
+        let req = InsertBlock {
+            data: BlockData::Markdown("foo**bar**".into()),
+            next_id: None,
+            previous_id: Some("20211229114650-vrek5x6".into()),
+            parent_id: None,
+        };
+        let transactions = insert_block(&server.client(), &req).await.unwrap();
+        let op = &transactions[0].do_operations[0];
+        assert_eq!(op.action, OperationAction::Insert);
+        assert_eq!(op.id, "20211230115020-g02dfx0");
+        assert!(transactions[0].undo_operations.is_none());
+
+        let sent = server.requests();
+        assert_eq!(sent[0].path, API_INSERT_BLOCK);
+        assert_eq!(
+            sent[0].body,
+            json!({
+                "dataType": "markdown",
+                "data": "foo**bar**",
+                "previousID": "20211229114650-vrek5x6"
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_child_and_update_blocks() {
+        let server = StubServer::start()
+            .await
+            .route(
+                API_PREPEND_BLOCK,
+                insert_transaction("insert", "20220107173950-7f9m1nb"),
+            )
+            .route(
+                API_APPEND_BLOCK,
+                insert_transaction("insert", "20220107173950-9ecke9f"),
+            )
+            .route(
+                API_UPDATE_BLOCK,
+                insert_transaction("update", "20211230161520-querkps"),
+            );
+        let client = server.client();
+        let parent = "20220107173950-7f9m1nb".to_string();
+
+        let req = ChildBlock {
+            data: BlockData::Dom("<div>foo</div>".into()),
+            parent_id: parent.clone(),
+        };
+        prepend_block(&client, &req).await.unwrap();
+        append_block(&client, &req).await.unwrap();
+        let req = UpdateBlock {
+            data: BlockData::Markdown("updated".into()),
+            id: "20211230161520-querkps".into(),
+        };
+        let transactions = update_block(&client, &req).await.unwrap();
+        assert_eq!(
+            transactions[0].do_operations[0].action,
+            OperationAction::Update
+        );
+
+        let sent = server.requests();
+        assert_eq!(sent.len(), 3);
+        assert_eq!(sent[0].body["dataType"], "dom");
+        assert_eq!(sent[1].body["parentID"], json!(parent));
+        assert_eq!(sent[2].body["id"], "20211230161520-querkps");
+    }
+
+    #[tokio::test]
+    async fn test_delete_and_move_block() {
+        let server = StubServer::start()
+            .await
+            .route(
+                API_DELETE_BLOCK,
+                json!([{"doOperations": [{"action": "delete", "id": "20211230115020-g02dfx0"}]}]),
+            )
+            .route(
+                API_MOVE_BLOCK,
+                json!([{"doOperations": [{"action": "move", "id": "20230406180530-3o1rqkc"}]}]),
+            );
+        let client = server.client();
+
+        let transactions = delete_block(&client, "20211230115020-g02dfx0")
+            .await
+            .unwrap();
+        assert_eq!(
+            transactions[0].do_operations[0].action,
+            OperationAction::Delete
+        );
+
+        let req = MoveBlock {
+            id: "20230406180530-3o1rqkc".into(),
+            previous_id: None,
+            parent_id: Some("20230404183855-woe52ko".into()),
+        };
+        let transactions = move_block(&client, &req).await.unwrap();
+        assert_eq!(
+            transactions[0].do_operations[0].action,
+            OperationAction::Move
+        );
+        assert_eq!(
+            server.requests()[1].body,
+            json!({"id": "20230406180530-3o1rqkc", "parentID": "20230404183855-woe52ko"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_block_kramdown() {
+        let server = StubServer::start().await.route(
+            API_GET_BLOCK_KRAMDOWN,
+            json!({
+                "id": "20201225220955-l154bn4",
+                "kramdown": "* {: id=\"20201225220955-2nn1mns\"}新建笔记本\n  {: id=\"20210131155408-3t627wc\"}"
+            }),
+        );
+        let block = get_block_kramdown(&server.client(), "20201225220955-l154bn4")
+            .await
+            .unwrap();
+        assert_eq!(block.id, "20201225220955-l154bn4");
+        assert!(block.kramdown.contains("新建笔记本"));
+    }
+
+    #[tokio::test]
+    async fn test_kernel_error() {
+        let server = StubServer::start()
+            .await
+            .fail(API_DELETE_BLOCK, -1, "block not found");
+        let err = delete_block(&server.client(), "20211230115020-g02dfx0")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ApiError::Kernel { code: -1, .. }));
+    }
+}
@@ -0,0 +1,265 @@
+//! 块引用解析.
+//!
+//! 动态锚文本(`d`)的引用显示被引用块当前的内容, 静态锚文本(`s`)保持用户填写的文本.
+//! [`RefResolver`] 通过数据后端批量查询被引用块并缓存结果, 查不到的块即为失效的引用,
+//! 由渲染层以不同的样式显示.
+use crate::backend::SharedBackend;
+use crate::domain::BlockInfo;
+use crate::lute::node::Node;
+use crate::lute::{walk, walk_mut, WalkStatus};
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+/// 动态锚文本的最大字符数, 与内核的默认设置一致
+pub const DYNAMIC_ANCHOR_TEXT_MAX_LEN: usize = 96;
+
+/// 动态锚文本: 优先使用命名, 容器块使用第一个子块的内容, 其余使用块内容
+pub fn anchor_text(block: &BlockInfo) -> String {
+    let text = [&block.name, &block.fcontent, &block.content]
+        .into_iter()
+        .map(|text| text.trim())
+        .find(|text| !text.is_empty())
+        .unwrap_or_default();
+    match text.char_indices().nth(DYNAMIC_ANCHOR_TEXT_MAX_LEN) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// 文档中引用的块 ID, 按出现顺序去重
+pub fn ref_ids(node: &Node) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut ids = Vec::new();
+    walk(node, &mut |node, entering| {
+        if let Some(id) = node.block_ref_id().filter(|_| entering) {
+            if seen.insert(id) {
+                ids.push(id.to_string());
+            }
+        }
+        WalkStatus::Continue
+    });
+    ids
+}
+
+/// 按 [`RefResolver::resolve`] 的结果更新文档中动态引用的锚文本, 返回失效引用的块 ID.
+///
+/// 不在 `blocks` 中的引用保持不变, 因此可以把旧版本文档的解析结果用于重新加载后的文档.
+pub fn apply_refs(doc: &mut Node, blocks: &HashMap<String, Option<BlockInfo>>) -> HashSet<String> {
+    let mut dangling = HashSet::new();
+    walk_mut(doc, &mut |node, entering| {
+        let Some(id) = node.block_ref_id().filter(|_| entering) else {
+            return WalkStatus::Continue;
+        };
+        match blocks.get(id) {
+            Some(Some(block)) if node.block_ref_subtype() == Some("d") => {
+                node.text_mark_text_content = Some(anchor_text(block));
+            }
+            Some(None) => {
+                dangling.insert(id.to_string());
+            }
+            _ => {}
+        }
+        WalkStatus::Continue
+    });
+    dangling
+}
+
+/// 带缓存的块引用解析器
+#[derive(Debug)]
+pub struct RefResolver {
+    backend: SharedBackend,
+    /// 块 ID -> 块信息, `None` 表示块不存在
+    cache: Mutex<HashMap<String, Option<BlockInfo>>>,
+}
+
+impl RefResolver {
+    pub fn new(backend: SharedBackend) -> Self {
+        Self {
+            backend,
+            cache: Mutex::default(),
+        }
+    }
+
+    /// 查询被引用的块, 只向后端请求未缓存的块. 块不存在时对应的值为 `None`
+    pub async fn resolve(&self, ids: &[String]) -> Result<HashMap<String, Option<BlockInfo>>> {
+        let missing: Vec<String> = {
+            let cache = self.cache.lock().unwrap();
+            ids.iter()
+                .filter(|id| !cache.contains_key(*id))
+                .cloned()
+                .collect()
+        };
+        if !missing.is_empty() {
+            let mut found: HashMap<String, BlockInfo> = self
+                .backend
+                .get_blocks(&missing)
+                .await?
+                .into_iter()
+                .map(|block| (block.id.clone(), block))
+                .collect();
+            let mut cache = self.cache.lock().unwrap();
+            for id in missing {
+                let block = found.remove(&id);
+                cache.insert(id, block);
+            }
+        }
+        let cache = self.cache.lock().unwrap();
+        Ok(ids
+            .iter()
+            .map(|id| (id.clone(), cache.get(id).cloned().flatten()))
+            .collect())
+    }
+
+    /// 将文档中动态引用的锚文本更新为被引用块的当前内容, 返回失效引用的块 ID
+    pub async fn resolve_document(&self, doc: &mut Node) -> Result<HashSet<String>> {
+        let blocks = self.resolve(&ref_ids(doc)).await?;
+        Ok(apply_refs(doc, &blocks))
+    }
+
+    /// 块被修改或删除后使缓存失效
+    pub fn invalidate(&self, id: &str) {
+        self.cache.lock().unwrap().remove(id);
+    }
+
+    pub fn clear(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{FsBackend, KernelBackend};
+    use crate::mock::{fixture_data_dir, MockKernel};
+    use crate::stub::StubServer;
+    use crate::API_SQL_QUERY;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    const RUST_DOC: &str =
+        "20210808180117-czj9bvb/20230620165438-1pqr39r/20240107160843-8f02mqs.sy";
+
+    fn block(name: &str, content: &str, fcontent: &str) -> BlockInfo {
+        BlockInfo {
+            name: name.to_string(),
+            content: content.to_string(),
+            fcontent: fcontent.to_string(),
+            ..BlockInfo::default()
+        }
+    }
+
+    #[test]
+    fn test_anchor_text() {
+        assert_eq!(anchor_text(&block("todo", "Buy milk", "")), "todo");
+        assert_eq!(anchor_text(&block("", "a b c", "a")), "a");
+        assert_eq!(anchor_text(&block("", " Buy milk\n", "")), "Buy milk");
+        let long = "长".repeat(DYNAMIC_ANCHOR_TEXT_MAX_LEN + 1);
+        let text = anchor_text(&block("", &long, ""));
+        assert_eq!(text.chars().count(), DYNAMIC_ANCHOR_TEXT_MAX_LEN + 3);
+        assert!(text.ends_with("长..."));
+        let exact = "a".repeat(DYNAMIC_ANCHOR_TEXT_MAX_LEN);
+        assert_eq!(anchor_text(&block("", &exact, "")), exact);
+    }
+
+    #[test]
+    fn test_apply_refs_skips_unknown_refs() {
+        let mut doc = crate::lute::protyle::markdown_to_protyle(
+            "((20240301110000-aaaaaaa)) ((20240301110000-bbbbbbb)) ((20240301110000-ccccccc 'old'))\n",
+        );
+        let blocks = HashMap::from([
+            (
+                "20240301110000-aaaaaaa".to_string(),
+                Some(block("", "Buy milk", "")),
+            ),
+            ("20240301110000-bbbbbbb".to_string(), None),
+        ]);
+        let dangling = apply_refs(&mut doc, &blocks);
+        assert_eq!(
+            dangling,
+            HashSet::from(["20240301110000-bbbbbbb".to_string()])
+        );
+        let anchors: Vec<_> = doc.children[0]
+            .children
+            .iter()
+            .filter(|node| node.block_ref_id().is_some())
+            .map(|node| node.text_mark_text_content.as_deref().unwrap_or_default())
+            .collect();
+        assert_eq!(anchors, vec!["Buy milk", "", "old"]);
+    }
+
+    /// 把 Rust notes 中的第一个引用改为动态锚文本, 指向 Inbox 中命名为 `todo` 的块
+    async fn rust_notes(backend: &SharedBackend) -> Node {
+        let mut doc = backend.load_document(RUST_DOC).await.unwrap();
+        walk_mut(&mut doc, &mut |node, entering| {
+            if entering && node.block_ref_id() == Some("20231026101149-10uol14") {
+                node.text_mark_block_ref_id = Some("20240301091510-m4n5b6v".to_string());
+                node.text_mark_block_ref_subtype = Some("d".to_string());
+            }
+            WalkStatus::Continue
+        });
+        doc
+    }
+
+    #[tokio::test]
+    async fn test_resolve_document() {
+        let kernel = MockKernel::start(fixture_data_dir()).await.unwrap();
+        let backends: Vec<SharedBackend> = vec![
+            Arc::new(KernelBackend::new(kernel.client())),
+            Arc::new(FsBackend::new(kernel.profile()).index_file(None)),
+        ];
+        for backend in backends {
+            let kind = backend.kind();
+            let mut doc = rust_notes(&backend).await;
+            let resolver = RefResolver::new(backend);
+            let dangling = resolver.resolve_document(&mut doc).await.unwrap();
+            assert_eq!(
+                dangling,
+                HashSet::from(["20240101000000-missing".to_string()]),
+                "{:?}",
+                kind
+            );
+            let mut anchors = Vec::new();
+            walk(&doc, &mut |node, entering| {
+                if entering && node.block_ref_id().is_some() {
+                    anchors.push(node.text_mark_text_content.clone().unwrap_or_default());
+                }
+                WalkStatus::Continue
+            });
+            // 静态锚文本保持不变
+            assert_eq!(anchors, vec!["todo", "a removed block"], "{:?}", kind);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_caches_results() {
+        let server = StubServer::start().await.route(
+            API_SQL_QUERY,
+            json!([{"id": "20231026101149-10uol14", "type": "p", "content": "This is a test file."}]),
+        );
+        let resolver = RefResolver::new(Arc::new(KernelBackend::new(server.client())));
+        let ids = vec![
+            "20231026101149-10uol14".to_string(),
+            "20240101000000-missing".to_string(),
+        ];
+        let blocks = resolver.resolve(&ids).await.unwrap();
+        assert_eq!(
+            blocks[&ids[0]].as_ref().map(|b| b.content.as_str()),
+            Some("This is a test file.")
+        );
+        assert_eq!(blocks[&ids[1]], None);
+
+        // 已缓存的块(包括不存在的块)不再请求
+        assert_eq!(resolver.resolve(&ids).await.unwrap(), blocks);
+        assert_eq!(server.requests().len(), 1);
+        resolver.invalidate(&ids[0]);
+        resolver.resolve(&ids).await.unwrap();
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[1].body["stmt"],
+            "SELECT id, root_id, type, name, content, fcontent FROM blocks \
+             WHERE id IN ('20231026101149-10uol14') LIMIT 1"
+        );
+    }
+}
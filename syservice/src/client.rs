@@ -0,0 +1,119 @@
+use crate::config::Profile;
+use crate::domain::ApiResponse;
+use crate::error::{ApiError, ApiResult};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// 思源内核 HTTP 客户端.
+///
+/// 内部的 `reqwest::Client` 自带连接池, clone 的开销很小,
+/// 可以直接 clone 后传入异步任务.
+#[derive(Debug, Clone)]
+pub struct SiyuanClient {
+    profile: Arc<Profile>,
+    http: reqwest::Client,
+}
+
+impl SiyuanClient {
+    pub fn new(profile: Profile) -> anyhow::Result<Self> {
+        let http = reqwest::Client::builder()
+            .no_proxy()
+            .timeout(profile.timeout)
+            .connect_timeout(profile.connect_timeout)
+            .build()?;
+        Ok(Self {
+            profile: Arc::new(profile),
+            http,
+        })
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// 构造带鉴权信息的 POST 请求
+    pub fn post(&self, api: &str) -> reqwest::RequestBuilder {
+        let builder = self.http.post(self.profile.api_url(api));
+        if self.profile.token.is_empty() {
+            builder
+        } else {
+            builder.header("Authorization", format!("Token {}", self.profile.token))
+        }
+    }
+
+    /// 调用内核 API, 并将响应中的 `data` 解析为 `Resp`.
+    ///
+    /// 内核返回非 0 的 `code` 时返回 [`ApiError::Kernel`].
+    /// 对于没有返回值的接口, `Resp` 使用 `()` 即可.
+    pub async fn call<Req, Resp>(&self, api: &str, req: &Req) -> ApiResult<Resp>
+    where
+        Req: Serialize + ?Sized,
+        Resp: DeserializeOwned,
+    {
+        let http_err = |source| ApiError::Http {
+            api: api.to_string(),
+            source,
+        };
+        let response = self.post(api).json(req).send().await.map_err(http_err)?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ApiError::Status {
+                api: api.to_string(),
+                status,
+            });
+        }
+        let body = response.bytes().await.map_err(http_err)?;
+        parse_response(api, &body)
+    }
+}
+
+/// 解析内核响应体 `{"code": 0, "msg": "", "data": ...}`
+pub(crate) fn parse_response<Resp: DeserializeOwned>(api: &str, body: &[u8]) -> ApiResult<Resp> {
+    let decode_err = |source| ApiError::Decode {
+        api: api.to_string(),
+        source,
+    };
+    let response: ApiResponse<serde_json::Value> =
+        serde_json::from_slice(body).map_err(decode_err)?;
+    if response.code != 0 {
+        return Err(ApiError::Kernel {
+            api: api.to_string(),
+            code: response.code,
+            msg: response.msg,
+        });
+    }
+    serde_json::from_value(response.data.unwrap_or_default()).map_err(decode_err)
+}
+
+impl Default for SiyuanClient {
+    fn default() -> Self {
+        Self::new(Profile::default()).expect("Failed to build http client")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response() {
+        let body = br#"{"code":0,"msg":"","data":"20250523153354-3z19fz1"}"#;
+        let id: String = parse_response("/api/filetree/createDocWithMd", body).unwrap();
+        assert_eq!(id, "20250523153354-3z19fz1");
+
+        let body = br#"{"code":0,"msg":"","data":null}"#;
+        let _: () = parse_response("/api/filetree/removeDoc", body).unwrap();
+    }
+
+    #[test]
+    fn test_parse_kernel_error() {
+        let body = br#"{"code":-1,"msg":"notebook not found","data":null}"#;
+        let err = parse_response::<()>("/api/filetree/renameDoc", body).unwrap_err();
+        assert_eq!(err.kernel_code(), Some(-1));
+        assert!(err.to_string().contains("notebook not found"));
+
+        let err = parse_response::<String>("/api/filetree/getHPathByID", b"<html>").unwrap_err();
+        assert!(matches!(err, ApiError::Decode { .. }));
+    }
+}
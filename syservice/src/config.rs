@@ -0,0 +1,262 @@
+//! 思源内核连接配置
+//!
+//! 配置文件为 TOML 格式, 可以声明多个具名 profile, 启动时选择其中一个:
+//!
+//! ```toml
+//! default_profile = "work"
+//!
+//! [profiles.work]
+//! base_url = "http://127.0.0.1:6806"
+//! token = "xxxxxxxx"
+//! data_dir = "/Users/me/SiYuan/data"
+//! timeout_secs = 10
+//!
+//! [profiles.personal]
+//! base_url = "http://127.0.0.1:6807"
+//! data_dir = "/Users/me/Personal/data"
+//! ```
+//!
+//! 读取完配置文件后, 环境变量中的同名配置会覆盖 profile 中的值.
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// 指定配置文件路径
+pub const ENV_CONFIG: &str = "RSY_SCRIBE_CONFIG";
+/// 指定启动时使用的 profile
+pub const ENV_PROFILE: &str = "RSY_SCRIBE_PROFILE";
+pub const ENV_BASE_URL: &str = "SIYUAN_BASE_URL";
+pub const ENV_TOKEN: &str = "SIYUAN_TOKEN";
+pub const ENV_DATA_DIR: &str = "SIYUAN_DATA_DIR";
+pub const ENV_TIMEOUT_SECS: &str = "SIYUAN_TIMEOUT_SECS";
+
+pub const DEFAULT_PROFILE: &str = "default";
+const DEFAULT_BASE_URL: &str = "http://127.0.0.1:6806";
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 配置文件内容
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Config {
+    /// 未显式指定 profile 时使用的 profile 名称
+    #[serde(default)]
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileConfig>,
+}
+
+/// 配置文件中的单个 profile, 未填写的字段使用默认值
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProfileConfig {
+    pub base_url: Option<String>,
+    pub token: Option<String>,
+    /// 工作空间下的 data 目录
+    pub data_dir: Option<PathBuf>,
+    pub timeout_secs: Option<u64>,
+    pub connect_timeout_secs: Option<u64>,
+}
+
+/// 解析完成的连接配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+    /// 内核地址, 例如 `http://127.0.0.1:6806`
+    pub base_url: String,
+    /// API token, 为空时不发送 Authorization 请求头
+    pub token: String,
+    /// 工作空间下的 data 目录, `.sy` 文件都位于其中
+    pub data_dir: PathBuf,
+    /// 单次请求的超时时间
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_PROFILE.to_string(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            token: String::new(),
+            data_dir: PathBuf::new(),
+            timeout: DEFAULT_TIMEOUT,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+        }
+    }
+}
+
+impl Config {
+    /// 从默认位置加载配置. 配置文件不存在时返回空配置.
+    ///
+    /// 查找顺序: `$RSY_SCRIBE_CONFIG` > `$XDG_CONFIG_HOME/rsy-scribe/config.toml`
+    /// > `$HOME/.config/rsy-scribe/config.toml`
+    pub fn load() -> anyhow::Result<Config> {
+        if let Ok(path) = std::env::var(ENV_CONFIG) {
+            return Self::from_path(path);
+        }
+        match default_config_path() {
+            Some(path) if path.exists() => Self::from_path(path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> anyhow::Result<Config> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config: {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Failed to parse config: {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> anyhow::Result<Config> {
+        Ok(toml::from_str(text)?)
+    }
+
+    /// 当前可选的 profile 名称
+    pub fn profile_names(&self) -> impl Iterator<Item = &str> {
+        self.profiles.keys().map(String::as_str)
+    }
+
+    /// 选择 profile 并应用环境变量覆盖.
+    ///
+    /// 选择顺序: 参数 `name` > `$RSY_SCRIBE_PROFILE` > `default_profile`
+    /// > 名为 `default` 的 profile. 配置文件中没有任何 profile 时使用内置默认值.
+    pub fn profile(&self, name: Option<&str>) -> anyhow::Result<Profile> {
+        let env_name = std::env::var(ENV_PROFILE).ok();
+        let profile = self.select(name.or(env_name.as_deref()))?;
+        Ok(profile.with_overrides(|key| std::env::var(key).ok()))
+    }
+
+    /// 只按名称选择 profile, 不读取环境变量
+    pub fn select(&self, name: Option<&str>) -> anyhow::Result<Profile> {
+        let name = name
+            .or(self.default_profile.as_deref())
+            .unwrap_or(DEFAULT_PROFILE);
+        match self.profiles.get(name) {
+            Some(config) => Ok(config.to_profile(name)),
+            None if self.profiles.is_empty() && name == DEFAULT_PROFILE => Ok(Profile::default()),
+            None => Err(anyhow!(
+                "Unknown profile '{}', available: [{}]",
+                name,
+                self.profile_names().collect::<Vec<_>>().join(", ")
+            )),
+        }
+    }
+}
+
+impl ProfileConfig {
+    fn to_profile(&self, name: &str) -> Profile {
+        let default = Profile::default();
+        Profile {
+            name: name.to_string(),
+            base_url: self.base_url.clone().unwrap_or(default.base_url),
+            token: self.token.clone().unwrap_or(default.token),
+            data_dir: self.data_dir.clone().unwrap_or(default.data_dir),
+            timeout: self
+                .timeout_secs
+                .map(Duration::from_secs)
+                .unwrap_or(default.timeout),
+            connect_timeout: self
+                .connect_timeout_secs
+                .map(Duration::from_secs)
+                .unwrap_or(default.connect_timeout),
+        }
+    }
+}
+
+impl Profile {
+    /// 使用 `lookup` 读取覆盖值, 通常传入环境变量查找函数
+    pub fn with_overrides(mut self, lookup: impl Fn(&str) -> Option<String>) -> Profile {
+        if let Some(base_url) = lookup(ENV_BASE_URL) {
+            self.base_url = base_url;
+        }
+        if let Some(token) = lookup(ENV_TOKEN) {
+            self.token = token;
+        }
+        if let Some(data_dir) = lookup(ENV_DATA_DIR) {
+            self.data_dir = PathBuf::from(data_dir);
+        }
+        if let Some(secs) = lookup(ENV_TIMEOUT_SECS).and_then(|s| s.parse().ok()) {
+            self.timeout = Duration::from_secs(secs);
+        }
+        self
+    }
+
+    /// 拼接 API 完整地址
+    pub fn api_url(&self, api: &str) -> String {
+        format!("{}{}", self.base_url.trim_end_matches('/'), api)
+    }
+}
+
+fn default_config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("rsy-scribe").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const CONFIG: &str = r#"
+default_profile = "work"
+
+[profiles.work]
+base_url = "http://127.0.0.1:6806/"
+token = "work-token"
+data_dir = "/notes/work/data"
+timeout_secs = 5
+
+[profiles.personal]
+base_url = "http://127.0.0.1:6807"
+data_dir = "/notes/personal/data"
+"#;
+
+    #[test]
+    fn test_select_profile() {
+        let config = Config::parse(CONFIG).unwrap();
+
+        let work = config.select(None).unwrap();
+        assert_eq!(work.name, "work");
+        assert_eq!(work.token, "work-token");
+        assert_eq!(work.timeout, Duration::from_secs(5));
+        assert_eq!(
+            work.api_url("/api/query/sql"),
+            "http://127.0.0.1:6806/api/query/sql"
+        );
+
+        let personal = config.select(Some("personal")).unwrap();
+        assert_eq!(personal.data_dir, PathBuf::from("/notes/personal/data"));
+        assert_eq!(personal.token, "");
+        assert_eq!(personal.timeout, DEFAULT_TIMEOUT);
+
+        assert!(config.select(Some("missing")).is_err());
+    }
+
+    #[test]
+    fn test_empty_config_uses_default_profile() {
+        let config = Config::parse("").unwrap();
+        assert_eq!(config.select(None).unwrap(), Profile::default());
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let config = Config::parse(CONFIG).unwrap();
+        let env: HashMap<&str, &str> = HashMap::from([
+            (ENV_TOKEN, "env-token"),
+            (ENV_DATA_DIR, "/tmp/data"),
+            (ENV_TIMEOUT_SECS, "30"),
+        ]);
+        let profile = config
+            .select(Some("personal"))
+            .unwrap()
+            .with_overrides(|key| env.get(key).map(|v| v.to_string()));
+
+        assert_eq!(profile.base_url, "http://127.0.0.1:6807");
+        assert_eq!(profile.token, "env-token");
+        assert_eq!(profile.data_dir, PathBuf::from("/tmp/data"));
+        assert_eq!(profile.timeout, Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,209 @@
+//! 块的纯文本内容, 与内核写入 `blocks` 表的 `content`/`fcontent` 规则一致.
+//!
+//! 离线索引, 字数统计和预览都以此为准, 这样与内核搜索的结果能够对得上.
+use crate::lute::node::{Node, NodeType, TextMarks};
+use crate::lute::render::node_type;
+use crate::lute::walk::{walk, WalkStatus};
+
+/// 容器块, 内容由子块拼接而成
+fn is_container(node_type: NodeType) -> bool {
+    matches!(
+        node_type,
+        NodeType::NodeDocument
+            | NodeType::NodeBlockquote
+            | NodeType::NodeList
+            | NodeType::NodeListItem
+            | NodeType::NodeFootnotesDefBlock
+            | NodeType::NodeFootnotesDef
+            | NodeType::NodeSuperBlock
+    )
+}
+
+fn property<'a>(node: &'a Node, key: &str) -> &'a str {
+    node.properties
+        .as_ref()
+        .and_then(|props| props.get(key))
+        .map(String::as_str)
+        .unwrap_or_default()
+}
+
+fn data(node: &Node) -> &str {
+    node.data.as_deref().unwrap_or_default()
+}
+
+/// 块的 `content`: 文档为标题, 其余为整棵子树的纯文本
+pub fn content(node: &Node) -> String {
+    match node_type(node) {
+        NodeType::NodeDocument => property(node, "title").to_string(),
+        _ => static_content(node),
+    }
+}
+
+/// 块的 `fcontent`: 文档为标题, 容器块为第一个叶子块的纯文本, 叶子块为空
+pub fn fcontent(node: &Node) -> String {
+    match node_type(node) {
+        NodeType::NodeDocument => property(node, "title").to_string(),
+        kind if is_container(kind) => first_leaf_block(node)
+            .map(static_content)
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+/// 第一个非容器的子孙块
+fn first_leaf_block(node: &Node) -> Option<&Node> {
+    node.children.iter().find_map(|child| {
+        let kind = node_type(child);
+        if is_container(kind) {
+            first_leaf_block(child)
+        } else {
+            kind.is_block().then_some(child)
+        }
+    })
+}
+
+/// 节点的纯文本, 不含 Markdown 标记.
+///
+/// 容器块之间和表格单元格之间以一个空格分隔, 标签保留两侧的 `#`,
+/// 链接附带标题和地址, 行级备注附带备注内容, 以便都能被搜索到.
+pub fn static_content(node: &Node) -> String {
+    let mut text = String::new();
+    let mut last_space = false;
+    walk(node, &mut |node, entering| {
+        if !entering {
+            return WalkStatus::Continue;
+        }
+        let kind = node_type(node);
+        if is_container(kind) {
+            if !last_space && !text.is_empty() {
+                text.push(' ');
+                last_space = true;
+            }
+            return WalkStatus::Continue;
+        }
+        match kind {
+            NodeType::NodeTableCell => {
+                if !last_space && !text.is_empty() {
+                    text.push(' ');
+                    last_space = true;
+                }
+                return WalkStatus::Continue;
+            }
+            NodeType::NodeText
+            | NodeType::NodeLinkText
+            | NodeType::NodeCodeSpanContent
+            | NodeType::NodeInlineMathContent
+            | NodeType::NodeCodeBlockCode
+            | NodeType::NodeMathBlockContent
+            | NodeType::NodeHtmlBlock
+            | NodeType::NodeInlineHtml
+            | NodeType::NodeHtmlEntity
+            | NodeType::NodeBlockRefText
+            | NodeType::NodeBlockRefDynamicText
+            | NodeType::NodeFileAnnotationRefText
+            | NodeType::NodeEmojiUnicode => text.push_str(data(node)),
+            NodeType::NodeBackslashContent => {
+                text.push('\\');
+                text.push_str(data(node));
+            }
+            NodeType::NodeImage => {
+                // 图片取替代文本和标题, 不含地址
+                for child in &node.children {
+                    match node_type(child) {
+                        NodeType::NodeLinkText => {
+                            text.push_str(data(child));
+                            text.push(' ');
+                        }
+                        NodeType::NodeLinkTitle => text.push_str(data(child)),
+                        _ => {}
+                    }
+                }
+                last_space = false;
+                return WalkStatus::SkipChildren;
+            }
+            NodeType::NodeTextMark => {
+                text_mark_content(node, &mut text);
+                last_space = false;
+                return WalkStatus::SkipChildren;
+            }
+            _ => return WalkStatus::Continue,
+        }
+        last_space = false;
+        WalkStatus::Continue
+    });
+    text
+}
+
+fn text_mark_content(node: &Node, text: &mut String) {
+    let marks = node.text_marks();
+    let content = node.text_mark_text_content.as_deref().unwrap_or_default();
+    if marks.contains(TextMarks::INLINE_MATH) {
+        text.push_str(node.inline_math_content().unwrap_or_default());
+    } else if marks.contains(TextMarks::TAG) {
+        text.push('#');
+        text.push_str(content);
+        text.push('#');
+    } else {
+        text.push_str(content);
+    }
+    if let Some(title) = node.a_title().filter(|title| !title.is_empty()) {
+        text.push(' ');
+        text.push_str(title);
+    }
+    // 资源文件的地址没有检索意义
+    if let Some(href) = node
+        .a_href()
+        .filter(|href| !href.is_empty() && !href.starts_with("assets/"))
+    {
+        text.push(' ');
+        text.push_str(href);
+    }
+    if let Some(memo) = node.inline_memo_content().filter(|memo| !memo.is_empty()) {
+        text.push(' ');
+        text.push_str(memo);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lute::protyle::markdown_to_protyle;
+
+    #[test]
+    fn test_container_content() {
+        let doc = markdown_to_protyle(
+            "> - one **two**\n> - three #tag#\n\n| a | b |\n| - | - |\n| c | d |\n",
+        );
+        let quote = &doc.children[0];
+        assert_eq!(content(quote), "one two three #tag#");
+        assert_eq!(fcontent(quote), "one two");
+        let table = &doc.children[1];
+        assert_eq!(content(table), "a b c d");
+        assert_eq!(fcontent(table), "");
+    }
+
+    #[test]
+    fn test_text_mark_content() {
+        let doc = markdown_to_protyle(
+            "[site](https://example.com \"Example\") $x^2$ ![logo](assets/a.png \"Logo\") `a*b`\n",
+        );
+        assert_eq!(
+            content(&doc.children[0]),
+            "site Example https://example.com x^2 logo Logo a*b"
+        );
+    }
+
+    #[test]
+    fn test_document_content() {
+        let doc: Node = serde_json::from_value(serde_json::json!({
+            "ID": "20240301100000-aaaaaaa",
+            "Type": "NodeDocument",
+            "Properties": {"title": "Notes"},
+            "Children": [{"Type": "NodeParagraph", "Children": [{"Type": "NodeText", "Data": "x"}]}]
+        }))
+        .unwrap();
+        assert_eq!(content(&doc), "Notes");
+        assert_eq!(fcontent(&doc), "Notes");
+        assert_eq!(static_content(&doc), "x");
+    }
+}
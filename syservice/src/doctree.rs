@@ -0,0 +1,535 @@
+//! 笔记本与文档树.
+//!
+//! 读取 `data/<box>/.siyuan/conf.json` 与 `sort.json`, 遍历 `<id>.sy` 与同名子目录组成的层级,
+//! 按笔记本的排序方式排列文档并计算人类可读路径, 结果与桌面端文件树一致.
+//! 按名称排序时直接比较标题, 不按拼音排序; 离线时无法统计引用数, 按引用数排序时退化为按创建时间排序.
+use crate::backend::fs::{read_doc, read_notebooks, sorted_entries, NotebookEntry};
+use crate::domain::{DocFile, Notebook};
+use crate::lute::node::Node;
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+const SORT_FILE: &str = ".siyuan/sort.json";
+/// 工作空间全局配置, 相对 data 目录的上一级
+const GLOBAL_CONF: &str = "conf/conf.json";
+
+/// 文档排序方式, 取值与内核一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortMode {
+    NameAsc,
+    NameDesc,
+    UpdatedAsc,
+    UpdatedDesc,
+    /// 自然排序, 标题中的数字按数值比较
+    AlphanumAsc,
+    AlphanumDesc,
+    /// 拖拽排序, 顺序保存在 `sort.json` 中
+    #[default]
+    Custom,
+    RefCountAsc,
+    RefCountDesc,
+    CreatedAsc,
+    CreatedDesc,
+    SizeAsc,
+    SizeDesc,
+    SubDocCountAsc,
+    SubDocCountDesc,
+    /// 跟随全局文档树设置
+    FileTree,
+}
+
+impl SortMode {
+    pub fn from_code(code: i64) -> Option<SortMode> {
+        let mode = match code {
+            0 => SortMode::NameAsc,
+            1 => SortMode::NameDesc,
+            2 => SortMode::UpdatedAsc,
+            3 => SortMode::UpdatedDesc,
+            4 => SortMode::AlphanumAsc,
+            5 => SortMode::AlphanumDesc,
+            6 => SortMode::Custom,
+            7 => SortMode::RefCountAsc,
+            8 => SortMode::RefCountDesc,
+            9 => SortMode::CreatedAsc,
+            10 => SortMode::CreatedDesc,
+            11 => SortMode::SizeAsc,
+            12 => SortMode::SizeDesc,
+            13 => SortMode::SubDocCountAsc,
+            14 => SortMode::SubDocCountDesc,
+            15 => SortMode::FileTree,
+            _ => return None,
+        };
+        Some(mode)
+    }
+
+    fn compare(self, a: &DocNode, b: &DocNode) -> Ordering {
+        match self {
+            SortMode::NameAsc => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+            SortMode::NameDesc => b.title.to_lowercase().cmp(&a.title.to_lowercase()),
+            SortMode::UpdatedAsc => a.updated.cmp(&b.updated),
+            SortMode::UpdatedDesc => b.updated.cmp(&a.updated),
+            SortMode::AlphanumAsc => natural_cmp(&a.title, &b.title),
+            SortMode::AlphanumDesc => natural_cmp(&b.title, &a.title),
+            SortMode::Custom => a.sort.cmp(&b.sort),
+            SortMode::CreatedDesc | SortMode::RefCountDesc => b.id.cmp(&a.id),
+            SortMode::SizeAsc => a.size.cmp(&b.size),
+            SortMode::SizeDesc => b.size.cmp(&a.size),
+            SortMode::SubDocCountAsc => a.sub_doc_count.cmp(&b.sub_doc_count),
+            SortMode::SubDocCountDesc => b.sub_doc_count.cmp(&a.sub_doc_count),
+            SortMode::CreatedAsc | SortMode::RefCountAsc | SortMode::FileTree => Ordering::Equal,
+        }
+    }
+
+    /// 排序, 相同时按 id(即创建时间) 排列
+    pub fn sort(self, docs: &mut [DocNode]) {
+        docs.sort_by(|a, b| self.compare(a, b).then_with(|| a.id.cmp(&b.id)));
+    }
+}
+
+/// 文档树中的一个文档
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocNode {
+    pub id: String,
+    pub box_id: String,
+    /// 存储路径, 例如 `/20200812220555-lj3enxa/20210808180320-fqgskfj.sy`
+    pub path: String,
+    /// 人类可读路径, 例如 `/父文档/子文档`
+    pub hpath: String,
+    pub title: String,
+    /// 文档块的命名属性
+    pub name: String,
+    pub icon: String,
+    pub created: String,
+    pub updated: String,
+    /// `.sy` 文件大小
+    pub size: u64,
+    /// `sort.json` 中的排序值
+    pub sort: i64,
+    pub sub_doc_count: usize,
+    /// 未加载子文档时为空, 以 `sub_doc_count` 为准
+    pub children: Vec<DocNode>,
+}
+
+impl DocNode {
+    pub fn to_doc_file(&self) -> DocFile {
+        DocFile {
+            id: self.id.clone(),
+            // 与内核一致: `name` 为标题加 `.sy`, `name1` 为命名
+            name: format!("{}.sy", self.title),
+            name1: self.name.clone(),
+            path: self.path.clone(),
+            icon: self.icon.clone(),
+            size: self.size,
+            sort: self.sort as i32,
+            sub_file_count: self.sub_doc_count as i32,
+            ..DocFile::default()
+        }
+    }
+
+    /// 深度优先查找
+    pub fn find(&self, id: &str) -> Option<&DocNode> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.find(id))
+    }
+}
+
+/// 一个笔记本及其文档树
+#[derive(Debug, Clone)]
+pub struct NotebookTree {
+    pub notebook: Notebook,
+    /// 实际生效的排序方式, 不会是 [`SortMode::FileTree`]
+    pub sort_mode: SortMode,
+    /// 顶层文档, 已关闭的笔记本为空
+    pub docs: Vec<DocNode>,
+}
+
+impl NotebookTree {
+    pub fn find(&self, id: &str) -> Option<&DocNode> {
+        self.docs.iter().find_map(|doc| doc.find(id))
+    }
+}
+
+/// 工作空间中全部笔记本的文档树
+#[derive(Debug, Clone, Default)]
+pub struct DocTree {
+    pub notebooks: Vec<NotebookTree>,
+}
+
+impl DocTree {
+    /// 加载 `data_dir` 下的全部笔记本, 笔记本按自定义顺序排列
+    pub fn load(data_dir: &Path) -> Result<DocTree> {
+        let default_mode = global_sort_mode(data_dir);
+        let mut notebooks = Vec::new();
+        for entry in read_notebooks(data_dir)? {
+            let loader = Loader::new(data_dir, &entry, default_mode);
+            let docs = match entry.conf.closed {
+                true => Vec::new(),
+                false => loader.load(&data_dir.join(&entry.id), "", "", None)?,
+            };
+            notebooks.push(NotebookTree {
+                notebook: entry.to_notebook(),
+                sort_mode: loader.mode,
+                docs,
+            });
+        }
+        notebooks.sort_by(|a, b| {
+            a.notebook
+                .sort
+                .cmp(&b.notebook.sort)
+                .then_with(|| a.notebook.id.cmp(&b.notebook.id))
+        });
+        Ok(DocTree { notebooks })
+    }
+
+    pub fn notebook(&self, box_id: &str) -> Option<&NotebookTree> {
+        self.notebooks
+            .iter()
+            .find(|tree| tree.notebook.id == box_id)
+    }
+
+    pub fn find(&self, id: &str) -> Option<&DocNode> {
+        self.notebooks.iter().find_map(|tree| tree.find(id))
+    }
+}
+
+/// 只读取 `path` 下的一层文档, `path` 为 `/` 时读取顶层文档
+pub fn list_children(data_dir: &Path, box_id: &str, path: &str) -> Result<Vec<DocNode>> {
+    let entry = read_notebooks(data_dir)?
+        .into_iter()
+        .find(|entry| entry.id == box_id)
+        .ok_or_else(|| anyhow!("notebook [{}] not found", box_id))?;
+    let loader = Loader::new(data_dir, &entry, global_sort_mode(data_dir));
+    let box_dir = data_dir.join(box_id);
+    let rel = path.trim_end_matches(".sy").trim_end_matches('/');
+    // 逐级读取祖先文档的标题以计算 hpath
+    let mut hpath = String::new();
+    let mut ancestor = String::new();
+    for id in rel.split('/').filter(|id| !id.is_empty()) {
+        ancestor = format!("{}/{}", ancestor, id);
+        let title =
+            match read_doc(&box_dir.join(format!("{}.sy", ancestor.trim_start_matches('/')))) {
+                Ok(node) => property(&node, "title").to_string(),
+                Err(err) => {
+                    log::warn!("Skipping unreadable document: {:#}", err);
+                    id.to_string()
+                }
+            };
+        hpath = format!("{}/{}", hpath, title);
+    }
+    let dir = box_dir.join(rel.trim_start_matches('/'));
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    loader.load(&dir, rel, &hpath, Some(0))
+}
+
+/// `conf/conf.json` 中的 `fileTree.sort`, 缺省为自定义排序
+fn global_sort_mode(data_dir: &Path) -> SortMode {
+    let Some(workspace) = data_dir.parent() else {
+        return SortMode::default();
+    };
+    fs::read(workspace.join(GLOBAL_CONF))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok())
+        .and_then(|conf| conf.pointer("/fileTree/sort").and_then(Value::as_i64))
+        .and_then(SortMode::from_code)
+        .filter(|mode| *mode != SortMode::FileTree)
+        .unwrap_or_default()
+}
+
+fn property<'a>(node: &'a Node, key: &str) -> &'a str {
+    node.properties
+        .as_ref()
+        .and_then(|props| props.get(key))
+        .map(String::as_str)
+        .unwrap_or_default()
+}
+
+fn count_docs(dir: &Path) -> usize {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_name().to_string_lossy().ends_with(".sy"))
+                .count()
+        })
+        .unwrap_or(0)
+}
+
+struct Loader {
+    box_id: String,
+    mode: SortMode,
+    /// 文档 id -> 排序值
+    sort_map: HashMap<String, i64>,
+}
+
+impl Loader {
+    fn new(data_dir: &Path, entry: &NotebookEntry, default_mode: SortMode) -> Self {
+        let mode = entry
+            .conf
+            .extra
+            .get("sortMode")
+            .and_then(Value::as_i64)
+            .and_then(SortMode::from_code)
+            .filter(|mode| *mode != SortMode::FileTree)
+            .unwrap_or(default_mode);
+        let sort_map = fs::read(data_dir.join(&entry.id).join(SORT_FILE))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        Self {
+            box_id: entry.id.clone(),
+            mode,
+            sort_map,
+        }
+    }
+
+    /// 读取 `dir` 下的文档, `depth` 为继续向下读取的层数, `None` 表示读取全部
+    fn load(
+        &self,
+        dir: &Path,
+        rel: &str,
+        hpath: &str,
+        depth: Option<usize>,
+    ) -> Result<Vec<DocNode>> {
+        let mut docs = Vec::new();
+        for (name, path) in sorted_entries(dir)? {
+            let Some(id) = name.strip_suffix(".sy") else {
+                continue;
+            };
+            if !path.is_file() {
+                continue;
+            }
+            let node = match read_doc(&path) {
+                Ok(node) => node,
+                Err(err) => {
+                    // 写入到一半或已损坏的文档不影响同级文档的显示
+                    log::warn!("Skipping unreadable document: {:#}", err);
+                    continue;
+                }
+            };
+            let title = property(&node, "title");
+            let created = id.get(..14).unwrap_or_default().to_string();
+            let updated = match property(&node, "updated") {
+                "" => created.clone(),
+                updated => updated.to_string(),
+            };
+            let sub_dir = dir.join(id);
+            let mut doc = DocNode {
+                id: id.to_string(),
+                box_id: self.box_id.clone(),
+                path: format!("{}/{}", rel, name),
+                hpath: format!("{}/{}", hpath, title),
+                title: title.to_string(),
+                name: property(&node, "name").to_string(),
+                icon: property(&node, "icon").to_string(),
+                created,
+                updated,
+                size: fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0),
+                sort: self.sort_map.get(id).copied().unwrap_or(0),
+                sub_doc_count: count_docs(&sub_dir),
+                children: Vec::new(),
+            };
+            if doc.sub_doc_count > 0 && depth != Some(0) {
+                let sub_rel = format!("{}/{}", rel, id);
+                doc.children =
+                    self.load(&sub_dir, &sub_rel, &doc.hpath, depth.map(|depth| depth - 1))?;
+            }
+            docs.push(doc);
+        }
+        self.mode.sort(&mut docs);
+        Ok(docs)
+    }
+}
+
+/// 自然排序: 连续的数字按数值比较, 其余部分忽略大小写比较
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = take_number(&mut a);
+                let y = take_number(&mut b);
+                let ordering = x.len().cmp(&y.len()).then_with(|| x.cmp(&y));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+/// 读取连续的数字并去掉前导 0
+fn take_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut number = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        number.push(c);
+    }
+    number.trim_start_matches('0').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lute::node::rand_str;
+    use crate::mock::fixture_data_dir;
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    const BOX: &str = "20210808180117-czj9bvb";
+
+    fn titles(docs: &[DocNode]) -> Vec<&str> {
+        docs.iter().map(|doc| doc.title.as_str()).collect()
+    }
+
+    /// 在临时目录中创建只有一个笔记本的工作空间, 返回 data 目录
+    fn workspace(sort_mode: i64, docs: &[(&str, &str)], sort: Value) -> PathBuf {
+        let data_dir = std::env::temp_dir()
+            .join(format!("rsy-doctree-{}", rand_str(8)))
+            .join("data");
+        let box_dir = data_dir.join(BOX);
+        fs::create_dir_all(box_dir.join(".siyuan")).unwrap();
+        let conf = json!({"name": "Temp", "sortMode": sort_mode});
+        fs::write(box_dir.join(".siyuan/conf.json"), conf.to_string()).unwrap();
+        fs::write(box_dir.join(SORT_FILE), sort.to_string()).unwrap();
+        for (path, title) in docs {
+            let id = path.rsplit('/').next().unwrap();
+            let doc = json!({
+                "ID": id,
+                "Type": "NodeDocument",
+                "Properties": {"id": id, "title": title},
+            });
+            let file = box_dir.join(format!("{}.sy", path));
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, doc.to_string()).unwrap();
+        }
+        data_dir
+    }
+
+    #[test]
+    fn test_load_fixture_tree() {
+        let tree = DocTree::load(&fixture_data_dir()).unwrap();
+        let notebook = tree.notebook(BOX).unwrap();
+        assert_eq!(notebook.notebook.name, "Fixture");
+        assert_eq!(notebook.sort_mode, SortMode::Custom);
+        assert_eq!(titles(&notebook.docs), vec!["Draft", "Inbox"]);
+        let file = notebook.docs[0].to_doc_file();
+        assert_eq!((file.name.as_str(), file.title()), ("Draft.sy", "Draft"));
+
+        let doc = tree.find("20240107160843-8f02mqs").unwrap();
+        assert_eq!(doc.hpath, "/Draft/Rust notes");
+        assert_eq!(
+            doc.path,
+            "/20230620165438-1pqr39r/20240107160843-8f02mqs.sy"
+        );
+        assert_eq!(notebook.docs[0].sub_doc_count, 1);
+        assert_eq!(notebook.docs[1].icon, "1f4e5");
+    }
+
+    fn copy_dir(from: &Path, to: &Path) {
+        fs::create_dir_all(to).unwrap();
+        for entry in fs::read_dir(from).unwrap() {
+            let path = entry.unwrap().path();
+            let target = to.join(path.file_name().unwrap());
+            if path.is_dir() {
+                copy_dir(&path, &target);
+            } else {
+                fs::copy(&path, &target).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_skip_unreadable_documents() {
+        let workspace = std::env::temp_dir().join(format!("rsy-doctree-{}", rand_str(8)));
+        let data_dir = workspace.join("data");
+        copy_dir(&fixture_data_dir(), &data_dir);
+        let box_dir = data_dir.join(BOX);
+        fs::write(box_dir.join("20240301100000-aaaaaaa.sy"), "").unwrap();
+        // 祖先文档损坏时以 ID 代替标题
+        fs::write(box_dir.join("20230620165438-1pqr39r.sy"), "").unwrap();
+
+        let docs = list_children(&data_dir, BOX, "/").unwrap();
+        assert_eq!(titles(&docs), vec!["Inbox"]);
+        let children = list_children(&data_dir, BOX, "/20230620165438-1pqr39r.sy").unwrap();
+        assert_eq!(titles(&children), vec!["Rust notes"]);
+        assert_eq!(children[0].hpath, "/20230620165438-1pqr39r/Rust notes");
+        let tree = DocTree::load(&data_dir).unwrap();
+        assert_eq!(titles(&tree.notebook(BOX).unwrap().docs), vec!["Inbox"]);
+        fs::remove_dir_all(workspace).unwrap();
+    }
+
+    #[test]
+    fn test_sort_modes() {
+        let docs = [
+            ("20240101000000-aaaaaaa", "note 10"),
+            ("20240102000000-bbbbbbb", "Note 9"),
+            ("20240103000000-ccccccc", "note 1"),
+            ("20240103000000-ccccccc/20240104000000-ddddddd", "child"),
+        ];
+        let sort = json!({"20240101000000-aaaaaaa": 2, "20240102000000-bbbbbbb": 0, "20240103000000-ccccccc": 1});
+
+        let data_dir = workspace(6, &docs, sort.clone());
+        let tree = DocTree::load(&data_dir).unwrap();
+        assert_eq!(
+            titles(&tree.notebooks[0].docs),
+            vec!["Note 9", "note 1", "note 10"]
+        );
+        assert_eq!(
+            tree.find("20240104000000-ddddddd").unwrap().hpath,
+            "/note 1/child"
+        );
+        fs::remove_dir_all(data_dir.parent().unwrap()).unwrap();
+
+        let data_dir = workspace(4, &docs, sort.clone());
+        let tree = DocTree::load(&data_dir).unwrap();
+        assert_eq!(
+            titles(&tree.notebooks[0].docs),
+            vec!["note 1", "Note 9", "note 10"]
+        );
+        fs::remove_dir_all(data_dir.parent().unwrap()).unwrap();
+
+        // 跟随全局设置
+        let data_dir = workspace(15, &docs, sort);
+        let conf_dir = data_dir.parent().unwrap().join("conf");
+        fs::create_dir_all(&conf_dir).unwrap();
+        fs::write(conf_dir.join("conf.json"), r#"{"fileTree": {"sort": 10}}"#).unwrap();
+        let tree = DocTree::load(&data_dir).unwrap();
+        assert_eq!(tree.notebooks[0].sort_mode, SortMode::CreatedDesc);
+        assert_eq!(
+            titles(&tree.notebooks[0].docs),
+            vec!["note 1", "Note 9", "note 10"]
+        );
+
+        let children = list_children(&data_dir, BOX, "/20240103000000-ccccccc.sy").unwrap();
+        assert_eq!(children[0].hpath, "/note 1/child");
+        fs::remove_dir_all(data_dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        assert_eq!(natural_cmp("a2", "a10"), Ordering::Less);
+        assert_eq!(natural_cmp("a010", "a9"), Ordering::Greater);
+        assert_eq!(natural_cmp("B", "a"), Ordering::Greater);
+        assert_eq!(natural_cmp("第2章", "第10章"), Ordering::Less);
+        assert_eq!(natural_cmp("abc", "abc"), Ordering::Equal);
+    }
+}
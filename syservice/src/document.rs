@@ -1,58 +1,32 @@
-use anyhow::{anyhow, Context};
+use anyhow::anyhow;
 
 use super::domain::*;
 use super::*;
-use std::collections::HashMap;
+use crate::client::SiyuanClient;
+use crate::sql::Query;
 use serde_json::json;
 
-async fn create_doc_with_md(
-    notebook: String,
-    path: String,
-    markdown: String,
-) -> anyhow::Result<()> {
-    let url = "/api/filetree/createDocWithMd";
+pub async fn search_doc_with_title(
+    client: &SiyuanClient,
+    title: String,
+) -> anyhow::Result<SyResponse, anyhow::Error> {
+    let sql = Query::blocks()
+        .block_type(SyBlockType::Document)
+        .contains("content", &title)
+        .limit(20)
+        .build();
+    let body = json!({ "stmt": sql });
 
-    let mut map = HashMap::new();
-    map.insert("notebook", notebook);
-    map.insert("path", path);
-    map.insert("markdown", markdown);
-
-    let client = reqwest::Client::new();
-    let response = client.post(url).json(&map).send().await?;
-
-    if response.status().is_success() {
-        let body = response.text().await?;
-        println!("Response body: {}", body);
-    }
-    Ok(())
-}
-
-pub async fn search_doc_with_title(title: String) -> anyhow::Result<SyResponse, anyhow::Error> {
-    let sql = format!(
-        "SELECT * FROM blocks WHERE content LIKE '%{}%' and type='d' LIMIT 20",
-        title
-    );
-
-    let mut map = HashMap::new();
-    map.insert("stmt", sql);
-    let body = json!(map);
-
-    let client = reqwest::Client::builder()
-        .no_proxy()
-        .build()?;
-    
     let response = client
-        .post(format!("{}{}", SIYUAN_BASE, API_SQL_QUERY))
+        .post(API_SQL_QUERY)
         .header("Content-Type", "application/json")
-        .header("Authorization", "Token ".to_owned() + API_TOKEN)
         .json(&body)
         .send()
         .await
-        .map_err(|e| anyhow!("Failed to send request to {}: {}", API_SQL_QUERY , e))?;
+        .map_err(|e| anyhow!("Failed to send request to {}: {}", API_SQL_QUERY, e))?;
 
     if !response.status().is_success() {
-        println!("API returned {}: {}",
-                 response.status(), response.url());
+        println!("API returned {}: {}", response.status(), response.url());
         return Err(anyhow!("API returned"));
     }
 
@@ -65,16 +39,33 @@ pub async fn search_doc_with_title(title: String) -> anyhow::Result<SyResponse,
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::mock::{fixture_data_dir, MockKernel};
+    use crate::stub::StubServer;
+
+    #[tokio::test]
+    async fn test_search_doc_with_title_escapes_input() {
+        let server = StubServer::start().await.route(API_SQL_QUERY, json!([]));
+        let resp = search_doc_with_title(&server.client(), "it's 100%".to_string())
+            .await
+            .unwrap();
+        assert!(resp.data.is_empty());
+        assert_eq!(
+            server.requests()[0].body["stmt"],
+            "SELECT * FROM blocks WHERE type = 'd' AND content LIKE '%it''s 100\\%%' ESCAPE '\\' LIMIT 20"
+        );
+    }
 
     #[tokio::test]
     async fn test_get_document_list_by_title() {
-        let result = search_doc_with_title(String::from("rust")).await;
+        let kernel = MockKernel::start(fixture_data_dir()).await.unwrap();
+        let client = kernel.client();
+        let result = search_doc_with_title(&client, String::from("rust")).await;
         match result {
             Ok(resp) => {
                 assert_eq!(resp.code, 0);
-                assert!(resp.data.len() > 0);
+                assert!(!resp.data.is_empty());
             }
-            Err(ref resp) => {
+            Err(_) => {
                 panic!("Search failed: {:?}", result.err());
             }
         }
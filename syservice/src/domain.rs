@@ -1,4 +1,13 @@
 use serde::{Deserialize, Serialize};
+
+/// 内核 API 的通用响应结构
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiResponse<T> {
+    pub code: i32,
+    pub msg: String,
+    pub data: Option<T>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SyResponse{
     pub code: i32,
@@ -36,6 +45,7 @@ pub struct SyBlock {
     pub updated: String,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SyBlockType{
     Document,
     // H1-H6
@@ -60,3 +70,323 @@ pub enum SyBlockType{
     // 分割线
     Tb,
 }
+
+impl SyBlockType {
+    /// `blocks.type` 列中的取值
+    pub fn code(&self) -> &'static str {
+        match self {
+            SyBlockType::Document => "d",
+            SyBlockType::Title => "h",
+            SyBlockType::List => "l",
+            SyBlockType::ListItem => "i",
+            SyBlockType::Quote => "b",
+            SyBlockType::SuperBlock => "s",
+            SyBlockType::Paragraph => "p",
+            SyBlockType::Code => "c",
+            SyBlockType::Method => "m",
+            SyBlockType::Table => "t",
+            SyBlockType::DataBlock => "av",
+            SyBlockType::QueryEmbed => "query_embed",
+            SyBlockType::Video => "video",
+            SyBlockType::Audio => "audio",
+            SyBlockType::Widget => "widget",
+            SyBlockType::IFrame => "iframe",
+            SyBlockType::Html => "html",
+            SyBlockType::Tb => "tb",
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// filetree
+// ---------------------------------------------------------------------------
+
+/// `/api/filetree/createDocWithMd`, 返回新文档的 id
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateDocWithMd {
+    pub notebook: String,
+    /// 人类可读路径, 例如 `/foo/bar`
+    pub path: String,
+    pub markdown: String,
+}
+
+/// `/api/filetree/renameDoc`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RenameDoc {
+    pub notebook: String,
+    /// 文档存储路径, 例如 `/20210902210113-0avi12f.sy`
+    pub path: String,
+    pub title: String,
+}
+
+/// `/api/filetree/removeDoc`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RemoveDoc {
+    pub notebook: String,
+    pub path: String,
+}
+
+/// `/api/filetree/moveDocs`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveDocs {
+    /// 待移动文档的存储路径, 格式为 `<box>/<path>`
+    pub from_paths: Vec<String>,
+    pub to_notebook: String,
+    /// 目标父文档的存储路径, 移动到笔记本根目录时为 `/`
+    pub to_path: String,
+}
+
+/// `/api/filetree/getHPathByPath`, 返回人类可读路径
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetHPathByPath {
+    pub notebook: String,
+    pub path: String,
+}
+
+/// `/api/filetree/getHPathByID`, 返回人类可读路径
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetHPathById {
+    pub id: String,
+}
+
+/// `/api/filetree/getIDsByHPath`, 返回匹配的文档 id 列表
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetIdsByHPath {
+    pub notebook: String,
+    /// 人类可读路径
+    pub path: String,
+}
+
+/// `/api/filetree/listDocsByPath`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ListDocsByPath {
+    pub notebook: String,
+    /// 父文档的存储路径, 笔记本根目录为 `/`
+    pub path: String,
+    /// 排序方式, 为空时使用笔记本配置
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_list_count: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ListDocsByPathResponse {
+    #[serde(rename = "box")]
+    pub box_id: String,
+    pub path: String,
+    #[serde(default)]
+    pub files: Vec<DocFile>,
+}
+
+/// 文件树中的一个文档
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DocFile {
+    pub id: String,
+    /// 文档标题加上 `.sy`, 例如 `Draft.sy`, 标题见 [`DocFile::title`]
+    pub name: String,
+    /// 文档块的命名属性, 通常为空
+    pub name1: String,
+    pub path: String,
+    pub icon: String,
+    pub alias: String,
+    pub memo: String,
+    pub bookmark: String,
+    /// 引用计数
+    pub count: i32,
+    pub size: u64,
+    pub h_size: String,
+    pub mtime: i64,
+    pub ctime: i64,
+    pub h_mtime: String,
+    pub h_ctime: String,
+    pub sort: i32,
+    pub sub_file_count: i32,
+    pub hidden: bool,
+}
+
+impl DocFile {
+    /// 文档标题, 即去掉 `.sy` 后缀的 `name`
+    pub fn title(&self) -> &str {
+        self.name.strip_suffix(".sy").unwrap_or(&self.name)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// block
+// ---------------------------------------------------------------------------
+
+/// 块内容, 序列化为 `{"dataType": "markdown", "data": "..."}`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "dataType", content = "data", rename_all = "lowercase")]
+pub enum BlockData {
+    Markdown(String),
+    Dom(String),
+}
+
+/// `/api/block/insertBlock`, `next_id`/`previous_id`/`parent_id` 至少需要一个
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InsertBlock {
+    #[serde(flatten)]
+    pub data: BlockData,
+    #[serde(rename = "nextID", skip_serializing_if = "Option::is_none")]
+    pub next_id: Option<String>,
+    #[serde(rename = "previousID", skip_serializing_if = "Option::is_none")]
+    pub previous_id: Option<String>,
+    #[serde(rename = "parentID", skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+}
+
+/// `/api/block/prependBlock` 与 `/api/block/appendBlock`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChildBlock {
+    #[serde(flatten)]
+    pub data: BlockData,
+    #[serde(rename = "parentID")]
+    pub parent_id: String,
+}
+
+/// `/api/block/updateBlock`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UpdateBlock {
+    #[serde(flatten)]
+    pub data: BlockData,
+    pub id: String,
+}
+
+/// `/api/block/moveBlock`, `previous_id` 为空时移动为 `parent_id` 的第一个子块
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MoveBlock {
+    pub id: String,
+    #[serde(rename = "previousID", skip_serializing_if = "Option::is_none")]
+    pub previous_id: Option<String>,
+    #[serde(rename = "parentID", skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+}
+
+/// 只需要块 id 的请求, 例如 `/api/block/deleteBlock`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BlockId {
+    pub id: String,
+}
+
+/// `/api/block/getBlockKramdown` 的返回值
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BlockKramdown {
+    pub id: String,
+    pub kramdown: String,
+}
+
+/// 解析块引用时需要的块信息, 对应 `blocks` 表中的部分列
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct BlockInfo {
+    pub id: String,
+    pub root_id: String,
+    /// 块类型, 即 [`SyBlockType::code`]
+    #[serde(rename = "type")]
+    pub block_type: String,
+    /// 命名
+    pub name: String,
+    pub content: String,
+    /// 容器块第一个子块的内容
+    pub fcontent: String,
+}
+
+/// 一次块操作产生的事务
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Transaction {
+    #[serde(default)]
+    pub do_operations: Vec<Operation>,
+    #[serde(default)]
+    pub undo_operations: Option<Vec<Operation>>,
+}
+
+/// 事务中的单个操作
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Operation {
+    pub action: OperationAction,
+    /// 操作后的块内容, 通常为 DOM 字符串
+    #[serde(default)]
+    pub data: serde_json::Value,
+    #[serde(default)]
+    pub id: String,
+    #[serde(rename = "parentID", default)]
+    pub parent_id: String,
+    #[serde(rename = "previousID", default)]
+    pub previous_id: String,
+    #[serde(rename = "nextID", default)]
+    pub next_id: String,
+    #[serde(default)]
+    pub ret_data: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum OperationAction {
+    Insert,
+    PrependInsert,
+    AppendInsert,
+    Update,
+    Delete,
+    Move,
+    /// 当前版本未建模的操作
+    #[serde(other)]
+    Unknown,
+}
+
+// ---------------------------------------------------------------------------
+// notebook
+// ---------------------------------------------------------------------------
+
+/// 笔记本
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Notebook {
+    /// 笔记本 id, 即 `SyBlock::box_id`
+    pub id: String,
+    pub name: String,
+    pub icon: String,
+    pub sort: i32,
+    pub sort_mode: i32,
+    pub closed: bool,
+}
+
+/// 笔记本配置, 对应 `data/<box>/.siyuan/conf.json`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NotebookConf {
+    pub name: String,
+    #[serde(default)]
+    pub closed: bool,
+    #[serde(default)]
+    pub ref_create_save_path: String,
+    #[serde(default)]
+    pub doc_create_save_path: String,
+    #[serde(default)]
+    pub daily_note_save_path: String,
+    #[serde(default)]
+    pub daily_note_template_path: String,
+    /// 未建模的配置项, 调用 setNotebookConf 时原样回传
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Notebooks {
+    pub notebooks: Vec<Notebook>,
+}
+
+/// `/api/notebook/getNotebookConf` 的返回值
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NotebookConfResponse {
+    #[serde(rename = "box")]
+    pub box_id: String,
+    pub name: String,
+    pub conf: NotebookConf,
+}
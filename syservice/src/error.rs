@@ -0,0 +1,52 @@
+use thiserror::Error;
+
+/// 调用内核 API 时的错误
+#[derive(Debug, Error)]
+pub enum ApiError {
+    /// 请求未能发送或未收到响应
+    #[error("Failed to send request to {api}: {source}")]
+    Http {
+        api: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    /// HTTP 状态码非 2xx
+    #[error("{api} returned HTTP {status}")]
+    Status {
+        api: String,
+        status: reqwest::StatusCode,
+    },
+    /// 内核返回了非 0 的 `code`
+    #[error("{api} returned code {code}: {msg}")]
+    Kernel { api: String, code: i32, msg: String },
+    /// 响应体无法解析为预期的结构
+    #[error("Failed to parse response of {api}: {source}")]
+    Decode {
+        api: String,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+pub type ApiResult<T> = Result<T, ApiError>;
+
+impl ApiError {
+    /// 内核返回的错误码, 非内核错误时为 `None`
+    pub fn kernel_code(&self) -> Option<i32> {
+        match self {
+            ApiError::Kernel { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+}
+
+/// 写回文档时磁盘上的版本比加载时更新, 说明文档已被其他程序修改
+#[derive(Debug, Error)]
+#[error("{path} was updated at {on_disk} after it was loaded at {loaded}")]
+pub struct StaleDocument {
+    pub path: std::path::PathBuf,
+    /// 加载时文档的 `updated` 属性
+    pub loaded: String,
+    /// 磁盘上文档的 `updated` 属性
+    pub on_disk: String,
+}
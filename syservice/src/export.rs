@@ -0,0 +1,232 @@
+//! 导出为 HTML, 方便与不使用思源的人分享笔记.
+//!
+//! 导出笔记本时每个文档一个页面, 文件名为文档 ID, 另有按文档树生成的导航页 `index.html`.
+//! 块引用指向被引用块所在页面的锚点, 引用笔记本之外的块时链接失效.
+use crate::backend::Backend;
+use crate::domain::DocFile;
+use crate::lute::node::Node;
+use crate::lute::render::{escape_html, render_html, HtmlOptions};
+use crate::lute::walk::{walk, WalkStatus};
+use anyhow::{Context, Result};
+use futures_util::future::BoxFuture;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 导航页文件名
+pub const INDEX_PAGE: &str = "index.html";
+
+/// 导航中的一个文档
+struct NavItem {
+    doc: DocFile,
+    children: Vec<NavItem>,
+}
+
+/// 文档的标题, 缺省为文档 ID
+fn doc_title(node: &Node) -> &str {
+    node.properties
+        .as_ref()
+        .and_then(|props| props.get("title"))
+        .or(node.id.as_ref())
+        .map(String::as_str)
+        .unwrap_or_default()
+}
+
+fn page_name(id: &str) -> String {
+    format!("{id}.html")
+}
+
+/// 完整的 HTML 页面, `nav` 为正文前的导航
+fn page(title: &str, nav: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\" />\n<title>{title}</title>\n</head>\n\
+         <body>\n{nav}<h1>{title}</h1>\n{body}</body>\n</html>\n",
+        title = escape_html(title),
+    )
+}
+
+/// 将单个文档渲染为完整的 HTML 页面, 块引用均视为文档内的锚点
+pub fn document_html(doc: &Node) -> String {
+    page(
+        doc_title(doc),
+        "",
+        &render_html(doc, &HtmlOptions::default()),
+    )
+}
+
+/// 将笔记本导出到 `out_dir`, 返回写入的文件, 导航页在最前
+pub async fn export_notebook(
+    backend: &dyn Backend,
+    notebook: &str,
+    out_dir: &Path,
+) -> Result<Vec<PathBuf>> {
+    let name = backend
+        .ls_notebooks()
+        .await?
+        .into_iter()
+        .find(|box_| box_.id == notebook)
+        .map_or_else(|| notebook.to_string(), |box_| box_.name);
+    let nav = list_tree(backend, notebook, "/".to_string()).await?;
+
+    let mut docs = Vec::new();
+    let mut pending: Vec<&NavItem> = nav.iter().collect();
+    while let Some(item) = pending.pop() {
+        let file_path = format!("{}{}", notebook, item.doc.path);
+        docs.push(backend.load_document(&file_path).await?);
+        pending.extend(&item.children);
+    }
+
+    // 块 ID -> 所在页面
+    let mut block_pages = HashMap::new();
+    for doc in &docs {
+        let page = page_name(doc.id.as_deref().unwrap_or_default());
+        walk(doc, &mut |node, entering| {
+            if let Some(id) = node.id.as_ref().filter(|_| entering) {
+                block_pages.insert(id.clone(), page.clone());
+            }
+            WalkStatus::Continue
+        });
+    }
+
+    fs::create_dir_all(out_dir)
+        .with_context(|| format!("Failed to create {}", out_dir.display()))?;
+    let mut written = Vec::with_capacity(docs.len() + 1);
+    let index = out_dir.join(INDEX_PAGE);
+    let mut index_body = String::new();
+    nav_list(&nav, &mut index_body);
+    fs::write(&index, page(&name, "", &index_body))?;
+    written.push(index);
+
+    let back = format!(
+        "<nav><a href=\"{INDEX_PAGE}\">{}</a></nav>\n",
+        escape_html(&name)
+    );
+    let mut options = HtmlOptions {
+        block_pages,
+        page: String::new(),
+    };
+    for doc in &docs {
+        options.page = page_name(doc.id.as_deref().unwrap_or_default());
+        let path = out_dir.join(&options.page);
+        fs::write(
+            &path,
+            page(doc_title(doc), &back, &render_html(doc, &options)),
+        )
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// 递归列出 `path` 下的文档
+fn list_tree<'a>(
+    backend: &'a dyn Backend,
+    notebook: &'a str,
+    path: String,
+) -> BoxFuture<'a, Result<Vec<NavItem>>> {
+    Box::pin(async move {
+        let mut items = Vec::new();
+        for doc in backend.list_docs(notebook, &path).await? {
+            let children = match doc.sub_file_count {
+                0 => Vec::new(),
+                _ => list_tree(backend, notebook, doc.path.clone()).await?,
+            };
+            items.push(NavItem { doc, children });
+        }
+        Ok(items)
+    })
+}
+
+/// 按文档树输出嵌套的链接列表
+fn nav_list(items: &[NavItem], out: &mut String) {
+    if items.is_empty() {
+        return;
+    }
+    out.push_str("<ul>\n");
+    for item in items {
+        out.push_str(&format!(
+            "<li><a href=\"{}\">{}</a>",
+            page_name(&item.doc.id),
+            escape_html(item.doc.title())
+        ));
+        if !item.children.is_empty() {
+            out.push('\n');
+            nav_list(&item.children, out);
+        }
+        out.push_str("</li>\n");
+    }
+    out.push_str("</ul>\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{FsBackend, KernelBackend, SharedBackend};
+    use crate::lute::node::rand_str;
+    use crate::mock::{fixture_data_dir, MockKernel};
+    use std::sync::Arc;
+
+    const BOX: &str = "20210808180117-czj9bvb";
+
+    #[tokio::test]
+    async fn test_export_notebook() {
+        let kernel = MockKernel::start(fixture_data_dir()).await.unwrap();
+        let backends: Vec<SharedBackend> = vec![
+            Arc::new(KernelBackend::new(kernel.client())),
+            Arc::new(FsBackend::new(kernel.profile()).index_file(None)),
+        ];
+        for backend in backends {
+            let kind = backend.kind();
+            let out_dir = std::env::temp_dir().join(format!("rsy-export-{}", rand_str(8)));
+            let written = export_notebook(backend.as_ref(), BOX, &out_dir)
+                .await
+                .unwrap();
+            let names: Vec<_> = written
+                .iter()
+                .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+                .collect();
+            assert_eq!(names.len(), 4, "{:?}", kind);
+            assert_eq!(names[0], INDEX_PAGE, "{:?}", kind);
+
+            let index = fs::read_to_string(out_dir.join(INDEX_PAGE)).unwrap();
+            assert!(index.contains("<title>Fixture</title>"), "{:?}", kind);
+            assert!(
+                index.contains(
+                    "<li><a href=\"20230620165438-1pqr39r.html\">Draft</a>\n\
+                     <ul>\n<li><a href=\"20240107160843-8f02mqs.html\">Rust notes</a></li>\n</ul>\n</li>\n"
+                ),
+                "{:?}",
+                kind
+            );
+
+            // 跨文档的引用指向被引用文档, 笔记本中不存在的块只保留锚点
+            let notes = fs::read_to_string(out_dir.join("20240107160843-8f02mqs.html")).unwrap();
+            assert!(notes.contains("<h1>Rust notes</h1>"), "{:?}", kind);
+            assert!(notes.contains(
+                "<a href=\"20230620165438-1pqr39r.html#20231026101149-10uol14\" class=\"block-ref\">the draft</a>"
+            ));
+            assert!(notes.contains(
+                "<a href=\"#20240101000000-missing\" class=\"block-ref\">a removed block</a>"
+            ));
+            let draft = fs::read_to_string(out_dir.join("20230620165438-1pqr39r.html")).unwrap();
+            assert!(draft.contains("<p id=\"20231026101149-10uol14\">This is a test file.</p>"));
+            fs::remove_dir_all(out_dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_document_html() {
+        let doc: Node = serde_json::from_value(serde_json::json!({
+            "ID": "20240301100000-aaaaaaa",
+            "Type": "NodeDocument",
+            "Properties": {"title": "<T>"},
+            "Children": [{"Type": "NodeParagraph", "Children": [{"Type": "NodeText", "Data": "x"}]}]
+        }))
+        .unwrap();
+        assert_eq!(
+            document_html(&doc),
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\" />\n<title>&lt;T&gt;</title>\n</head>\n\
+             <body>\n<h1>&lt;T&gt;</h1>\n<p>x</p>\n</body>\n</html>\n"
+        );
+    }
+}
@@ -1,11 +1,22 @@
-use crate::{lute, REPO_PATH};
+use crate::client::{parse_response, SiyuanClient};
+use crate::config::Profile;
+use crate::error::{ApiError, ApiResult, StaleDocument};
+use crate::lute;
+use crate::lute::node::rand_str;
 use anyhow::{Context, Result};
-use std::path::{Path, PathBuf};
-use std::{fs::File, io::BufReader};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, Write};
+use std::path::Path;
 
-pub fn load_json_node(file_path: &String) -> Result<lute::node::Node> {
-    let root= Path::new(REPO_PATH);
-    let full_path = root.join(file_path);
+pub const API_GET_FILE: &str = "/api/file/getFile";
+
+/// 读取 data 目录下的 `.sy` 文件. `file_path` 为相对 data 目录的路径,
+/// 例如 `<box>/<id>/<id>.sy`
+pub fn load_json_node(profile: &Profile, file_path: &str) -> Result<lute::node::Node> {
+    let full_path = profile.data_dir.join(file_path.trim_start_matches('/'));
     // 打开文件并添加错误上下文
     let file = File::open(&full_path)
         .with_context(|| format!("Failed to open file: {}", full_path.display()))?;
@@ -14,29 +25,162 @@ pub fn load_json_node(file_path: &String) -> Result<lute::node::Node> {
     let reader = BufReader::with_capacity(1024 * 1024, file);
 
     // 反序列化并添加详细错误上下文
-     serde_json::from_reader(reader).map_err(|e| {
+    serde_json::from_reader(reader).map_err(|e| {
         // 将原始错误转换为 anyhow::Error 并添加上下文
-        anyhow::Error::new(e).context(format!(
-            "Failed to parse JSON from: {}",
-            file_path.clone()
-        ))
+        anyhow::Error::new(e).context(format!("Failed to parse JSON from: {}", file_path))
     })
+}
+
+/// 将文档写回 data 目录下的 `.sy` 文件, `file_path` 与 [`load_json_node`] 相同.
+///
+/// `loaded_updated` 为加载时文档的 `updated` 属性, 磁盘上的文档比它更新时
+/// 拒绝写入并返回 [`StaleDocument`]. 内容先写入同目录下的临时文件, 再重命名覆盖原文件,
+/// 写入中途失败不会留下不完整的文档.
+pub fn save_json_node(
+    profile: &Profile,
+    file_path: &str,
+    node: &lute::node::Node,
+    loaded_updated: &str,
+) -> Result<()> {
+    let full_path = profile.data_dir.join(file_path.trim_start_matches('/'));
+    if let Some(on_disk) = read_updated(&full_path)? {
+        if on_disk.as_str() > loaded_updated {
+            return Err(StaleDocument {
+                path: full_path,
+                loaded: loaded_updated.to_string(),
+                on_disk,
+            }
+            .into());
+        }
+    }
 
-  
+    let content =
+        lute::sy::to_sy_vec(node).with_context(|| format!("Failed to serialize {}", file_path))?;
+    let file_name = full_path
+        .file_name()
+        .with_context(|| format!("Invalid document path: {}", file_path))?
+        .to_string_lossy();
+    let tmp_path = full_path.with_file_name(format!(".{}.{}.tmp", file_name, rand_str(6)));
+    let written = File::create(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(&content)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp_path, &full_path));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(
+            anyhow::Error::new(e).context(format!("Failed to write file: {}", full_path.display()))
+        );
+    }
+    Ok(())
+}
+
+/// 读取磁盘上文档的 `updated` 属性, 文件不存在时返回 `None`
+fn read_updated(path: &Path) -> Result<Option<String>> {
+    #[derive(Deserialize)]
+    struct Header {
+        #[serde(rename = "Properties", default)]
+        properties: HashMap<String, String>,
+    }
+
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(
+                anyhow::Error::new(e).context(format!("Failed to open file: {}", path.display()))
+            )
+        }
+    };
+    let header: Header = serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("Failed to parse JSON from: {}", path.display()))?;
+    Ok(header.properties.get("updated").cloned())
+}
+
+/// 通过内核读取 data 目录下的 `.sy` 文件, `file_path` 与 [`load_json_node`] 相同.
+///
+/// 该接口成功时直接返回文件内容, 失败时才返回 `{"code": .., "msg": ..}`.
+pub async fn get_json_node(client: &SiyuanClient, file_path: &str) -> ApiResult<lute::node::Node> {
+    let path = format!("/data/{}", file_path.trim_start_matches('/'));
+    let http_err = |source| ApiError::Http {
+        api: API_GET_FILE.to_string(),
+        source,
+    };
+    let response = client
+        .post(API_GET_FILE)
+        .json(&json!({ "path": path }))
+        .send()
+        .await
+        .map_err(http_err)?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(ApiError::Status {
+            api: API_GET_FILE.to_string(),
+            status,
+        });
+    }
+    let body = response.bytes().await.map_err(http_err)?;
+    serde_json::from_slice(&body).or_else(|source| {
+        // 不是文档时按错误响应解析, 以便保留内核给出的错误信息
+        parse_response::<()>(API_GET_FILE, &body)?;
+        Err(ApiError::Decode {
+            api: API_GET_FILE.to_string(),
+            source,
+        })
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use serde_json::json;
+    use crate::mock::fixture_data_dir;
+
     #[test]
     fn test_load_node() {
-            
-        let path = String::from("20230620162729-levf2as/20230629142416-fk29t9w/20230629142458-ffxtme3/20240107160843-8f02mqs.sy");
-        let json_data = load_json_node(&path).unwrap();
-        
+        let profile = Profile {
+            data_dir: fixture_data_dir(),
+            ..Profile::default()
+        };
+        let path =
+            String::from("20210808180117-czj9bvb/20230620165438-1pqr39r/20240107160843-8f02mqs.sy");
+        let json_data = load_json_node(&profile, &path).unwrap();
+
         let serialized = serde_json::to_string_pretty(&json_data).unwrap();
         println!("{}", serialized);
         assert!(json_data.id.is_some());
     }
+
+    #[test]
+    fn test_save_node() {
+        let source = fixture_data_dir()
+            .join("20210808180117-czj9bvb/20230620165438-1pqr39r/20240107160843-8f02mqs.sy");
+        let data_dir = std::env::temp_dir().join(format!("rsy-save-{}", rand_str(8)));
+        fs::create_dir_all(&data_dir).unwrap();
+        fs::copy(&source, data_dir.join("doc.sy")).unwrap();
+        let profile = Profile {
+            data_dir: data_dir.clone(),
+            ..Profile::default()
+        };
+
+        let mut node = load_json_node(&profile, "doc.sy").unwrap();
+        let loaded = node.properties.as_ref().unwrap()["updated"].clone();
+        save_json_node(&profile, "doc.sy", &node, &loaded).unwrap();
+        assert_eq!(
+            fs::read(data_dir.join("doc.sy")).unwrap(),
+            fs::read(&source).unwrap()
+        );
+
+        // 其他程序在加载之后修改了文档
+        let props = node.properties.as_mut().unwrap();
+        props.insert("updated".to_string(), "99991231235959".to_string());
+        props.insert("title".to_string(), "changed".to_string());
+        save_json_node(&profile, "doc.sy", &node, &loaded).unwrap();
+        let err = save_json_node(&profile, "doc.sy", &node, &loaded).unwrap_err();
+        assert!(err.downcast_ref::<StaleDocument>().is_some());
+
+        let files: Vec<_> = fs::read_dir(&data_dir).unwrap().collect();
+        assert_eq!(files.len(), 1);
+        fs::remove_dir_all(&data_dir).unwrap();
+    }
 }
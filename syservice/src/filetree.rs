@@ -0,0 +1,123 @@
+//! 文档树接口 `/api/filetree/*`
+use crate::client::SiyuanClient;
+use crate::domain::*;
+use crate::error::ApiResult;
+
+pub const API_CREATE_DOC_WITH_MD: &str = "/api/filetree/createDocWithMd";
+pub const API_RENAME_DOC: &str = "/api/filetree/renameDoc";
+pub const API_REMOVE_DOC: &str = "/api/filetree/removeDoc";
+pub const API_MOVE_DOCS: &str = "/api/filetree/moveDocs";
+pub const API_GET_HPATH_BY_PATH: &str = "/api/filetree/getHPathByPath";
+pub const API_GET_HPATH_BY_ID: &str = "/api/filetree/getHPathByID";
+pub const API_GET_IDS_BY_HPATH: &str = "/api/filetree/getIDsByHPath";
+pub const API_LIST_DOCS_BY_PATH: &str = "/api/filetree/listDocsByPath";
+
+/// 使用 Markdown 创建文档, 返回新文档的 id
+pub async fn create_doc_with_md(client: &SiyuanClient, req: &CreateDocWithMd) -> ApiResult<String> {
+    client.call(API_CREATE_DOC_WITH_MD, req).await
+}
+
+pub async fn rename_doc(client: &SiyuanClient, req: &RenameDoc) -> ApiResult<()> {
+    client.call(API_RENAME_DOC, req).await
+}
+
+pub async fn remove_doc(client: &SiyuanClient, req: &RemoveDoc) -> ApiResult<()> {
+    client.call(API_REMOVE_DOC, req).await
+}
+
+pub async fn move_docs(client: &SiyuanClient, req: &MoveDocs) -> ApiResult<()> {
+    client.call(API_MOVE_DOCS, req).await
+}
+
+/// 根据存储路径获取人类可读路径
+pub async fn get_hpath_by_path(client: &SiyuanClient, req: &GetHPathByPath) -> ApiResult<String> {
+    client.call(API_GET_HPATH_BY_PATH, req).await
+}
+
+/// 根据块 id 获取人类可读路径
+pub async fn get_hpath_by_id(client: &SiyuanClient, req: &GetHPathById) -> ApiResult<String> {
+    client.call(API_GET_HPATH_BY_ID, req).await
+}
+
+/// 根据人类可读路径获取文档 id. 同名文档可能有多个.
+pub async fn get_ids_by_hpath(
+    client: &SiyuanClient,
+    req: &GetIdsByHPath,
+) -> ApiResult<Vec<String>> {
+    client.call(API_GET_IDS_BY_HPATH, req).await
+}
+
+/// 列出指定路径下的子文档
+pub async fn list_docs_by_path(
+    client: &SiyuanClient,
+    req: &ListDocsByPath,
+) -> ApiResult<ListDocsByPathResponse> {
+    client.call(API_LIST_DOCS_BY_PATH, req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::parse_response;
+
+    #[test]
+    fn test_request_fields() {
+        let req = MoveDocs {
+            from_paths: vec!["20210817205410-2kvfpfn/20210902210113-0avi12f.sy".into()],
+            to_notebook: "20210817205410-2kvfpfn".into(),
+            to_path: "/".into(),
+        };
+        let json = serde_json::to_value(&req).unwrap();
+        assert!(json.get("fromPaths").is_some());
+        assert_eq!(json["toNotebook"], "20210817205410-2kvfpfn");
+
+        let req = ListDocsByPath {
+            notebook: "20210817205410-2kvfpfn".into(),
+            path: "/".into(),
+            sort: None,
+            max_list_count: Some(32),
+        };
+        let json = serde_json::to_value(&req).unwrap();
+        assert!(json.get("sort").is_none());
+        assert_eq!(json["maxListCount"], 32);
+    }
+
+    #[test]
+    fn test_parse_list_docs_by_path() {
+        let body = br#"{
+            "code": 0,
+            "msg": "",
+            "data": {
+                "box": "20210817205410-2kvfpfn",
+                "files": [{
+                    "alias": "",
+                    "bookmark": "",
+                    "count": 0,
+                    "ctime": 1689833376,
+                    "hCtime": "2023-07-20 14:09:36",
+                    "hMtime": "2 days ago",
+                    "hSize": "1.2 kB",
+                    "hidden": false,
+                    "icon": "1f4d4",
+                    "id": "20230620165438-1pqr39r",
+                    "memo": "",
+                    "mtime": 1747986965,
+                    "name": "Draft.sy",
+                    "name1": "",
+                    "newFlashcardCount": 0,
+                    "path": "/20230620165438-1pqr39r.sy",
+                    "size": 1234,
+                    "sort": 3,
+                    "subFileCount": 2
+                }],
+                "path": "/"
+            }
+        }"#;
+        let resp: ListDocsByPathResponse = parse_response(API_LIST_DOCS_BY_PATH, body).unwrap();
+        assert_eq!(resp.box_id, "20210817205410-2kvfpfn");
+        assert_eq!(resp.files.len(), 1);
+        assert_eq!(resp.files[0].title(), "Draft");
+        assert_eq!(resp.files[0].name1, "");
+        assert_eq!(resp.files[0].sub_file_count, 2);
+    }
+}
@@ -1,10 +1,7 @@
-use crate::document;
-use crate::domain::SyBlock;
-
 // pub fn search_doc_with_title(title: String) -> Vec<SyBlock>{
 //     tokio::spawn(async move {
 //         let result =
 //             document::search_doc_with_title(title).await;
 //     });
 //     return result;
-// }
\ No newline at end of file
+// }
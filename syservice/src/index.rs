@@ -0,0 +1,754 @@
+//! 离线模式使用的进程内全文索引.
+//!
+//! 以块为单位建立倒排索引, 使用 BM25 排序. 索引可以按文档或块增量更新,
+//! 并以 JSON 保存在工作空间的 `temp` 目录下, 启动时只需重新解析修改过的 `.sy` 文件.
+mod tokenizer;
+
+pub use tokenizer::{tokenize, tokenize_query};
+
+use crate::backend::fs::{block_type, read_doc, read_notebooks, sorted_entries};
+use crate::content::{content, fcontent};
+use crate::domain::BlockInfo;
+use crate::lute::node::{Node, NodeType};
+use crate::lute::{walk, WalkStatus};
+use crate::search::{
+    highlight, type_filter_key, FullTextSearch, FullTextSearchResult, SearchBlock,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// 索引格式版本, 格式变化后旧索引会被丢弃并重建
+const INDEX_VERSION: u32 = 3;
+/// 索引文件相对工作空间的路径
+const INDEX_FILE: &str = "temp/rsy-scribe/search-index.json";
+/// 与内核一致的默认分页大小
+const DEFAULT_PAGE_SIZE: usize = 32;
+
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+/// 已索引的文档
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedDoc {
+    box_id: String,
+    /// 存储路径, 例如 `/20200812220555-lj3enxa/20210808180320-fqgskfj.sy`
+    path: String,
+    hpath: String,
+    title: String,
+    /// `.sy` 文件的修改时间(毫秒)
+    mtime: u64,
+    block_ids: Vec<String>,
+    container_ids: Vec<String>,
+}
+
+/// 已索引的块
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedBlock {
+    root_id: String,
+    parent_id: String,
+    /// 节点类型, 例如 `NodeParagraph`
+    node_type: String,
+    /// 全文搜索中的类型过滤 key, 例如 `paragraph`
+    type_key: Option<String>,
+    /// 块类型, 例如 `p`
+    block_type: String,
+    name: String,
+    content: String,
+    fcontent: String,
+    /// 块的 `updated` 属性, 缺失时为空
+    updated: String,
+    /// 词元数量
+    len: u32,
+}
+
+/// BM25 全文索引
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchIndex {
+    version: u32,
+    docs: BTreeMap<String, IndexedDoc>,
+    blocks: HashMap<String, IndexedBlock>,
+    /// 不参与搜索的容器块, 只用于查询块信息
+    containers: HashMap<String, IndexedBlock>,
+    /// 词元 -> 块 id -> 词频
+    postings: HashMap<String, HashMap<String, u32>>,
+    total_len: u64,
+    /// 自加载或保存以来是否有修改
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl Default for SearchIndex {
+    fn default() -> Self {
+        Self {
+            version: INDEX_VERSION,
+            docs: BTreeMap::new(),
+            blocks: HashMap::new(),
+            containers: HashMap::new(),
+            postings: HashMap::new(),
+            total_len: 0,
+            dirty: false,
+        }
+    }
+}
+
+impl SearchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `data_dir` 所在工作空间的默认索引文件
+    pub fn default_path(data_dir: &Path) -> PathBuf {
+        data_dir.parent().unwrap_or(data_dir).join(INDEX_FILE)
+    }
+
+    /// 读取索引文件, 文件不存在, 无法解析或版本不一致时返回空索引
+    pub fn load(path: &Path) -> SearchIndex {
+        fs::read(path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<SearchIndex>(&bytes).ok())
+            .filter(|index| index.version == INDEX_VERSION)
+            .unwrap_or_default()
+    }
+
+    /// 先写入临时文件再重命名, 避免中断时留下不完整的索引
+    pub fn save(&mut self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(self)?)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// 与 `data_dir` 同步: 重新索引新增或修改过的文档, 移除已删除的文档
+    /// 以及已关闭笔记本中的文档.
+    pub fn sync(&mut self, data_dir: &Path) -> Result<()> {
+        let mut seen = HashSet::new();
+        for notebook in read_notebooks(data_dir)? {
+            if !notebook.conf.closed {
+                let dir = data_dir.join(&notebook.id);
+                self.sync_dir(&dir, &notebook.id, "", "", &mut seen)?;
+            }
+        }
+        let removed: Vec<String> = self
+            .docs
+            .keys()
+            .filter(|id| !seen.contains(*id))
+            .cloned()
+            .collect();
+        for id in removed {
+            self.remove_document(&id);
+        }
+        Ok(())
+    }
+
+    fn sync_dir(
+        &mut self,
+        dir: &Path,
+        box_id: &str,
+        rel: &str,
+        hpath: &str,
+        seen: &mut HashSet<String>,
+    ) -> Result<()> {
+        for (name, path) in sorted_entries(dir)? {
+            let Some(id) = name.strip_suffix(".sy") else {
+                continue;
+            };
+            if !path.is_file() {
+                continue;
+            }
+            let doc_path = format!("{}/{}", rel, name);
+            let mtime = modified_millis(&path);
+            let unchanged = self.docs.get(id).is_some_and(|doc| {
+                doc.mtime == mtime && doc.box_id == box_id && doc.path == doc_path
+            });
+            let doc_hpath = if unchanged {
+                // 文件未修改, 但父文档可能改了标题
+                let doc = self.docs.get_mut(id).expect("indexed document");
+                let doc_hpath = format!("{}/{}", hpath, doc.title);
+                if doc.hpath != doc_hpath {
+                    doc.hpath = doc_hpath.clone();
+                    self.dirty = true;
+                }
+                doc_hpath
+            } else {
+                match read_doc(&path) {
+                    Ok(node) => {
+                        let doc_hpath = format!("{}/{}", hpath, doc_title(&node));
+                        self.index_document(box_id, &doc_path, &doc_hpath, &node);
+                        if let Some(doc) = self.docs.get_mut(id) {
+                            doc.mtime = mtime;
+                        }
+                        doc_hpath
+                    }
+                    Err(err) => {
+                        // 可能是写入到一半的文件, 保留上次的索引, 下次同步时重试
+                        log::warn!("Skipping unreadable document: {:#}", err);
+                        match self.docs.get(id) {
+                            Some(doc) => doc.hpath.clone(),
+                            None => format!("{}/{}", hpath, id),
+                        }
+                    }
+                }
+            };
+            seen.insert(id.to_string());
+            let sub_dir = dir.join(id);
+            if sub_dir.is_dir() {
+                self.sync_dir(
+                    &sub_dir,
+                    box_id,
+                    &format!("{}/{}", rel, id),
+                    &doc_hpath,
+                    seen,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 重新索引整篇文档, `node` 为文档根节点
+    pub fn index_document(&mut self, box_id: &str, path: &str, hpath: &str, node: &Node) {
+        let Some(root_id) = node.id.clone() else {
+            return;
+        };
+        self.remove_document(&root_id);
+        let (containers, blocks): (Vec<_>, Vec<_>) = collect_blocks(node)
+            .into_iter()
+            .partition(|(_, _, node)| is_unsearchable_container(node));
+        let ids = |blocks: &[(&str, &str, &Node)]| {
+            blocks.iter().map(|(id, _, _)| id.to_string()).collect()
+        };
+        self.docs.insert(
+            root_id.clone(),
+            IndexedDoc {
+                box_id: box_id.to_string(),
+                path: path.to_string(),
+                hpath: hpath.to_string(),
+                title: doc_title(node).to_string(),
+                mtime: 0,
+                block_ids: ids(&blocks),
+                container_ids: ids(&containers),
+            },
+        );
+        for (id, parent_id, block) in blocks {
+            self.insert_block(id, &root_id, parent_id, block);
+        }
+        for (id, parent_id, block) in containers {
+            self.containers
+                .insert(id.to_string(), new_block(&root_id, parent_id, block));
+        }
+        self.dirty = true;
+    }
+
+    /// 移除文档及其全部块
+    pub fn remove_document(&mut self, root_id: &str) {
+        if let Some(doc) = self.docs.remove(root_id) {
+            for id in &doc.block_ids {
+                self.remove_postings(id);
+            }
+            for id in &doc.container_ids {
+                self.containers.remove(id);
+            }
+            self.dirty = true;
+        }
+    }
+
+    /// 更新已索引块的内容, 块不在索引中时返回 `false`
+    pub fn update_block(&mut self, node: &Node) -> bool {
+        let Some(id) = node.id.as_deref() else {
+            return false;
+        };
+        if let Some(old) = self.containers.get_mut(id) {
+            *old = new_block(&old.root_id, &old.parent_id, node);
+            self.dirty = true;
+            return true;
+        }
+        let Some(old) = self.remove_postings(id) else {
+            return false;
+        };
+        self.insert_block(id, &old.root_id, &old.parent_id, node);
+        if let NodeType::NodeDocument = node.node_type {
+            if let Some(doc) = self.docs.get_mut(&old.root_id) {
+                doc.title = doc_title(node).to_string();
+            }
+        }
+        self.dirty = true;
+        true
+    }
+
+    /// 从索引中移除单个块
+    pub fn remove_block(&mut self, id: &str) -> bool {
+        let Some(old) = self
+            .containers
+            .remove(id)
+            .or_else(|| self.remove_postings(id))
+        else {
+            return false;
+        };
+        if let Some(doc) = self.docs.get_mut(&old.root_id) {
+            doc.block_ids.retain(|block_id| block_id != id);
+            doc.container_ids.retain(|block_id| block_id != id);
+        }
+        self.dirty = true;
+        true
+    }
+
+    /// 已索引的块(包括容器块)的信息
+    pub fn block_info(&self, id: &str) -> Option<BlockInfo> {
+        let block = self.blocks.get(id).or_else(|| self.containers.get(id))?;
+        Some(BlockInfo {
+            id: id.to_string(),
+            root_id: block.root_id.clone(),
+            block_type: block.block_type.clone(),
+            name: block.name.clone(),
+            content: block.content.clone(),
+            fcontent: block.fcontent.clone(),
+        })
+    }
+
+    fn insert_block(&mut self, id: &str, root_id: &str, parent_id: &str, node: &Node) {
+        let mut block = new_block(root_id, parent_id, node);
+        let tokens = tokenize(&block.content);
+        for token in &tokens {
+            *self
+                .postings
+                .entry(token.clone())
+                .or_default()
+                .entry(id.to_string())
+                .or_default() += 1;
+        }
+        self.total_len += tokens.len() as u64;
+        block.len = tokens.len() as u32;
+        self.blocks.insert(id.to_string(), block);
+    }
+
+    fn remove_postings(&mut self, id: &str) -> Option<IndexedBlock> {
+        let block = self.blocks.remove(id)?;
+        for token in tokenize(&block.content) {
+            if let Some(posting) = self.postings.get_mut(&token) {
+                posting.remove(id);
+                if posting.is_empty() {
+                    self.postings.remove(&token);
+                }
+            }
+        }
+        self.total_len -= block.len as u64;
+        Some(block)
+    }
+
+    /// 搜索包含全部查询词元的块, 按 BM25 得分从高到低排列
+    pub fn search(&self, req: &FullTextSearch) -> FullTextSearchResult {
+        let mut terms = tokenize_query(&req.query);
+        terms.sort();
+        terms.dedup();
+        let mut scores: Vec<(f64, &str)> = self
+            .candidates(&terms)
+            .into_iter()
+            .filter(|id| self.accepts(id, req))
+            .map(|id| (self.score(id, &terms), id))
+            .collect();
+        scores.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+
+        // 以匹配时使用的词元高亮, 没有空格的中文查询也能标出命中部分
+        let keywords: Vec<&str> = terms.iter().map(String::as_str).collect();
+        // 与内核一致, 0 表示使用默认分页大小
+        let page_size = match req.page_size {
+            None | Some(0) => DEFAULT_PAGE_SIZE,
+            Some(size) => size as usize,
+        };
+        let page = req.page.max(1) as usize;
+        let roots: BTreeSet<&str> = scores
+            .iter()
+            .map(|(_, id)| self.blocks[*id].root_id.as_str())
+            .collect();
+        FullTextSearchResult {
+            matched_block_count: scores.len() as u32,
+            matched_root_count: roots.len() as u32,
+            page_count: scores.len().div_ceil(page_size) as u32,
+            blocks: Some(
+                scores
+                    .iter()
+                    .skip((page - 1) * page_size)
+                    .take(page_size)
+                    .map(|(_, id)| self.search_block(id, &keywords))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// 同时包含全部词元的块
+    fn candidates(&self, terms: &[String]) -> Vec<&str> {
+        let mut postings = Vec::with_capacity(terms.len());
+        for term in terms {
+            match self.postings.get(term) {
+                Some(posting) => postings.push(posting),
+                None => return Vec::new(),
+            }
+        }
+        // 从最短的倒排表开始求交集
+        postings.sort_by_key(|posting| posting.len());
+        let Some((first, rest)) = postings.split_first() else {
+            return Vec::new();
+        };
+        first
+            .keys()
+            .filter(|id| rest.iter().all(|posting| posting.contains_key(*id)))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// 笔记本, 路径与块类型过滤
+    fn accepts(&self, id: &str, req: &FullTextSearch) -> bool {
+        let block = &self.blocks[id];
+        let Some(doc) = self.docs.get(&block.root_id) else {
+            return false;
+        };
+        let in_box = req.boxes.is_empty() || req.boxes.contains(&doc.box_id);
+        let in_paths = req.paths.is_empty() || {
+            let full_path = format!("{}{}", doc.box_id, doc.path);
+            req.paths
+                .iter()
+                .any(|path| full_path.starts_with(path.trim_end_matches(".sy")))
+        };
+        let type_enabled = match &req.types {
+            Some(types) => block
+                .type_key
+                .as_deref()
+                .is_some_and(|key| types.get(key).copied().unwrap_or(false)),
+            None => true,
+        };
+        in_box && in_paths && type_enabled
+    }
+
+    fn score(&self, id: &str, terms: &[String]) -> f64 {
+        let n = self.blocks.len() as f64;
+        let avg_len = (self.total_len as f64 / n).max(1.0);
+        let len = self.blocks[id].len as f64;
+        terms
+            .iter()
+            .filter_map(|term| self.postings.get(term))
+            .map(|posting| {
+                let df = posting.len() as f64;
+                let tf = posting.get(id).copied().unwrap_or(0) as f64;
+                let idf = ((n - df + 0.5) / (df + 0.5) + 1.0).ln();
+                idf * tf * (BM25_K1 + 1.0)
+                    / (tf + BM25_K1 * (1.0 - BM25_B + BM25_B * len / avg_len))
+            })
+            .sum()
+    }
+
+    fn search_block(&self, id: &str, keywords: &[&str]) -> SearchBlock {
+        let block = &self.blocks[id];
+        let doc = &self.docs[&block.root_id];
+        // 块 ID 的前 14 位为创建时间
+        let created = id.get(..14).unwrap_or_default().to_string();
+        let updated = match block.updated.as_str() {
+            "" => created.clone(),
+            updated => updated.to_string(),
+        };
+        SearchBlock {
+            id: id.to_string(),
+            box_id: doc.box_id.clone(),
+            path: doc.path.clone(),
+            h_path: doc.hpath.clone(),
+            root_id: block.root_id.clone(),
+            parent_id: block.parent_id.clone(),
+            content: highlight(&block.content, keywords),
+            node_type: block.node_type.clone(),
+            updated,
+            created,
+            ..SearchBlock::default()
+        }
+    }
+}
+
+fn doc_title(node: &Node) -> &str {
+    property(node, "title")
+}
+
+fn property<'a>(node: &'a Node, key: &str) -> &'a str {
+    node.properties
+        .as_ref()
+        .and_then(|props| props.get(key))
+        .map(String::as_str)
+        .unwrap_or_default()
+}
+
+fn new_block(root_id: &str, parent_id: &str, node: &Node) -> IndexedBlock {
+    let block_type = block_type(node);
+    IndexedBlock {
+        root_id: root_id.to_string(),
+        parent_id: parent_id.to_string(),
+        node_type: node.type_str.clone(),
+        type_key: block_type.and_then(type_filter_key).map(str::to_string),
+        block_type: block_type.map_or("", |t| t.code()).to_string(),
+        name: property(node, "name").to_string(),
+        content: content(node),
+        fcontent: fcontent(node),
+        updated: property(node, "updated").to_string(),
+        len: 0,
+    }
+}
+
+fn modified_millis(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_millis() as u64)
+}
+
+/// 列表, 引述等容器块的内容与子块重复, 不参与搜索
+/// 文档块和脚注定义虽然也是容器块 (见 `content`), 但仍可以被搜索到
+fn is_unsearchable_container(node: &Node) -> bool {
+    matches!(
+        node.node_type,
+        NodeType::NodeList
+            | NodeType::NodeListItem
+            | NodeType::NodeBlockquote
+            | NodeType::NodeSuperBlock
+    )
+}
+
+/// 收集全部块及其父块 ID
+fn collect_blocks(node: &Node) -> Vec<(&str, &str, &Node)> {
+    let mut blocks = Vec::new();
+    let mut parents = vec![""];
+    walk(node, &mut |node, entering| {
+        let Some(id) = node.id.as_deref() else {
+            return WalkStatus::Continue;
+        };
+        if !entering {
+            parents.pop();
+            return WalkStatus::Continue;
+        }
+        if block_type(node).is_some() {
+            blocks.push((id, *parents.last().unwrap(), node));
+        }
+        parents.push(id);
+        WalkStatus::Continue
+    });
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lute::node::rand_str;
+    use crate::mock::fixture_data_dir;
+
+    fn fixture_index() -> SearchIndex {
+        let mut index = SearchIndex::new();
+        index.sync(&fixture_data_dir()).unwrap();
+        index
+    }
+
+    fn ids(result: &FullTextSearchResult) -> Vec<&str> {
+        result
+            .blocks()
+            .iter()
+            .map(|block| block.id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_search_ranks_by_bm25() {
+        let index = fixture_index();
+        let result = index.search(&FullTextSearch::new("ownership"));
+        // 标题更短, 得分更高
+        assert_eq!(
+            ids(&result),
+            vec!["20240107160850-h7ncw2q", "20240107160905-3xk1m0d"]
+        );
+        assert_eq!(result.matched_root_count, 1);
+        let block = &result.blocks()[1];
+        assert_eq!(block.h_path, "/Draft/Rust notes");
+        assert_eq!(block.highlighted_content().matches, vec![10..19]);
+        assert_eq!(block.created, "20240107160905");
+        assert_eq!(block.updated, "20240107160950");
+
+        // 所有查询词元都必须出现
+        let result = index.search(&FullTextSearch::new("ownership milk"));
+        assert!(result.blocks().is_empty());
+        let result = index.search(&FullTextSearch::new("MANAGE memory"));
+        assert_eq!(ids(&result), vec!["20240107160905-3xk1m0d"]);
+    }
+
+    #[test]
+    fn test_zero_page_size_uses_default() {
+        let index = fixture_index();
+        let result = index.search(&FullTextSearch::new("ownership").page_size(0));
+        assert_eq!(result.page_count, 1);
+        assert_eq!(
+            ids(&result),
+            vec!["20240107160850-h7ncw2q", "20240107160905-3xk1m0d"]
+        );
+    }
+
+    #[test]
+    fn test_incremental_update() {
+        let mut index = fixture_index();
+        let data_dir = fixture_data_dir();
+        let path = data_dir.join("20210808180117-czj9bvb/20240301091500-q2w3e4r.sy");
+        let doc = read_doc(&path).unwrap();
+        let mut block = doc.children[0].clone();
+        block.children[0].data = Some("买牛奶和面包".to_string());
+        assert!(index.update_block(&block));
+        assert!(index
+            .search(&FullTextSearch::new("milk"))
+            .blocks()
+            .is_empty());
+        let result = index.search(&FullTextSearch::new("牛奶"));
+        assert_eq!(ids(&result), vec!["20240301091510-m4n5b6v"]);
+        assert_eq!(result.blocks()[0].highlighted_content().matches, vec![3..9]);
+        // 查询与内容的空格不一致时按词元高亮
+        block.children[0].data = Some("Rust 所有权".to_string());
+        assert!(index.update_block(&block));
+        let result = index.search(&FullTextSearch::new("rust所有权"));
+        assert_eq!(
+            result.blocks()[0].highlighted_content().matches,
+            vec![0..4, 5..14]
+        );
+        block.children[0].data = Some("买牛奶和面包".to_string());
+        assert!(index.update_block(&block));
+        // 单字查询命中单字词元
+        assert_eq!(
+            index.search(&FullTextSearch::new("面")).matched_block_count,
+            1
+        );
+
+        assert!(index.remove_block("20240301091510-m4n5b6v"));
+        assert!(index
+            .search(&FullTextSearch::new("牛奶"))
+            .blocks()
+            .is_empty());
+        index.remove_document("20240301091500-q2w3e4r");
+        assert!(index
+            .search(&FullTextSearch::new("inbox"))
+            .blocks()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_block_info() {
+        let mut index = fixture_index();
+        let count = index.block_count();
+        // 容器块可以查询, 但不参与搜索
+        let item = index.block_info("20250512161442-im7tv6x").unwrap();
+        assert_eq!(item.root_id, "20230620165438-1pqr39r");
+        assert_eq!(item.block_type, "i");
+        assert_eq!(item.fcontent, "Parent1");
+        let todo = index.block_info("20240301091510-m4n5b6v").unwrap();
+        assert_eq!(
+            (todo.name.as_str(), todo.block_type.as_str()),
+            ("todo", "p")
+        );
+        let doc = index.block_info("20240107160843-8f02mqs").unwrap();
+        assert_eq!(
+            (doc.content.as_str(), doc.block_type.as_str()),
+            ("Rust notes", "d")
+        );
+        assert_eq!(index.block_info("20240101000000-missing"), None);
+
+        assert!(index.remove_block("20250512161442-im7tv6x"));
+        assert_eq!(index.block_info("20250512161442-im7tv6x"), None);
+        assert_eq!(index.block_count(), count);
+        index.remove_document("20230620165438-1pqr39r");
+        assert_eq!(index.block_info("20250512161443-xewqu8e"), None);
+    }
+
+    #[test]
+    fn test_sync_skips_unreadable_documents() {
+        let data_dir = std::env::temp_dir()
+            .join(format!("rsy-index-{}", rand_str(8)))
+            .join("data");
+        let box_dir = data_dir.join("20240301100000-aaaaaaa");
+        fs::create_dir_all(box_dir.join(".siyuan")).unwrap();
+        fs::write(box_dir.join(".siyuan/conf.json"), r#"{"name": "Temp"}"#).unwrap();
+        let doc = |id: &str, title: &str, block_type: &str| {
+            serde_json::json!({
+                "ID": id,
+                "Type": "NodeDocument",
+                "Properties": {"id": id, "title": title},
+                "Children": [{
+                    "ID": format!("{}-p", &id[..14]),
+                    "Type": block_type,
+                    "Children": [{"Type": "NodeText", "Data": "ownership"}]
+                }]
+            })
+            .to_string()
+        };
+        fs::write(
+            box_dir.join("20240301100001-bbbbbbb.sy"),
+            doc("20240301100001-bbbbbbb", "Good", "NodeParagraph"),
+        )
+        .unwrap();
+        // 更新版本的思源新增的块类型
+        fs::write(
+            box_dir.join("20240301100002-ccccccc.sy"),
+            doc("20240301100002-ccccccc", "Future", "NodeFromTheFuture"),
+        )
+        .unwrap();
+        let broken = box_dir.join("20240301100003-ddddddd.sy");
+        fs::write(&broken, "{\"ID\": ").unwrap();
+
+        let mut index = SearchIndex::new();
+        index.sync(&data_dir).unwrap();
+        let result = index.search(&FullTextSearch::new("ownership"));
+        assert_eq!(ids(&result), vec!["20240301100001-p"]);
+        assert!(index.block_info("20240301100002-ccccccc").is_some());
+
+        // 文件修复后重新索引
+        fs::write(
+            &broken,
+            doc("20240301100003-ddddddd", "Fixed", "NodeParagraph"),
+        )
+        .unwrap();
+        index.sync(&data_dir).unwrap();
+        assert_eq!(
+            index
+                .search(&FullTextSearch::new("ownership"))
+                .matched_block_count,
+            2
+        );
+        fs::remove_dir_all(data_dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_persist_and_sync() {
+        let mut index = fixture_index();
+        let count = index.block_count();
+        let path = std::env::temp_dir().join(format!("rsy-index-{}.json", rand_str(8)));
+        index.save(&path).unwrap();
+        assert!(!index.is_dirty());
+
+        let mut loaded = SearchIndex::load(&path);
+        assert_eq!(loaded.block_count(), count);
+        // 文件均未修改, 同步后无需重新保存
+        loaded.sync(&fixture_data_dir()).unwrap();
+        assert!(!loaded.is_dirty());
+        assert_eq!(
+            ids(&loaded.search(&FullTextSearch::new("rust"))),
+            ids(&index.search(&FullTextSearch::new("rust")))
+        );
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(SearchIndex::load(&path).block_count(), 0);
+    }
+}
@@ -0,0 +1,85 @@
+//! 中英文混排文本的分词.
+//!
+//! 拉丁字母与数字按连续的字母数字切分为单词并转为小写;
+//! 中日韩文字没有分隔符, 建索引时输出单字与相邻二元组, 查询时只使用二元组,
+//! 这样单字查询与多字查询都能命中, 多字查询也不会匹配到字序不同的文本.
+
+/// 是否为中日韩文字
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}'     // 平假名, 片假名
+        | '\u{3400}'..='\u{4dbf}'   // 扩展 A
+        | '\u{4e00}'..='\u{9fff}'   // 基本汉字
+        | '\u{ac00}'..='\u{d7af}'   // 谚文
+        | '\u{f900}'..='\u{faff}'   // 兼容汉字
+        | '\u{20000}'..='\u{2ffff}' // 扩展 B 及以后
+    )
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Index,
+    Query,
+}
+
+/// 建索引时使用的分词, 同一个词可能出现多次
+pub fn tokenize(text: &str) -> Vec<String> {
+    split(text, Mode::Index)
+}
+
+/// 查询时使用的分词
+pub fn tokenize_query(text: &str) -> Vec<String> {
+    split(text, Mode::Query)
+}
+
+fn split(text: &str, mode: Mode) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut cjk_run: Vec<char> = Vec::new();
+    for c in text.chars() {
+        if is_cjk(c) {
+            flush_word(&mut word, &mut tokens);
+            cjk_run.push(c);
+        } else if c.is_alphanumeric() {
+            flush_cjk(&mut cjk_run, mode, &mut tokens);
+            word.extend(c.to_lowercase());
+        } else {
+            flush_word(&mut word, &mut tokens);
+            flush_cjk(&mut cjk_run, mode, &mut tokens);
+        }
+    }
+    flush_word(&mut word, &mut tokens);
+    flush_cjk(&mut cjk_run, mode, &mut tokens);
+    tokens
+}
+
+fn flush_word(word: &mut String, tokens: &mut Vec<String>) {
+    if !word.is_empty() {
+        tokens.push(std::mem::take(word));
+    }
+}
+
+fn flush_cjk(run: &mut Vec<char>, mode: Mode, tokens: &mut Vec<String>) {
+    if run.len() == 1 || mode == Mode::Index {
+        tokens.extend(run.iter().map(|c| c.to_string()));
+    }
+    tokens.extend(run.windows(2).map(|pair| pair.iter().collect::<String>()));
+    run.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_mixed_text() {
+        assert_eq!(
+            tokenize("Rust 所有权, v1.0"),
+            vec!["rust", "所", "有", "权", "所有", "有权", "v1", "0"]
+        );
+        assert_eq!(tokenize_query("所有权Rust"), vec!["所有", "有权", "rust"]);
+        assert_eq!(tokenize_query("权"), vec!["权"]);
+        assert_eq!(tokenize_query("ひらがな"), vec!["ひら", "らが", "がな"]);
+        assert!(tokenize(" ,.!").is_empty());
+    }
+}
@@ -1,28 +1,38 @@
+pub mod attr;
+pub mod backend;
+pub mod block;
+pub mod block_ref;
+pub mod client;
+pub mod config;
+pub mod content;
+pub mod doctree;
 pub mod document;
 pub mod domain;
+pub mod error;
+pub mod export;
 pub mod file;
+pub mod filetree;
 mod handler;
+pub mod index;
 pub mod lute;
+#[cfg(any(test, feature = "mock-kernel"))]
+pub mod mock;
+pub mod notebook;
+pub mod search;
+pub mod sql;
+#[cfg(test)]
+mod stub;
+pub mod ws;
 
-static REPO_PATH: &str= "/Users/crowds/Notes/SiYuanKnowledgeBase/data";
-static SIYUAN_BASE: &str = "http://127.0.0.1:6806";
-static API_SQL_QUERY: &str = "/api/query/sql";
-static API_TOKEN: &str = "1g4rmbq473pv40jo";
+pub(crate) const API_SQL_QUERY: &str = "/api/query/sql";
 pub fn add(left: u64, right: u64) -> u64 {
     left + right
 }
 
 use crate::domain::SyResponse;
 use std::future::Future;
-use std::path::Path;
-use std::pin::Pin;
 use tokio::runtime::Handle;
 
-// 定义异步搜索函数的 trait 别名（简化类型签名）
-type SearchFn = dyn Fn(String) -> Pin<Box<dyn Future<Output = anyhow::Result<SyResponse>> + Send>>
-    + Send
-    + Sync;
-
 pub fn perform_search<F, Fut>(search_func: F, input: String)
 where
     F: Fn(String) -> Fut + 'static + Send + Sync + Copy,
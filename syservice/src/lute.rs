@@ -1,6 +1,14 @@
-/// 参考 Lute 实现的
+//! 参考 Lute 实现的
 
-pub mod node;
-mod parse;
+pub mod arena;
+mod html_entity;
+pub mod ial;
 mod lexer;
-mod delimiter;
\ No newline at end of file
+pub mod node;
+pub mod parse;
+pub mod protyle;
+pub mod render;
+pub mod sy;
+pub mod walk;
+
+pub use walk::{walk, walk_mut, WalkStatus};
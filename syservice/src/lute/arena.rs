@@ -0,0 +1,380 @@
+//! 基于 arena 的文档树.
+//!
+//! [`Node`] 只通过 `children` 保存子节点, 无法从节点找到父节点或兄弟节点.
+//! [`NodeTree`] 将节点放入 [`SlotMap`], 节点之间用 [`NodeKey`] 互相引用,
+//! 支持 O(1) 的父节点, 兄弟节点, 子节点导航与按块 ID 查找.
+use crate::lute::node::Node;
+use slotmap::{new_key_type, SlotMap};
+use std::collections::HashMap;
+
+new_key_type! {
+    /// 节点在 [`NodeTree`] 中的位置, 节点被删除后失效
+    pub struct NodeKey;
+}
+
+#[derive(Debug, Clone)]
+struct Slot {
+    /// `children` 始终为空, 子节点通过下面的链接保存
+    node: Node,
+    parent: Option<NodeKey>,
+    previous: Option<NodeKey>,
+    next: Option<NodeKey>,
+    first_child: Option<NodeKey>,
+    last_child: Option<NodeKey>,
+}
+
+/// 以 arena 保存的文档树, 与 [`Node`] 互相转换
+#[derive(Debug, Clone)]
+pub struct NodeTree {
+    nodes: SlotMap<NodeKey, Slot>,
+    root: NodeKey,
+    /// 块 ID 到节点的索引
+    ids: HashMap<String, NodeKey>,
+}
+
+/// 新节点插入的位置
+#[derive(Clone, Copy)]
+enum Position {
+    Append(NodeKey),
+    Before(NodeKey),
+    After(NodeKey),
+}
+
+impl NodeTree {
+    pub fn from_node(node: Node) -> Self {
+        let mut tree = Self {
+            nodes: SlotMap::with_key(),
+            root: NodeKey::default(),
+            ids: HashMap::new(),
+        };
+        tree.root = tree.insert_subtree(node);
+        tree
+    }
+
+    /// 转换回 [`Node`], 用于保存或渲染
+    pub fn to_node(&self) -> Node {
+        self.subtree(self.root)
+    }
+
+    /// `key` 及其后代组成的 [`Node`]
+    pub fn subtree(&self, key: NodeKey) -> Node {
+        let mut node = self.nodes[key].node.clone();
+        node.children = self
+            .children(key)
+            .map(|child| self.subtree(child))
+            .collect();
+        node
+    }
+
+    pub fn root(&self) -> NodeKey {
+        self.root
+    }
+
+    /// 节点数量
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn contains(&self, key: NodeKey) -> bool {
+        self.nodes.contains_key(key)
+    }
+
+    /// 节点本身, 其 `children` 为空, 子节点通过 [`children`](Self::children) 获取
+    pub fn get(&self, key: NodeKey) -> Option<&Node> {
+        self.nodes.get(key).map(|slot| &slot.node)
+    }
+
+    /// 可修改节点的属性, 修改块 ID 请使用 [`set_id`](Self::set_id)
+    pub fn get_mut(&mut self, key: NodeKey) -> Option<&mut Node> {
+        self.nodes.get_mut(key).map(|slot| &mut slot.node)
+    }
+
+    /// 按块 ID 查找节点
+    pub fn find(&self, id: &str) -> Option<NodeKey> {
+        self.ids.get(id).copied()
+    }
+
+    /// 修改节点的块 ID 并更新索引
+    pub fn set_id(&mut self, key: NodeKey, id: Option<String>) {
+        let node = &mut self.nodes[key].node;
+        if let Some(old) = node.id.take() {
+            if self.ids.get(&old) == Some(&key) {
+                self.ids.remove(&old);
+            }
+        }
+        if let Some(id) = &id {
+            self.ids.insert(id.clone(), key);
+        }
+        node.id = id;
+    }
+
+    pub fn parent(&self, key: NodeKey) -> Option<NodeKey> {
+        self.nodes.get(key)?.parent
+    }
+
+    pub fn previous(&self, key: NodeKey) -> Option<NodeKey> {
+        self.nodes.get(key)?.previous
+    }
+
+    pub fn next(&self, key: NodeKey) -> Option<NodeKey> {
+        self.nodes.get(key)?.next
+    }
+
+    pub fn first_child(&self, key: NodeKey) -> Option<NodeKey> {
+        self.nodes.get(key)?.first_child
+    }
+
+    pub fn last_child(&self, key: NodeKey) -> Option<NodeKey> {
+        self.nodes.get(key)?.last_child
+    }
+
+    /// 按顺序遍历直接子节点
+    pub fn children(&self, key: NodeKey) -> impl Iterator<Item = NodeKey> + '_ {
+        std::iter::successors(self.first_child(key), move |&child| self.next(child))
+    }
+
+    /// 由近及远遍历祖先节点, 不包含 `key` 本身
+    pub fn ancestors(&self, key: NodeKey) -> impl Iterator<Item = NodeKey> + '_ {
+        std::iter::successors(self.parent(key), move |&parent| self.parent(parent))
+    }
+
+    /// 将 `node` 及其子节点追加为 `parent` 的最后一个子节点
+    pub fn append_child(&mut self, parent: NodeKey, node: Node) -> NodeKey {
+        self.insert(Position::Append(parent), node)
+    }
+
+    /// 将 `node` 及其子节点插入到 `sibling` 之前
+    ///
+    /// # Panics
+    /// `sibling` 为根节点时 panic
+    pub fn insert_before(&mut self, sibling: NodeKey, node: Node) -> NodeKey {
+        self.insert(Position::Before(sibling), node)
+    }
+
+    /// 将 `node` 及其子节点插入到 `sibling` 之后
+    ///
+    /// # Panics
+    /// `sibling` 为根节点时 panic
+    pub fn insert_after(&mut self, sibling: NodeKey, node: Node) -> NodeKey {
+        self.insert(Position::After(sibling), node)
+    }
+
+    /// 删除节点及其后代, 返回删除的子树. 根节点不能删除, 返回 `None`
+    pub fn remove(&mut self, key: NodeKey) -> Option<Node> {
+        if key == self.root || !self.contains(key) {
+            return None;
+        }
+        let node = self.subtree(key);
+        self.unlink(key);
+        let mut stack = vec![key];
+        while let Some(key) = stack.pop() {
+            stack.extend(self.children(key));
+            let slot = self.nodes.remove(key).unwrap();
+            if let Some(id) = slot.node.id {
+                if self.ids.get(&id) == Some(&key) {
+                    self.ids.remove(&id);
+                }
+            }
+        }
+        Some(node)
+    }
+
+    fn insert(&mut self, position: Position, node: Node) -> NodeKey {
+        let key = self.insert_subtree(node);
+        let (parent, previous, next) = match position {
+            Position::Append(parent) => (parent, self.nodes[parent].last_child, None),
+            Position::Before(sibling) => {
+                let slot = &self.nodes[sibling];
+                slot.parent
+                    .map(|parent| (parent, slot.previous, Some(sibling)))
+                    .expect("cannot insert a sibling of the root node")
+            }
+            Position::After(sibling) => {
+                let slot = &self.nodes[sibling];
+                slot.parent
+                    .map(|parent| (parent, Some(sibling), slot.next))
+                    .expect("cannot insert a sibling of the root node")
+            }
+        };
+        self.link(key, parent, previous, next);
+        key
+    }
+
+    /// 放入 arena, 但不与其他节点链接
+    fn insert_subtree(&mut self, mut node: Node) -> NodeKey {
+        let children = std::mem::take(&mut node.children);
+        let id = node.id.clone();
+        let key = self.nodes.insert(Slot {
+            node,
+            parent: None,
+            previous: None,
+            next: None,
+            first_child: None,
+            last_child: None,
+        });
+        if let Some(id) = id {
+            self.ids.entry(id).or_insert(key);
+        }
+        for child in children {
+            let child_key = self.insert_subtree(child);
+            let last = self.nodes[key].last_child;
+            self.link(child_key, key, last, None);
+        }
+        key
+    }
+
+    fn link(
+        &mut self,
+        key: NodeKey,
+        parent: NodeKey,
+        previous: Option<NodeKey>,
+        next: Option<NodeKey>,
+    ) {
+        let slot = &mut self.nodes[key];
+        slot.parent = Some(parent);
+        slot.previous = previous;
+        slot.next = next;
+        match previous {
+            Some(previous) => self.nodes[previous].next = Some(key),
+            None => self.nodes[parent].first_child = Some(key),
+        }
+        match next {
+            Some(next) => self.nodes[next].previous = Some(key),
+            None => self.nodes[parent].last_child = Some(key),
+        }
+    }
+
+    fn unlink(&mut self, key: NodeKey) {
+        let slot = &mut self.nodes[key];
+        let (parent, previous, next) = (slot.parent.take(), slot.previous.take(), slot.next.take());
+        let Some(parent) = parent else {
+            return;
+        };
+        match previous {
+            Some(previous) => self.nodes[previous].next = next,
+            None => self.nodes[parent].first_child = next,
+        }
+        match next {
+            Some(next) => self.nodes[next].previous = previous,
+            None => self.nodes[parent].last_child = previous,
+        }
+    }
+}
+
+impl From<Node> for NodeTree {
+    fn from(node: Node) -> Self {
+        Self::from_node(node)
+    }
+}
+
+impl From<&NodeTree> for Node {
+    fn from(tree: &NodeTree) -> Self {
+        tree.to_node()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::load_fixture;
+    use serde_json::{json, Value};
+
+    fn id(tree: &NodeTree, key: NodeKey) -> &str {
+        tree.get(key).unwrap().id.as_deref().unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let node = load_fixture("lists.sy");
+        let expected = serde_json::to_value(&node).unwrap();
+        let tree = NodeTree::from_node(node);
+        assert_eq!(serde_json::to_value(tree.to_node()).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_navigation() {
+        let tree = NodeTree::from_node(load_fixture("lists.sy"));
+        let root = tree.root();
+        assert_eq!(tree.children(root).count(), 3);
+
+        let paragraph = tree.find("20240301110003-ddddddd").unwrap();
+        let ancestors: Vec<_> = tree
+            .ancestors(paragraph)
+            .map(|key| id(&tree, key))
+            .collect();
+        assert_eq!(
+            ancestors,
+            [
+                "20240301110002-ccccccc",
+                "20240301110001-bbbbbbb",
+                "20240301110000-aaaaaaa"
+            ]
+        );
+
+        let first_list = tree.first_child(root).unwrap();
+        let second_list = tree.next(first_list).unwrap();
+        assert_eq!(id(&tree, second_list), "20240301110004-eeeeeee");
+        assert_eq!(tree.previous(second_list), Some(first_list));
+        assert_eq!(tree.previous(first_list), None);
+        assert_eq!(
+            id(&tree, tree.last_child(root).unwrap()),
+            "20240301110007-hhhhhhh"
+        );
+        assert!(tree.get(root).unwrap().children.is_empty());
+    }
+
+    #[test]
+    fn test_edit() {
+        let mut tree = NodeTree::from_node(load_fixture("lists.sy"));
+        let quote = tree.find("20240301110007-hhhhhhh").unwrap();
+        let paragraph = |id: &str| -> Node {
+            serde_json::from_value(json!({
+                "ID": id,
+                "Type": "NodeParagraph",
+                "Children": [{"Type": "NodeText", "Data": id}]
+            }))
+            .unwrap()
+        };
+
+        let before = tree.insert_before(quote, paragraph("20240301150000-before0"));
+        let after = tree.insert_after(quote, paragraph("20240301150001-after00"));
+        let appended = tree.append_child(quote, paragraph("20240301150002-append0"));
+        assert_eq!(tree.find("20240301150000-before0"), Some(before));
+        assert_eq!(tree.next(before), Some(quote));
+        assert_eq!(tree.last_child(tree.root()), Some(after));
+        assert_eq!(tree.last_child(quote), Some(appended));
+
+        let len = tree.len();
+        let removed = tree.remove(quote).unwrap();
+        assert_eq!(removed.children.len(), 3);
+        assert_eq!(tree.len(), len - 6);
+        assert!(!tree.contains(appended));
+        assert_eq!(tree.find("20240301110008-iiiiiii"), None);
+        assert_eq!(tree.next(before), Some(after));
+        assert_eq!(tree.remove(tree.root()).map(|_| ()), None);
+
+        tree.set_id(after, Some("20240301150003-renamed".to_string()));
+        assert_eq!(tree.find("20240301150001-after00"), None);
+        assert_eq!(tree.find("20240301150003-renamed"), Some(after));
+
+        let ids: Vec<Value> = serde_json::to_value(tree.to_node()).unwrap()["Children"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|child| child["ID"].clone())
+            .collect();
+        assert_eq!(
+            ids,
+            [
+                "20240301110001-bbbbbbb",
+                "20240301110004-eeeeeee",
+                "20240301150000-before0",
+                "20240301150003-renamed"
+            ]
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! kramdown IAL (行内属性列表), 例如 `{: id="..." updated="..." style="..."}`.
+//!
+//! 块 IAL 单独成行写在块之后, 列表项的写在标记之后; 行级 IAL 紧跟在行级元素之后.
+//! 属性值与内核一致保存转义后的形式, 换行写为 `_esc_newline_`, 因此 `Properties`
+//! 与 IAL 文本可以原样互相转换.
+use crate::lute::node::Node;
+use std::collections::HashMap;
+
+/// 属性的键, 例如 `id`, `custom-xxx`
+fn is_key_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'-' || b == b'_'
+}
+
+/// 解析以 `{:` 开头的 IAL, 返回属性与消耗的字节数. 至少需要一个属性
+pub fn parse_ial(text: &[u8]) -> Option<(Vec<Vec<String>>, usize)> {
+    if !text.starts_with(b"{:") {
+        return None;
+    }
+    let mut ial = Vec::new();
+    let mut pos = 2;
+    loop {
+        let spaces = text[pos..]
+            .iter()
+            .take_while(|&&b| b == b' ' || b == b'\t')
+            .count();
+        pos += spaces;
+        match text.get(pos) {
+            Some(b'}') if !ial.is_empty() => return Some((ial, pos + 1)),
+            Some(&b) if is_key_byte(b) && (spaces > 0 || ial.is_empty()) => {}
+            _ => return None,
+        }
+        let key_len = text[pos..].iter().take_while(|&&b| is_key_byte(b)).count();
+        let key = &text[pos..pos + key_len];
+        pos += key_len;
+        if text.get(pos..pos + 2) != Some(b"=\"") {
+            return None;
+        }
+        pos += 2;
+        let value_len = text[pos..].iter().position(|&b| b == b'"' || b == b'\n')?;
+        if text[pos + value_len] != b'"' {
+            return None;
+        }
+        let value = &text[pos..pos + value_len];
+        pos += value_len + 1;
+        ial.push(vec![
+            String::from_utf8_lossy(key).into_owned(),
+            String::from_utf8_lossy(value).into_owned(),
+        ]);
+    }
+}
+
+/// 整行为 IAL 时返回其属性, 忽略首尾空白
+pub fn parse_ial_line(line: &[u8]) -> Option<Vec<Vec<String>>> {
+    let line = line.trim_ascii();
+    parse_ial(line)
+        .filter(|(_, len)| *len == line.len())
+        .map(|(ial, _)| ial)
+}
+
+/// 输出 IAL 文本
+pub fn ial_text(ial: &[Vec<String>]) -> String {
+    let attrs: Vec<String> = ial
+        .iter()
+        .filter_map(|pair| match pair.as_slice() {
+            [key, value] => Some(format!("{}=\"{}\"", key, escape_value(value))),
+            _ => None,
+        })
+        .collect();
+    format!("{{: {}}}", attrs.join(" "))
+}
+
+/// 属性转换为 IAL, `id` 在最前, 其余按 key 排序
+pub fn properties_ial(properties: &HashMap<String, String>) -> Vec<Vec<String>> {
+    let mut keys: Vec<&String> = properties.keys().collect();
+    keys.sort_by_key(|key| (key.as_str() != "id", key.as_str()));
+    keys.into_iter()
+        .map(|key| vec![key.clone(), properties[key].clone()])
+        .collect()
+}
+
+/// IAL 转换为属性
+pub fn ial_properties(ial: &[Vec<String>]) -> HashMap<String, String> {
+    ial.iter()
+        .filter_map(|pair| match pair.as_slice() {
+            [key, value] => Some((key.clone(), value.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// 将 IAL 设置到节点上, 合并到已有属性中, 块的 ID 取自 `id` 属性
+pub fn set_ial(node: &mut Node, ial: Vec<Vec<String>>) {
+    let properties = node.properties.get_or_insert_with(HashMap::new);
+    properties.extend(ial_properties(&ial));
+    if let Some(id) = properties.get("id") {
+        node.id = Some(id.clone());
+    }
+    node.kramdown_ial = ial;
+}
+
+/// 转义属性值中的引号、尖括号与换行. 已经是实体的 `&...;` 保持不变, 所以可以重复转义
+pub fn escape_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for (i, c) in value.char_indices() {
+        match c {
+            '"' => escaped.push_str("&quot;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\n' => escaped.push_str("_esc_newline_"),
+            '&' if !starts_with_entity(&value[i..]) => escaped.push_str("&amp;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// `&name;`, `&#123;` 或 `&#x7B;`
+fn starts_with_entity(s: &str) -> bool {
+    let Some(end) = s.find(';') else {
+        return false;
+    };
+    let name = &s[1..end];
+    match name.strip_prefix('#') {
+        Some(num) => match num.strip_prefix(['x', 'X']) {
+            Some(hex) => !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+            None => !num.is_empty() && num.chars().all(|c| c.is_ascii_digit()),
+        },
+        None => {
+            name.starts_with(|c: char| c.is_ascii_alphabetic())
+                && name.chars().all(|c| c.is_ascii_alphanumeric())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ial() {
+        let text = b"{: id=\"20240301100000-aaaaaaa\" style=\"color: red;\"}text";
+        let (ial, len) = parse_ial(text).unwrap();
+        assert_eq!(len, text.len() - 4);
+        assert_eq!(
+            ial,
+            vec![
+                vec!["id".to_string(), "20240301100000-aaaaaaa".to_string()],
+                vec!["style".to_string(), "color: red;".to_string()],
+            ]
+        );
+        assert_eq!(
+            parse_ial_line(b"  {:custom-a=\"&quot;x&quot;\"}  "),
+            Some(vec![vec![
+                "custom-a".to_string(),
+                "&quot;x&quot;".to_string()
+            ]])
+        );
+        for text in [
+            "{: }",
+            "{: id=x}",
+            "{: id=\"x\"",
+            "{: a=\"1\"b=\"2\"}",
+            "{: a=\"1\"} b",
+            "{ a=\"1\"}",
+        ] {
+            assert_eq!(parse_ial_line(text.as_bytes()), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn test_ial_text() {
+        let properties = HashMap::from([
+            ("updated".to_string(), "20240301100000".to_string()),
+            ("memo".to_string(), "a \"b\" <c>\n&amp; d & e".to_string()),
+            ("id".to_string(), "20240301100000-aaaaaaa".to_string()),
+        ]);
+        let text = ial_text(&properties_ial(&properties));
+        assert_eq!(
+            text,
+            "{: id=\"20240301100000-aaaaaaa\" memo=\"a &quot;b&quot; &lt;c&gt;_esc_newline_&amp; d &amp; e\" \
+             updated=\"20240301100000\"}"
+        );
+        // 转义后的值再次输出不变
+        let ial = parse_ial_line(text.as_bytes()).unwrap();
+        assert_eq!(ial_text(&ial), text);
+        let mut node = Node::default();
+        set_ial(&mut node, ial);
+        assert_eq!(node.id.as_deref(), Some("20240301100000-aaaaaaa"));
+        assert_eq!(node.properties.unwrap().len(), 3);
+    }
+}
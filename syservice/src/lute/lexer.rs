@@ -1,8 +1,63 @@
-use serde_bytes::ByteBuf;
-
+/// 将输入切分为行.
+///
+/// `\r\n`, `\r` 与 `\n` 都作为换行, 返回的行不包含换行符;
+/// `\0` 按 CommonMark 的要求替换为 U+FFFD.
 pub struct Lexer {
-    input: ByteBuf,
+    input: Vec<u8>,
     length: usize,
     offset: usize,
-    width: usize,
+}
+
+impl Lexer {
+    pub fn new(input: &str) -> Self {
+        let input = if input.contains('\0') {
+            input.replace('\0', "\u{fffd}").into_bytes()
+        } else {
+            input.as_bytes().to_vec()
+        };
+        Self {
+            length: input.len(),
+            input,
+            offset: 0,
+        }
+    }
+
+    /// 下一行, 输入以换行结尾时不会额外返回一个空行
+    pub fn next_line(&mut self) -> Option<&[u8]> {
+        if self.offset >= self.length {
+            return None;
+        }
+        let start = self.offset;
+        let rest = &self.input[start..];
+        match rest.iter().position(|&b| b == b'\n' || b == b'\r') {
+            Some(end) => {
+                let width = if rest[end] == b'\r' && rest.get(end + 1) == Some(&b'\n') {
+                    2
+                } else {
+                    1
+                };
+                self.offset = start + end + width;
+                Some(&self.input[start..start + end])
+            }
+            None => {
+                self.offset = self.length;
+                Some(&self.input[start..])
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_line() {
+        let mut lexer = Lexer::new("a\r\nb\rc\n\nd\0\n");
+        let mut lines = Vec::new();
+        while let Some(line) = lexer.next_line() {
+            lines.push(String::from_utf8(line.to_vec()).unwrap());
+        }
+        assert_eq!(lines, ["a", "b", "c", "", "d\u{fffd}"]);
+    }
 }
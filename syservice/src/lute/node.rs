@@ -7,16 +7,18 @@
 //         http://license.coscl.org.cn/MulanPSL2
 // THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
 // See the Mulan PSL v2 for more details.
+use crate::lute::walk::{walk_mut, WalkStatus};
 use chrono::Local;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use serde_bytes::ByteBuf;
-use std::collections::{HashMap, HashSet};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
 use std::str::FromStr;
-use strum::EnumString;
+use strum::{EnumString, IntoStaticStr};
 
 // type NodeType = i32;
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct Node {
     #[serde(rename = "ID", skip_serializing_if = "Option::is_none")]
@@ -34,24 +36,7 @@ pub struct Node {
     #[serde(skip)]
     pub node_type: NodeType,
 
-    #[serde(skip)]
-    pub parent: Option<Box<Node>>,
-
-    #[serde(skip)]
-    pub previous: Option<Box<Node>>,
-
-    #[serde(skip)]
-    pub next: Option<Box<Node>>,
-
-    #[serde(skip)]
-    pub first_child: Option<Box<Node>>,
-
-    #[serde(skip)]
-    pub last_child: Option<Box<Node>>,
-
-    #[serde(rename = "Children", default, skip_serializing_if = "Vec::is_empty")]
-    pub children: Vec<Node>,
-
+    /// 解析时的原始字节, 文件中保存在 `Data`
     #[serde(skip)]
     pub tokens: ByteBuf,
 
@@ -89,13 +74,25 @@ pub struct Node {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub code_block_fence_offset: Option<i32>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        with = "crate::lute::sy::base64_bytes",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub code_block_open_fence: Option<ByteBuf>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        with = "crate::lute::sy::base64_bytes",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub code_block_info: Option<ByteBuf>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        with = "crate::lute::sy::base64_bytes",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub code_block_close_fence: Option<ByteBuf>,
 
     // HTML 块
@@ -127,7 +124,11 @@ pub struct Node {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub link_type: Option<i32>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        with = "crate::lute::sy::base64_bytes",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub link_ref_label: Option<ByteBuf>,
 
     // 标题
@@ -137,7 +138,7 @@ pub struct Node {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub heading_setext: Option<bool>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "HeadingNormalizedID", skip_serializing_if = "Option::is_none")]
     pub heading_normalized_id: Option<String>,
 
     // 数学公式块
@@ -145,7 +146,11 @@ pub struct Node {
     pub math_block_dollar_offset: Option<i32>,
 
     // 脚注
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        with = "crate::lute::sy::base64_bytes",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub footnotes_ref_label: Option<ByteBuf>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -155,14 +160,24 @@ pub struct Node {
     pub footnotes_refs: Vec<Node>,
 
     // HTML 实体
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        with = "crate::lute::sy::base64_bytes",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub html_entity_tokens: Option<ByteBuf>,
 
     // 属性
+    /// 文件中保存在 `Properties`, 与内核一致不单独输出
     #[serde(skip)]
     pub kramdown_ial: Vec<Vec<String>>,
 
-    #[serde(rename = "Properties", skip_serializing_if = "Option::is_none")]
+    #[serde(
+        rename = "Properties",
+        default,
+        serialize_with = "crate::lute::sy::sorted_properties",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub properties: Option<HashMap<String, String>>,
 
     // 文本标记
@@ -181,20 +196,23 @@ pub struct Node {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub text_mark_inline_memo_content: Option<String>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "TextMarkBlockRefID", skip_serializing_if = "Option::is_none")]
     pub text_mark_block_ref_id: Option<String>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub text_mark_block_ref_subtype: Option<String>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        rename = "TextMarkFileAnnotationRefID",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub text_mark_file_annotation_ref_id: Option<String>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub text_mark_text_content: Option<String>,
 
     // 属性视图
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "AttributeViewID", skip_serializing_if = "Option::is_none")]
     pub attribute_view_id: Option<String>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -206,26 +224,193 @@ pub struct Node {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub custom_block_info: Option<String>,
+
+    /// 未建模的字段, 保存时原样写回, 避免丢失新版本内核写入的数据
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+
+    // 子节点, 与内核一致放在最后输出. 需要父节点与兄弟节点时转换为 arena::NodeTree
+    #[serde(rename = "Children", default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Node>,
 }
 
 impl Node {
+    /// 指定类型的空节点
+    pub fn new(node_type: NodeType) -> Self {
+        Self {
+            node_type,
+            type_str: node_type.name().to_string(),
+            ..Self::default()
+        }
+    }
+
     pub fn create_doc_component(&self) {}
     /// 递归设置节点类型
     pub fn set_node_type_for_tree(&mut self) {
-        if let NodeType::Default = self.node_type {
-            self.node_type = NodeType::from_str(&self.type_str).unwrap();
-            for child in &mut self.children {
-                child.set_node_type_for_tree();
+        walk_mut(self, &mut |node, entering| {
+            if !entering {
+                return WalkStatus::Continue;
             }
-        }
+            // 已经设置过的子树不再重复处理
+            if node.node_type != NodeType::Default {
+                return WalkStatus::SkipChildren;
+            }
+            // 更新版本的思源新增的节点类型保持为 `Default`, 由渲染等处按未知节点处理
+            node.node_type = NodeType::from_str(&node.type_str).unwrap_or_default();
+            WalkStatus::Continue
+        });
     }
     
     pub fn has_child(&self) ->bool{
-        self.children.len() > 0
+        !self.children.is_empty()
+    }
+
+    /// 文本标记的类型, 非文本标记节点为空
+    pub fn text_marks(&self) -> TextMarks {
+        self.text_mark_type
+            .as_deref()
+            .map(TextMarks::parse)
+            .unwrap_or_default()
+    }
+
+    /// 文本标记的各个类型, 保留 `TextMarkType` 中的顺序
+    pub fn text_mark_list(&self) -> impl DoubleEndedIterator<Item = TextMarks> + '_ {
+        TextMarks::split(self.text_mark_type.as_deref().unwrap_or_default())
+    }
+
+    /// 仅当文本标记包含 `marks` 时返回对应字段
+    fn text_mark_field<'a>(&self, marks: TextMarks, field: &'a Option<String>) -> Option<&'a str> {
+        field.as_deref().filter(|_| self.text_marks().contains(marks))
+    }
+
+    /// 链接地址
+    pub fn a_href(&self) -> Option<&str> {
+        self.text_mark_field(TextMarks::A, &self.text_mark_a_href)
+    }
+
+    /// 链接标题
+    pub fn a_title(&self) -> Option<&str> {
+        self.text_mark_field(TextMarks::A, &self.text_mark_a_title)
+    }
+
+    /// 被引用块的 ID
+    pub fn block_ref_id(&self) -> Option<&str> {
+        self.text_mark_field(TextMarks::BLOCK_REF, &self.text_mark_block_ref_id)
+    }
+
+    /// 引用的锚文本类型, `s` 为静态, `d` 为动态
+    pub fn block_ref_subtype(&self) -> Option<&str> {
+        self.text_mark_field(TextMarks::BLOCK_REF, &self.text_mark_block_ref_subtype)
+    }
+
+    /// 被引用的 PDF 标注 ID
+    pub fn file_annotation_ref_id(&self) -> Option<&str> {
+        self.text_mark_field(
+            TextMarks::FILE_ANNOTATION_REF,
+            &self.text_mark_file_annotation_ref_id,
+        )
+    }
+
+    /// 行级公式的内容
+    pub fn inline_math_content(&self) -> Option<&str> {
+        self.text_mark_field(TextMarks::INLINE_MATH, &self.text_mark_inline_math_content)
+    }
+
+    /// 行级备注的内容
+    pub fn inline_memo_content(&self) -> Option<&str> {
+        self.text_mark_field(TextMarks::INLINE_MEMO, &self.text_mark_inline_memo_content)
+    }
+}
+
+bitflags::bitflags! {
+    /// 文本标记的类型, `TextMarkType` 中以空格分隔的各项. 未知的类型解析时忽略
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct TextMarks: u32 {
+        const STRONG = 1;
+        const EM = 1 << 1;
+        const U = 1 << 2;
+        const S = 1 << 3;
+        const MARK = 1 << 4;
+        const SUP = 1 << 5;
+        const SUB = 1 << 6;
+        const CODE = 1 << 7;
+        const KBD = 1 << 8;
+        const TAG = 1 << 9;
+        const A = 1 << 10;
+        const BLOCK_REF = 1 << 11;
+        const FILE_ANNOTATION_REF = 1 << 12;
+        const INLINE_MATH = 1 << 13;
+        const INLINE_MEMO = 1 << 14;
+        /// 只有样式 (颜色等) 的文本
+        const TEXT = 1 << 15;
+    }
+}
+
+impl TextMarks {
+    const NAMES: [(TextMarks, &'static str); 16] = [
+        (TextMarks::STRONG, "strong"),
+        (TextMarks::EM, "em"),
+        (TextMarks::U, "u"),
+        (TextMarks::S, "s"),
+        (TextMarks::MARK, "mark"),
+        (TextMarks::SUP, "sup"),
+        (TextMarks::SUB, "sub"),
+        (TextMarks::CODE, "code"),
+        (TextMarks::KBD, "kbd"),
+        (TextMarks::TAG, "tag"),
+        (TextMarks::A, "a"),
+        (TextMarks::BLOCK_REF, "block-ref"),
+        (TextMarks::FILE_ANNOTATION_REF, "file-annotation-ref"),
+        (TextMarks::INLINE_MATH, "inline-math"),
+        (TextMarks::INLINE_MEMO, "inline-memo"),
+        (TextMarks::TEXT, "text"),
+    ];
+
+    /// 解析 `TextMarkType`, 例如 `strong em a`
+    pub fn parse(types: &str) -> Self {
+        Self::split(types).collect()
+    }
+
+    /// 按 `TextMarkType` 中的顺序逐个解析, 渲染时靠前的类型在外层
+    pub fn split(types: &str) -> impl DoubleEndedIterator<Item = Self> + '_ {
+        types.split_ascii_whitespace().filter_map(Self::from_type)
+    }
+
+    /// 单个类型的名称对应的标记
+    pub fn from_type(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(_, n)| *n == name)
+            .map(|(mark, _)| *mark)
+    }
+
+    /// 包含的各类型名称, 按固定顺序
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        Self::NAMES
+            .into_iter()
+            .filter(move |(mark, _)| self.contains(*mark))
+            .map(|(_, name)| name)
+    }
+
+    /// 各自独立的标记, 相邻时不能合并为一个
+    pub fn is_atomic(self) -> bool {
+        self.intersects(
+            TextMarks::CODE
+                | TextMarks::INLINE_MATH
+                | TextMarks::TAG
+                | TextMarks::BLOCK_REF
+                | TextMarks::FILE_ANNOTATION_REF,
+        )
+    }
+}
+
+impl std::fmt::Display for TextMarks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.names().collect::<Vec<_>>().join(" "))
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct ListData {
     #[serde(rename = "Typ", skip_serializing_if = "Option::is_none")]
@@ -254,11 +439,20 @@ pub struct ListData {
     pub checked: Option<bool>, // 任务列表项是否勾选
 
     /// Base64 下的 List 前缀标识
-    #[serde(rename = "Marker", skip_serializing_if = "Option::is_none")]
+    #[serde(
+        rename = "Marker",
+        default,
+        with = "crate::lute::sy::base64_bytes",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub marker: Option<ByteBuf>, // 列表标识符原始字节
 
     #[serde(rename = "Num", skip_serializing_if = "Option::is_none")]
     pub num: Option<i32>, // 有序列表项修正序号
+
+    /// 未建模的字段
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 pub fn new_node_id() -> String {
@@ -271,7 +465,7 @@ pub fn new_node_id() -> String {
 }
 
 /// 生成指定长度的随机字符串（a-z0-9）
-fn rand_str(length: usize) -> String {
+pub(crate) fn rand_str(length: usize) -> String {
     const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
     let mut rng = rand::rng();
 
@@ -318,7 +512,9 @@ pub fn is_node_id_pattern(s: &str) -> bool {
 
     true
 }
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default, EnumString)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default, EnumString, IntoStaticStr,
+)]
 #[repr(i32)]
 #[serde(rename_all = "camelCase")]
 #[strum(serialize_all = "PascalCase")]
@@ -339,7 +535,9 @@ pub enum NodeType {
     NodeList = 7,
     /// 列表项.
     NodeListItem = 8,
+    #[strum(serialize = "NodeHTMLBlock")]
     NodeHtmlBlock = 9,
+    #[strum(serialize = "NodeInlineHTML")]
     NodeInlineHtml = 10,
     NodeCodeBlock = 11,
     NodeCodeBlockFenceOpenMarker = 12,
@@ -374,6 +572,7 @@ pub enum NodeType {
     NodeLinkDest = 41,
     NodeLinkTitle = 42,
     NodeLinkSpace = 43,
+    #[strum(serialize = "NodeHTMLEntity")]
     NodeHtmlEntity = 44,
     NodeLinkRefDefBlock = 45,
     NodeLinkRefDef = 46,
@@ -421,9 +620,11 @@ pub enum NodeType {
     NodeFootnotesRef = 412,
 
     // TOC (415-419)
+    #[strum(serialize = "NodeToC")]
     NodeToc = 415,
 
     // Heading ID (420-424)
+    #[strum(serialize = "NodeHeadingID")]
     NodeHeadingId = 420,
 
     // YAML Front Matter (425-429)
@@ -434,6 +635,7 @@ pub enum NodeType {
 
     // Block Reference (430-449)
     NodeBlockRef = 430,
+    #[strum(serialize = "NodeBlockRefID")]
     NodeBlockRefId = 431,
     NodeBlockRefSpace = 432,
     NodeBlockRefText = 433,
@@ -447,7 +649,9 @@ pub enum NodeType {
     NodeMark2CloseMarker = 454,
 
     // Kramdown IAL (455-459)
+    #[strum(serialize = "NodeKramdownBlockIAL")]
     NodeKramdownBlockIal = 455,
+    #[strum(serialize = "NodeKramdownSpanIAL")]
     NodeKramdownSpanIal = 456,
 
     // Tag (460-464)
@@ -497,6 +701,7 @@ pub enum NodeType {
 
     // File Annotation (540-549)
     NodeFileAnnotationRef = 540,
+    #[strum(serialize = "NodeFileAnnotationRefID")]
     NodeFileAnnotationRefId = 541,
     NodeFileAnnotationRefSpace = 542,
     NodeFileAnnotationRefText = 543,
@@ -508,8 +713,11 @@ pub enum NodeType {
     NodeCustomBlock = 560,
 
     // HTML Tags (570-599)
+    #[strum(serialize = "NodeHTMLTag")]
     NodeHtmlTag = 570,
+    #[strum(serialize = "NodeHTMLTagOpen")]
     NodeHtmlTagOpen = 571,
+    #[strum(serialize = "NodeHTMLTagClose")]
     NodeHtmlTagClose = 572,
 
     // Max Value
@@ -521,34 +729,121 @@ impl From<NodeType> for i32 {
     }
 }
 
-impl Node {
-    pub fn marker(&self, entering: bool) -> &'static str {
-        if !entering {
-            return "";
-        }
-        match self.node_type {
+impl NodeType {
+    /// 内核中的类型名, 即 `.sy` 文件中的 `Type` 字段
+    pub fn name(self) -> &'static str {
+        self.into()
+    }
+
+    /// 是否为块级节点, 即在思源中有块 ID 的节点
+    pub fn is_block(self) -> bool {
+        matches!(
+            self,
+            NodeType::NodeDocument
+                | NodeType::NodeParagraph
+                | NodeType::NodeHeading
+                | NodeType::NodeThematicBreak
+                | NodeType::NodeBlockquote
+                | NodeType::NodeList
+                | NodeType::NodeListItem
+                | NodeType::NodeHtmlBlock
+                | NodeType::NodeCodeBlock
+                | NodeType::NodeTable
+                | NodeType::NodeMathBlock
+                | NodeType::NodeFootnotesDefBlock
+                | NodeType::NodeFootnotesDef
+                | NodeType::NodeYamlFrontMatter
+                | NodeType::NodeBlockQueryEmbed
+                | NodeType::NodeSuperBlock
+                | NodeType::NodeGitConflict
+                | NodeType::NodeIFrame
+                | NodeType::NodeAudio
+                | NodeType::NodeVideo
+                | NodeType::NodeWidget
+                | NodeType::NodeAttributeView
+                | NodeType::NodeCustomBlock
+        )
+    }
+
+    /// 标识符节点对应的 Markdown 标记, 其他节点为空串
+    pub fn marker(self) -> &'static str {
+        match self {
             NodeType::NodeTagOpenMarker | NodeType::NodeTagCloseMarker => "#",
             NodeType::NodeEmA6kOpenMarker | NodeType::NodeEmA6kCloseMarker => "*",
             NodeType::NodeEmU8eOpenMarker | NodeType::NodeEmU8eCloseMarker => "_",
             NodeType::NodeStrongA6kOpenMarker | NodeType::NodeStrongA6kCloseMarker => "**",
             NodeType::NodeStrongU8eOpenMarker | NodeType::NodeStrongU8eCloseMarker => "__",
+            NodeType::NodeStrikethrough1OpenMarker | NodeType::NodeStrikethrough1CloseMarker => {
+                "~"
+            }
             NodeType::NodeStrikethrough2OpenMarker | NodeType::NodeStrikethrough2CloseMarker => {
                 "~~"
             }
             NodeType::NodeSupOpenMarker | NodeType::NodeSupCloseMarker => "^",
             NodeType::NodeSubOpenMarker | NodeType::NodeSubCloseMarker => "~",
             NodeType::NodeInlineMathOpenMarker | NodeType::NodeInlineMathCloseMarker => "$",
+            NodeType::NodeMathBlockOpenMarker | NodeType::NodeMathBlockCloseMarker => "$$",
+            NodeType::NodeCodeSpanOpenMarker | NodeType::NodeCodeSpanCloseMarker => "`",
+            NodeType::NodeCodeBlockFenceOpenMarker | NodeType::NodeCodeBlockFenceCloseMarker => {
+                "```"
+            }
             NodeType::NodeKbdOpenMarker => "<kbd>",
             NodeType::NodeKbdCloseMarker => "</kbd>",
             NodeType::NodeUnderlineOpenMarker => "<u>",
             NodeType::NodeUnderlineCloseMarker => "</u>",
+            NodeType::NodeMark1OpenMarker | NodeType::NodeMark1CloseMarker => "=",
             NodeType::NodeMark2OpenMarker | NodeType::NodeMark2CloseMarker => "==",
+            NodeType::NodeBlockquoteMarker => ">",
+            NodeType::NodeSuperBlockOpenMarker => "{{{",
+            NodeType::NodeSuperBlockCloseMarker => "}}}",
+            NodeType::NodeYamlFrontMatterOpenMarker | NodeType::NodeYamlFrontMatterCloseMarker => {
+                "---"
+            }
+            NodeType::NodeOpenBrace => "{{",
+            NodeType::NodeCloseBrace => "}}",
+            NodeType::NodeBackslash => "\\",
             NodeType::NodeBang => "!",
             NodeType::NodeOpenBracket => "[",
             NodeType::NodeCloseBracket => "]",
             NodeType::NodeOpenParen => "(",
             NodeType::NodeCloseParen => ")",
+            NodeType::NodeLess => "<",
+            NodeType::NodeGreater => ">",
             _ => "",
         }
     }
 }
+
+impl Node {
+    /// 进入标识符节点时输出的标记, 离开时为空串
+    pub fn marker(&self, entering: bool) -> &'static str {
+        if entering {
+            self.node_type.marker()
+        } else {
+            ""
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_marks() {
+        let marks = TextMarks::parse("a strong  em unknown");
+        assert_eq!(marks, TextMarks::A | TextMarks::STRONG | TextMarks::EM);
+        assert_eq!(marks.to_string(), "strong em a");
+        assert!(!marks.is_atomic());
+        assert!(TextMarks::parse("code em").is_atomic());
+        assert_eq!(TextMarks::parse(""), TextMarks::empty());
+
+        let mut node = Node::new(NodeType::NodeTextMark);
+        node.text_mark_type = Some("block-ref".to_string());
+        node.text_mark_block_ref_id = Some("20240301110000-aaaaaaa".to_string());
+        node.text_mark_a_href = Some("https://b3log.org".to_string());
+        assert_eq!(node.block_ref_id(), Some("20240301110000-aaaaaaa"));
+        // 字段与类型不符时忽略
+        assert_eq!(node.a_href(), None);
+    }
+}
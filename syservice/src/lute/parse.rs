@@ -1,71 +1,49 @@
-use crate::lute::delimiter::Delimiter;
+//! Markdown 块级结构解析, 移植自 Lute 的 parse 包 (即 commonmark.js 的 `blocks.js`).
+//!
+//! 逐行读入文本, 先确定块结构, 全部行处理完后再解析段落、标题与单元格中的行级内容.
+use crate::lute::arena::{NodeKey, NodeTree};
+use crate::lute::ial::set_ial;
 use crate::lute::lexer::Lexer;
-use crate::lute::node::Node;
+use crate::lute::node::{Node, NodeType};
+use crate::lute::{walk_mut, WalkStatus};
 use chrono::{DateTime, Utc};
-use serde_bytes::ByteBuf;
+use slotmap::SecondaryMap;
 use std::collections::HashMap;
 use std::path::PathBuf;
 
+mod blocks;
+mod delimiter;
+pub(crate) mod escape;
+pub(crate) mod html_block;
+mod inline;
+mod link_ref_def;
+mod table;
+
+pub use link_ref_def::LinkRefDef;
+
+/// Markdown 的 tab 宽度
+const TAB_STOP: usize = 4;
+/// 缩进达到该宽度时为缩进代码块
+const CODE_INDENT: usize = 4;
+
+/// 解析结果
 pub struct Tree {
-    // 使用 Option 表示可能为空的字段
-    pub root: Option<Node>,
-    pub context: Box<Option<Context>>,
-    pub lexer: Option<Lexer>,
-    pub inline_context: Option<InlineContext>,
+    pub root: Node,
 
-    // 字符串类型字段
     pub name: String,
     pub id: String,
-    pub container: String,  // 原Box字段，Rust中避免使用关键字命名
-    pub path: PathBuf,      // 更适合路径处理的类型
-    pub hpath: String,      // 人类可读路径
-    pub marks: Vec<String>, // 字符串数组
+    pub container: String,
+    pub path: PathBuf,
+    pub hpath: String,
+    pub marks: Vec<String>,
 
-    // 时间处理（使用chrono库）
     pub created: DateTime<Utc>,
     pub updated: DateTime<Utc>,
 
-    // 哈希值
     pub hash: String,
-}
-
-pub struct Context {
-    // 使用智能指针管理循环引用
-    tree: Tree,                     // 使用Arc实现多所有权
-    parse_options: Option<Options>, // 可空的解析选项
-
-    // 节点引用
-    tip: Node,
-    old_tip: Node, // Rust命名规范使用下划线
 
-    // 行数据
-    current_line: Vec<u8>,   // 使用Vec<u8>替代[]byte
-    current_line_len: usize, // Rust用usize替代int
-
-    // 解析状态
-    offset: usize,
-    column: usize,
-    next_nonspace: usize,
-    next_nonspace_column: usize,
-    indent: usize,
-
-    // 状态标志
-    indented: bool,
-    blank: bool,
-    partially_consumed_tab: bool,
-    all_closed: bool,
-
-    // 容器节点
-    last_matched_container: Node,
-    root_ial: Node, // 使用Option明确表示可空
-}
-
-pub struct InlineContext {
-    tokens: ByteBuf, // 当前解析的 Tokens（生命周期管理）
-    tokens_len: usize,
-    pos: usize,            // 当前解析位置
-    delimiters: Delimiter, // 分隔符栈（使用VecDeque实现高效两端操作）
-    brackets: Delimiter,   // 括号栈
+    /// 链接引用定义, key 为规范化后的标签
+    pub link_refs: HashMap<String, LinkRefDef>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -89,6 +67,8 @@ pub struct Options {
     pub file_annotation_ref: bool,
     pub super_block: bool,
     pub indent_code_block: bool,
+    /// MathBlock 设置是否打开 `$$` 数学公式块支持。
+    pub math_block: bool,
 
     // 行内元素扩展
     pub inline_math: bool,
@@ -133,3 +113,614 @@ pub struct Options {
     pub vditor_sv: bool,
     pub protyle_wysiwyg: bool,
 }
+
+impl Options {
+    /// 只包含 CommonMark 规范中的语法
+    pub fn commonmark() -> Self {
+        Self {
+            indent_code_block: true,
+            setext_heading: true,
+            link_ref: true,
+            ..Self::default()
+        }
+    }
+
+    /// CommonMark 加上 GFM 扩展
+    pub fn gfm() -> Self {
+        Self {
+            gfm_table: true,
+            gfm_task_list_item: true,
+            gfm_strikethrough: true,
+            gfm_strikethrough_single: true,
+            gfm_auto_link: true,
+            ..Self::commonmark()
+        }
+    }
+
+    /// 思源编辑器使用的语法
+    pub fn siyuan() -> Self {
+        Self {
+            super_block: true,
+            math_block: true,
+            inline_math: true,
+            mark: true,
+            sup: true,
+            sub: true,
+            tag: true,
+            block_ref: true,
+            file_annotation_ref: true,
+            kramdown_block_ial: true,
+            kramdown_span_ial: true,
+            text_mark: true,
+            // 单个 `~` 为下标
+            gfm_strikethrough_single: false,
+            ..Self::gfm()
+        }
+    }
+}
+
+/// 解析 Markdown 文本
+pub fn parse(name: &str, markdown: &str, options: &Options) -> Tree {
+    let mut context = Context::new(options);
+    let mut lexer = Lexer::new(markdown);
+    while let Some(line) = lexer.next_line() {
+        context.incorporate_line(line);
+    }
+    let mut root = context.finish();
+    if options.kramdown_block_ial {
+        attach_block_ials(&mut root);
+    }
+    let link_refs = std::mem::take(&mut context.link_refs);
+    parse_inlines(&mut root, options, &link_refs);
+    let now = Utc::now();
+    Tree {
+        root,
+        name: name.to_string(),
+        id: String::new(),
+        container: String::new(),
+        path: PathBuf::new(),
+        hpath: String::new(),
+        marks: Vec::new(),
+        created: now,
+        updated: now,
+        hash: String::new(),
+        link_refs,
+    }
+}
+
+/// 块级解析的状态
+struct Context<'a> {
+    options: &'a Options,
+    tree: NodeTree,
+    link_refs: HashMap<String, LinkRefDef>,
+    /// 块开始的行号, 用于判断空列表项
+    start_lines: SecondaryMap<NodeKey, usize>,
+
+    /// 最深的未关闭块
+    tip: NodeKey,
+    old_tip: NodeKey,
+    last_matched_container: NodeKey,
+
+    current_line: Vec<u8>,
+    line_number: usize,
+    offset: usize,
+    column: usize,
+    next_nonspace: usize,
+    next_nonspace_column: usize,
+    indent: usize,
+    indented: bool,
+    blank: bool,
+    partially_consumed_tab: bool,
+    all_closed: bool,
+    last_line_length: usize,
+}
+
+impl<'a> Context<'a> {
+    fn new(options: &'a Options) -> Self {
+        let tree = NodeTree::from_node(Node::new(NodeType::NodeDocument));
+        let root = tree.root();
+        Self {
+            options,
+            tree,
+            link_refs: HashMap::new(),
+            start_lines: SecondaryMap::new(),
+            tip: root,
+            old_tip: root,
+            last_matched_container: root,
+            current_line: Vec::new(),
+            line_number: 0,
+            offset: 0,
+            column: 0,
+            next_nonspace: 0,
+            next_nonspace_column: 0,
+            indent: 0,
+            indented: false,
+            blank: false,
+            partially_consumed_tab: false,
+            all_closed: true,
+            last_line_length: 0,
+        }
+    }
+
+    fn node(&self, key: NodeKey) -> &Node {
+        self.tree.get(key).expect("node is in the tree")
+    }
+
+    fn node_mut(&mut self, key: NodeKey) -> &mut Node {
+        self.tree.get_mut(key).expect("node is in the tree")
+    }
+
+    fn node_type(&self, key: NodeKey) -> NodeType {
+        self.node(key).node_type
+    }
+
+    /// 处理一行输入
+    fn incorporate_line(&mut self, line: &[u8]) {
+        self.current_line.clear();
+        self.current_line.extend_from_slice(line);
+        self.line_number += 1;
+        self.offset = 0;
+        self.column = 0;
+        self.blank = false;
+        self.partially_consumed_tab = false;
+        self.old_tip = self.tip;
+
+        // 先尝试延续已打开的块
+        let mut container = self.tree.root();
+        while let Some(last) = self.tree.last_child(container) {
+            if self.node(last).close {
+                break;
+            }
+            container = last;
+            self.find_next_nonspace();
+            match self.continue_block(container) {
+                blocks::Continue::Matched => {}
+                blocks::Continue::NotMatched => {
+                    container = self.tree.parent(container).unwrap();
+                    break;
+                }
+                blocks::Continue::LineHandled => {
+                    self.last_line_length = self.current_line.len();
+                    return;
+                }
+            }
+        }
+
+        self.all_closed = container == self.old_tip;
+        self.last_matched_container = container;
+
+        // 再尝试开始新的块
+        let mut matched_leaf = {
+            let node_type = self.node_type(container);
+            node_type != NodeType::NodeParagraph && blocks::accepts_lines(node_type)
+        };
+        while !matched_leaf {
+            self.find_next_nonspace();
+            // 不可能开始新块时直接跳过
+            if !self.indented && !blocks::maybe_special(self.peek(self.next_nonspace)) {
+                self.advance_next_nonspace();
+                break;
+            }
+            match self.try_block_starts(container) {
+                blocks::Start::Container => container = self.tip,
+                blocks::Start::Leaf => {
+                    container = self.tip;
+                    matched_leaf = true;
+                }
+                blocks::Start::None => {
+                    self.advance_next_nonspace();
+                    break;
+                }
+            }
+        }
+
+        // 剩余部分作为文本加入当前块
+        if !self.all_closed && !self.blank && self.node_type(self.tip) == NodeType::NodeParagraph {
+            // 段落的惰性延续行
+            self.add_line();
+        } else {
+            self.close_unmatched_blocks();
+            let node_type = self.node_type(container);
+            if self.blank {
+                if let Some(last) = self.tree.last_child(container) {
+                    self.node_mut(last).last_line_blank = true;
+                }
+            }
+
+            // 空行之后的块是否以空行结尾, 用于计算列表是否紧凑
+            let last_line_blank = self.blank
+                && !match node_type {
+                    NodeType::NodeBlockquote | NodeType::NodeMathBlock => true,
+                    NodeType::NodeCodeBlock => {
+                        self.node(container).is_fenced_code_block == Some(true)
+                    }
+                    NodeType::NodeListItem => {
+                        self.tree.first_child(container).is_none()
+                            && self.start_lines.get(container) == Some(&self.line_number)
+                    }
+                    _ => false,
+                };
+            let mut key = Some(container);
+            while let Some(current) = key {
+                self.node_mut(current).last_line_blank = last_line_blank;
+                key = self.tree.parent(current);
+            }
+
+            if blocks::accepts_lines(node_type) {
+                self.add_line();
+                if node_type == NodeType::NodeHtmlBlock {
+                    let html_block_type = self.node(container).html_block_type.unwrap_or(0);
+                    let rest = &self.current_line[self.offset..];
+                    if html_block::is_end(html_block_type, rest) {
+                        self.last_line_length = self.current_line.len();
+                        self.finalize(container);
+                    }
+                }
+            } else if self.offset < self.current_line.len() && !self.blank {
+                // 其余情况开始新段落
+                self.add_child(Node::new(NodeType::NodeParagraph));
+                self.advance_next_nonspace();
+                self.add_line();
+            }
+        }
+        self.last_line_length = self.current_line.len();
+    }
+
+    /// 所有行处理完后关闭全部块, 返回文档树
+    fn finish(&mut self) -> Node {
+        let root = self.tree.root();
+        loop {
+            let tip = self.tip;
+            self.finalize(tip);
+            if tip == root {
+                break;
+            }
+        }
+        self.tree.to_node()
+    }
+
+    fn peek(&self, offset: usize) -> Option<u8> {
+        self.current_line.get(offset).copied()
+    }
+
+    /// 定位下一个非空白字符, 同时计算缩进
+    fn find_next_nonspace(&mut self) {
+        let mut i = self.offset;
+        let mut cols = self.column;
+        while let Some(c) = self.peek(i) {
+            match c {
+                b' ' => cols += 1,
+                b'\t' => cols += TAB_STOP - cols % TAB_STOP,
+                _ => break,
+            }
+            i += 1;
+        }
+        self.blank = i == self.current_line.len();
+        self.next_nonspace = i;
+        self.next_nonspace_column = cols;
+        self.indent = cols - self.column;
+        self.indented = self.indent >= CODE_INDENT;
+    }
+
+    /// 前进 `count` 个字符或列, tab 可能只消耗一部分
+    fn advance_offset(&mut self, mut count: usize, columns: bool) {
+        while count > 0 {
+            let Some(c) = self.peek(self.offset) else {
+                break;
+            };
+            if c == b'\t' {
+                let chars_to_tab = TAB_STOP - self.column % TAB_STOP;
+                if columns {
+                    self.partially_consumed_tab = chars_to_tab > count;
+                    let chars_to_advance = chars_to_tab.min(count);
+                    self.column += chars_to_advance;
+                    if !self.partially_consumed_tab {
+                        self.offset += 1;
+                    }
+                    count -= chars_to_advance;
+                } else {
+                    self.partially_consumed_tab = false;
+                    self.column += chars_to_tab;
+                    self.offset += 1;
+                    count -= 1;
+                }
+            } else {
+                self.partially_consumed_tab = false;
+                self.offset += 1;
+                self.column += 1;
+                count -= 1;
+            }
+        }
+    }
+
+    fn advance_next_nonspace(&mut self) {
+        self.offset = self.next_nonspace;
+        self.column = self.next_nonspace_column;
+        self.partially_consumed_tab = false;
+    }
+
+    /// 将当前行剩余部分追加到 tip 的 tokens 中
+    fn add_line(&mut self) {
+        let mut line = Vec::new();
+        if self.partially_consumed_tab {
+            // 被部分消耗的 tab 剩余的列以空格补齐
+            self.offset += 1;
+            let chars_to_tab = TAB_STOP - self.column % TAB_STOP;
+            line.resize(chars_to_tab, b' ');
+        }
+        line.extend_from_slice(&self.current_line[self.offset.min(self.current_line.len())..]);
+        line.push(b'\n');
+        let tip = self.tip;
+        self.node_mut(tip).tokens.extend_from_slice(&line);
+    }
+
+    /// 在 tip 下添加子块, tip 不能包含该块时先关闭 tip
+    fn add_child(&mut self, node: Node) -> NodeKey {
+        while !blocks::can_contain(self.node_type(self.tip), node.node_type) {
+            let tip = self.tip;
+            self.finalize(tip);
+        }
+        let key = self.tree.append_child(self.tip, node);
+        self.start_lines.insert(key, self.line_number);
+        self.tip = key;
+        key
+    }
+
+    /// 添加标记节点, 标记节点不参与块的延续
+    fn append_marker(&mut self, parent: NodeKey, mut marker: Node) -> NodeKey {
+        marker.close = true;
+        self.tree.append_child(parent, marker)
+    }
+
+    /// 关闭未能延续的块
+    fn close_unmatched_blocks(&mut self) {
+        if self.all_closed {
+            return;
+        }
+        while self.old_tip != self.last_matched_container {
+            let old_tip = self.old_tip;
+            let parent = self.tree.parent(old_tip).unwrap();
+            self.finalize(old_tip);
+            self.old_tip = parent;
+        }
+        self.all_closed = true;
+    }
+
+    /// 关闭块, tip 移动到其父块
+    fn finalize(&mut self, key: NodeKey) {
+        let parent = self.tree.parent(key);
+        self.node_mut(key).close = true;
+        self.finalize_block(key);
+        self.tip = parent.unwrap_or(key);
+    }
+}
+
+/// 块 IAL 设置到前一个块上并从树中移除. 文档的 IAL 在最后, 紧跟最后一个块的 IAL 之后
+fn attach_block_ials(node: &mut Node) {
+    let mut children = Vec::with_capacity(node.children.len());
+    // 尚未设置 IAL 的前一个块
+    let mut last_block = None;
+    let mut document_ial = None;
+    for mut child in std::mem::take(&mut node.children) {
+        if child.node_type != NodeType::NodeKramdownBlockIal {
+            attach_block_ials(&mut child);
+            if child.node_type.is_block() {
+                last_block = Some(children.len());
+            }
+            children.push(child);
+            continue;
+        }
+        let ial = std::mem::take(&mut child.kramdown_ial);
+        match last_block.take() {
+            Some(index) => set_ial(&mut children[index], ial),
+            None if node.node_type == NodeType::NodeDocument => document_ial = Some(ial),
+            None => {}
+        }
+    }
+    node.children = children;
+    if let Some(ial) = document_ial {
+        set_ial(node, ial);
+    }
+}
+
+/// 解析段落、标题与单元格的行级内容
+fn parse_inlines(root: &mut Node, options: &Options, link_refs: &HashMap<String, LinkRefDef>) {
+    walk_mut(root, &mut |node, entering| {
+        if !entering {
+            return WalkStatus::Continue;
+        }
+        match node.node_type {
+            NodeType::NodeParagraph | NodeType::NodeHeading | NodeType::NodeTableCell => {
+                let tokens = std::mem::take(&mut node.tokens);
+                node.children.extend(inline::parse_inline(
+                    tokens.trim_ascii(),
+                    options,
+                    link_refs,
+                ));
+                WalkStatus::SkipChildren
+            }
+            _ => WalkStatus::Continue,
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lute::render::{render_html, HtmlOptions};
+    use serde::Deserialize;
+    use std::fs;
+    use std::path::Path;
+
+    #[derive(Deserialize)]
+    struct Example {
+        markdown: String,
+        html: String,
+        example: usize,
+        section: String,
+    }
+
+    fn to_html(markdown: &str) -> String {
+        let tree = parse("spec", markdown, &Options::commonmark());
+        render_html(&tree.root, &HtmlOptions::default())
+    }
+
+    #[test]
+    fn test_commonmark_spec() {
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/resources/commonmark/spec.json");
+        let examples: Vec<Example> = serde_json::from_slice(&fs::read(path).unwrap()).unwrap();
+        let failed = examples
+            .iter()
+            .filter(|example| to_html(&example.markdown) != example.html)
+            .map(|example| {
+                eprintln!(
+                    "example {} ({}): {:?}\n  expected {:?}\n  actual   {:?}",
+                    example.example,
+                    example.section,
+                    example.markdown,
+                    example.html,
+                    to_html(&example.markdown)
+                );
+                example.example
+            })
+            .collect::<Vec<_>>();
+        assert!(failed.is_empty(), "failed examples: {failed:?}");
+        assert_eq!(examples.len(), 652);
+    }
+
+    fn parse_siyuan(markdown: &str) -> Node {
+        parse("test", markdown, &Options::siyuan()).root
+    }
+
+    fn types(node: &Node) -> Vec<&'static str> {
+        node.children
+            .iter()
+            .map(|child| child.node_type.name())
+            .collect()
+    }
+
+    #[test]
+    fn test_list_data() {
+        let root = parse_siyuan("3) a\n4) b\n\n* [x] done\n* [ ] todo\n");
+        let list = root.children[0].list_data.as_ref().unwrap();
+        assert_eq!(list.typ, Some(1));
+        assert_eq!(list.start, Some(3));
+        assert_eq!(list.delimiter, Some(b')'));
+        assert_eq!(list.padding, Some(3));
+        assert_eq!(list.tight, Some(true));
+        let second = root.children[0].children[1].list_data.as_ref().unwrap();
+        assert_eq!(second.num, Some(4));
+        assert_eq!(
+            second.marker.as_deref().map(Vec::as_slice),
+            Some(&b"4)"[..])
+        );
+
+        let tasks = &root.children[1];
+        assert_eq!(tasks.list_data.as_ref().unwrap().typ, Some(3));
+        let item = &tasks.children[0];
+        assert_eq!(item.list_data.as_ref().unwrap().checked, Some(true));
+        let paragraph = &item.children[0];
+        assert_eq!(types(paragraph), ["NodeTaskListItemMarker", "NodeText"]);
+        assert_eq!(paragraph.children[0].data.as_deref(), Some("[x]"));
+        assert_eq!(paragraph.children[1].data.as_deref(), Some("done"));
+        let todo = tasks.children[1].list_data.as_ref().unwrap();
+        assert_eq!(todo.checked, Some(false));
+    }
+
+    #[test]
+    fn test_table() {
+        let root = parse_siyuan("| a | b \\| c |\n|:-|--:|\n| 1 |\n\npara\n");
+        assert_eq!(types(&root), ["NodeTable", "NodeParagraph"]);
+        let table = &root.children[0];
+        assert_eq!(table.table_aligns, [1, 3]);
+        assert_eq!(types(table), ["NodeTableHead", "NodeTableRow"]);
+        let head = &table.children[0].children[0];
+        assert_eq!(head.children[1].data.as_deref(), Some("th"));
+        assert_eq!(head.children[1].children[0].data.as_deref(), Some("b | c"));
+        let row = &table.children[1];
+        assert_eq!(row.children[1].table_cell_align, Some(3));
+        assert!(row.children[1].children.is_empty());
+
+        // CommonMark 下没有表格
+        let root = parse("test", "| a |\n| - |\n", &Options::commonmark()).root;
+        assert_eq!(types(&root), ["NodeParagraph"]);
+    }
+
+    #[test]
+    fn test_code_and_math_blocks() {
+        let root =
+            parse_siyuan("```rust  extra\nfn main() {}\n```\n\n$$\na^2\n$$\n\n    indented\n");
+        let code = &root.children[0];
+        assert_eq!(
+            types(code),
+            [
+                "NodeCodeBlockFenceOpenMarker",
+                "NodeCodeBlockFenceInfoMarker",
+                "NodeCodeBlockCode",
+                "NodeCodeBlockFenceCloseMarker"
+            ]
+        );
+        assert_eq!(
+            code.code_block_info.as_deref().map(Vec::as_slice),
+            Some(&b"rust  extra"[..])
+        );
+        assert_eq!(code.children[0].data.as_deref(), Some("```"));
+        assert_eq!(code.children[2].data.as_deref(), Some("fn main() {}\n"));
+
+        let math = &root.children[1];
+        assert_eq!(
+            types(math),
+            [
+                "NodeMathBlockOpenMarker",
+                "NodeMathBlockContent",
+                "NodeMathBlockCloseMarker"
+            ]
+        );
+        assert_eq!(math.children[1].data.as_deref(), Some("a^2"));
+
+        let indented = &root.children[2];
+        assert_eq!(indented.is_fenced_code_block, Some(false));
+        assert_eq!(indented.children[0].data.as_deref(), Some("indented\n"));
+    }
+
+    #[test]
+    fn test_super_block() {
+        let root = parse_siyuan("{{{col\n# a\n\n```\n}}}\n```\n}}}\nafter\n");
+        assert_eq!(types(&root), ["NodeSuperBlock", "NodeParagraph"]);
+        let super_block = &root.children[0];
+        assert_eq!(
+            types(super_block),
+            [
+                "NodeSuperBlockOpenMarker",
+                "NodeSuperBlockLayoutMarker",
+                "NodeHeading",
+                "NodeCodeBlock",
+                "NodeSuperBlockCloseMarker"
+            ]
+        );
+        assert_eq!(super_block.children[1].data.as_deref(), Some("col"));
+        assert_eq!(
+            super_block.children[3].children[2].data.as_deref(),
+            Some("}}}\n")
+        );
+    }
+
+    #[test]
+    fn test_blockquote_and_link_refs() {
+        let tree = parse("test", "> [a]: /url 'T'\n> quoted\nlazy\n", &Options::gfm());
+        let blockquote = &tree.root.children[0];
+        assert_eq!(types(blockquote), ["NodeBlockquoteMarker", "NodeParagraph"]);
+        let paragraph = &blockquote.children[1];
+        assert_eq!(types(paragraph), ["NodeText", "NodeSoftBreak", "NodeText"]);
+        assert_eq!(paragraph.children[0].data.as_deref(), Some("quoted"));
+        assert_eq!(paragraph.children[2].data.as_deref(), Some("lazy"));
+        assert_eq!(
+            tree.link_refs["A"],
+            LinkRefDef {
+                destination: "/url".to_string(),
+                title: "T".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,807 @@
+//! 各类块的开始、延续与关闭.
+use super::escape::unescape_string;
+use super::html_block;
+use super::link_ref_def::parse_link_ref_def;
+use super::table::parse_table;
+use super::{Context, CODE_INDENT};
+use crate::lute::arena::NodeKey;
+use crate::lute::ial::{parse_ial, parse_ial_line, set_ial};
+use crate::lute::node::{ListData, Node, NodeType};
+
+/// 已打开的块能否延续到当前行
+pub(super) enum Continue {
+    Matched,
+    NotMatched,
+    /// 当前行已被处理 (如代码块的结束围栏)
+    LineHandled,
+}
+
+/// 当前行开始的新块
+pub(super) enum Start {
+    None,
+    /// 容器块, 继续尝试在其中开始新块
+    Container,
+    /// 叶子块, 剩余内容属于该块
+    Leaf,
+}
+
+/// 块是否直接接收文本行
+pub(super) fn accepts_lines(node_type: NodeType) -> bool {
+    matches!(
+        node_type,
+        NodeType::NodeParagraph
+            | NodeType::NodeCodeBlock
+            | NodeType::NodeHtmlBlock
+            | NodeType::NodeMathBlock
+    )
+}
+
+pub(super) fn can_contain(parent: NodeType, child: NodeType) -> bool {
+    match parent {
+        NodeType::NodeDocument
+        | NodeType::NodeBlockquote
+        | NodeType::NodeListItem
+        | NodeType::NodeSuperBlock => child != NodeType::NodeListItem,
+        NodeType::NodeList => child == NodeType::NodeListItem,
+        _ => false,
+    }
+}
+
+/// 以这些字符开头时才可能开始新块
+pub(super) fn maybe_special(c: Option<u8>) -> bool {
+    matches!(
+        c,
+        Some(b'#' | b'`' | b'~' | b'*' | b'+' | b'_' | b'=' | b'<' | b'>' | b'-' | b'$' | b'{')
+            | Some(b'0'..=b'9')
+    )
+}
+
+fn is_space_or_tab(c: Option<u8>) -> bool {
+    matches!(c, Some(b' ' | b'\t'))
+}
+
+fn is_blank(s: &[u8]) -> bool {
+    s.iter().all(|&b| b == b' ' || b == b'\t')
+}
+
+/// 去掉末尾的空行, 即 `(\n *)+$`
+fn trim_trailing_blank_lines(s: &[u8]) -> &[u8] {
+    let mut end = s.len();
+    while let Some(newline) = s[..end].iter().rposition(|&b| b == b'\n') {
+        if s[newline + 1..end].iter().all(|&b| b == b' ') {
+            end = newline;
+        } else {
+            break;
+        }
+    }
+    &s[..end]
+}
+
+/// ATX 标题内容去掉结尾的 `#` 序列
+fn strip_closing_sequence(content: &[u8]) -> &[u8] {
+    let trimmed = content.trim_ascii_end();
+    let end = trimmed
+        .iter()
+        .rposition(|&b| b != b'#')
+        .map_or(0, |i| i + 1);
+    if end == trimmed.len() {
+        return trimmed;
+    }
+    match end.checked_sub(1).map(|i| trimmed[i]) {
+        None => b"",
+        Some(b' ' | b'\t') => trimmed[..end].trim_ascii_end(),
+        Some(_) => trimmed,
+    }
+}
+
+fn marker(node_type: NodeType, data: &str) -> Node {
+    let mut node = Node::new(node_type);
+    if !data.is_empty() {
+        node.data = Some(data.to_string());
+    }
+    node
+}
+
+fn lists_match(a: &ListData, b: &ListData) -> bool {
+    a.typ == b.typ && a.delimiter == b.delimiter && a.bullet_char == b.bullet_char
+}
+
+impl Context<'_> {
+    pub(super) fn continue_block(&mut self, container: NodeKey) -> Continue {
+        let next = self.peek(self.next_nonspace);
+        match self.node_type(container) {
+            NodeType::NodeBlockquote => {
+                if self.indented || next != Some(b'>') {
+                    return Continue::NotMatched;
+                }
+                self.advance_next_nonspace();
+                self.advance_offset(1, false);
+                if is_space_or_tab(self.peek(self.offset)) {
+                    self.advance_offset(1, true);
+                }
+                Continue::Matched
+            }
+            NodeType::NodeListItem => {
+                let list_data = self.node(container).list_data.as_ref();
+                let width = list_data.map_or(0, |data| {
+                    data.marker_offset.unwrap_or(0) + data.padding.unwrap_or(0)
+                }) as usize;
+                if self.blank {
+                    // 空列表项后的空行结束该项
+                    if self.tree.first_child(container).is_none() {
+                        return Continue::NotMatched;
+                    }
+                    self.advance_next_nonspace();
+                } else if self.indent >= width {
+                    self.advance_offset(width, true);
+                } else {
+                    return Continue::NotMatched;
+                }
+                Continue::Matched
+            }
+            NodeType::NodeHeading
+            | NodeType::NodeThematicBreak
+            | NodeType::NodeKramdownBlockIal => Continue::NotMatched,
+            NodeType::NodeCodeBlock => {
+                let node = self.node(container);
+                if node.is_fenced_code_block == Some(true) {
+                    let fence_char = node.code_block_fence_char;
+                    let fence_len = node.code_block_fence_len.unwrap_or(3) as usize;
+                    let fence_offset = node.code_block_fence_offset.unwrap_or(0);
+                    let rest = &self.current_line[self.next_nonspace..];
+                    let run = rest.iter().take_while(|&&b| Some(b) == fence_char).count();
+                    if self.indent <= 3 && run >= fence_len && is_blank(&rest[run..]) {
+                        let close_fence = rest[..run].to_vec();
+                        self.node_mut(container).code_block_close_fence = Some(close_fence.into());
+                        self.last_line_length = self.current_line.len();
+                        self.finalize(container);
+                        return Continue::LineHandled;
+                    }
+                    // 去掉与开始围栏相同宽度的缩进
+                    let mut i = fence_offset;
+                    while i > 0 && is_space_or_tab(self.peek(self.offset)) {
+                        self.advance_offset(1, true);
+                        i -= 1;
+                    }
+                } else if self.indent >= CODE_INDENT {
+                    self.advance_offset(CODE_INDENT, true);
+                } else if self.blank {
+                    self.advance_next_nonspace();
+                } else {
+                    return Continue::NotMatched;
+                }
+                Continue::Matched
+            }
+            NodeType::NodeHtmlBlock => {
+                let html_block_type = self.node(container).html_block_type;
+                if self.blank && matches!(html_block_type, Some(6 | 7)) {
+                    Continue::NotMatched
+                } else {
+                    Continue::Matched
+                }
+            }
+            NodeType::NodeParagraph => {
+                if self.blank {
+                    Continue::NotMatched
+                } else {
+                    Continue::Matched
+                }
+            }
+            NodeType::NodeMathBlock => {
+                let rest = &self.current_line[self.next_nonspace..];
+                if self.indent <= 3 && rest.trim_ascii_end() == b"$$" {
+                    self.last_line_length = self.current_line.len();
+                    self.finalize(container);
+                    return Continue::LineHandled;
+                }
+                let mut i = self.node(container).math_block_dollar_offset.unwrap_or(0);
+                while i > 0 && is_space_or_tab(self.peek(self.offset)) {
+                    self.advance_offset(1, true);
+                    i -= 1;
+                }
+                Continue::Matched
+            }
+            NodeType::NodeSuperBlock => {
+                let rest = &self.current_line[self.next_nonspace..];
+                if !self.indented && rest.trim_ascii_end() == b"}}}" && !self.in_literal_block() {
+                    while self.tip != container {
+                        let tip = self.tip;
+                        self.finalize(tip);
+                    }
+                    self.last_line_length = self.current_line.len();
+                    self.finalize(container);
+                    return Continue::LineHandled;
+                }
+                Continue::Matched
+            }
+            _ => Continue::Matched,
+        }
+    }
+
+    /// tip 是否为内容按原样保留的块, 其中的 `}}}` 不结束超级块
+    fn in_literal_block(&self) -> bool {
+        let tip = self.node(self.tip);
+        match tip.node_type {
+            NodeType::NodeCodeBlock => tip.is_fenced_code_block == Some(true),
+            NodeType::NodeMathBlock => true,
+            NodeType::NodeHtmlBlock => matches!(tip.html_block_type, Some(1..=5)),
+            _ => false,
+        }
+    }
+
+    /// 按优先级依次尝试开始新块
+    pub(super) fn try_block_starts(&mut self, container: NodeKey) -> Start {
+        let starts: [fn(&mut Self, NodeKey) -> Start; 11] = [
+            Self::start_kramdown_block_ial,
+            Self::start_blockquote,
+            Self::start_atx_heading,
+            Self::start_fenced_code_block,
+            Self::start_math_block,
+            Self::start_super_block,
+            Self::start_html_block,
+            Self::start_setext_heading,
+            Self::start_thematic_break,
+            Self::start_list_item,
+            Self::start_indented_code_block,
+        ];
+        for start in starts {
+            let result = start(self, container);
+            if !matches!(result, Start::None) {
+                return result;
+            }
+        }
+        Start::None
+    }
+
+    fn rest(&self) -> &[u8] {
+        &self.current_line[self.next_nonspace..]
+    }
+
+    fn advance_to_line_end(&mut self) {
+        let remaining = self.current_line.len() - self.offset;
+        self.advance_offset(remaining, false);
+    }
+
+    /// 单独一行的 `{: ...}`, 是前一个块的属性, 能打断段落
+    fn start_kramdown_block_ial(&mut self, _container: NodeKey) -> Start {
+        if !self.options.kramdown_block_ial || self.indented {
+            return Start::None;
+        }
+        let Some(ial) = parse_ial_line(self.rest()) else {
+            return Start::None;
+        };
+        let mut node = Node::new(NodeType::NodeKramdownBlockIal);
+        node.data = Some(String::from_utf8_lossy(self.rest().trim_ascii()).into_owned());
+        node.kramdown_ial = ial;
+        self.close_unmatched_blocks();
+        self.add_child(node);
+        self.advance_to_line_end();
+        Start::Leaf
+    }
+
+    fn start_blockquote(&mut self, _container: NodeKey) -> Start {
+        if self.indented || self.peek(self.next_nonspace) != Some(b'>') {
+            return Start::None;
+        }
+        self.advance_next_nonspace();
+        self.advance_offset(1, false);
+        if is_space_or_tab(self.peek(self.offset)) {
+            self.advance_offset(1, true);
+        }
+        self.close_unmatched_blocks();
+        let blockquote = self.add_child(Node::new(NodeType::NodeBlockquote));
+        self.append_marker(blockquote, marker(NodeType::NodeBlockquoteMarker, ">"));
+        Start::Container
+    }
+
+    fn start_atx_heading(&mut self, _container: NodeKey) -> Start {
+        if self.indented {
+            return Start::None;
+        }
+        let rest = self.rest();
+        let level = rest.iter().take_while(|&&b| b == b'#').count();
+        if !(1..=6).contains(&level) || !matches!(rest.get(level), None | Some(b' ' | b'\t')) {
+            return Start::None;
+        }
+        let content = strip_closing_sequence(rest[level..].trim_ascii_start()).to_vec();
+        self.advance_next_nonspace();
+        self.close_unmatched_blocks();
+        let mut heading = Node::new(NodeType::NodeHeading);
+        heading.heading_level = Some(level as i32);
+        heading.tokens = content.into();
+        self.add_child(heading);
+        self.advance_to_line_end();
+        Start::Leaf
+    }
+
+    fn start_fenced_code_block(&mut self, _container: NodeKey) -> Start {
+        if self.indented {
+            return Start::None;
+        }
+        let rest = self.rest();
+        let Some(&fence_char @ (b'`' | b'~')) = rest.first() else {
+            return Start::None;
+        };
+        let fence_len = rest.iter().take_while(|&&b| b == fence_char).count();
+        if fence_len < 3 || fence_char == b'`' && rest[fence_len..].contains(&b'`') {
+            return Start::None;
+        }
+        let mut code_block = Node::new(NodeType::NodeCodeBlock);
+        code_block.is_fenced_code_block = Some(true);
+        code_block.code_block_fence_char = Some(fence_char);
+        code_block.code_block_fence_len = Some(fence_len as i32);
+        code_block.code_block_fence_offset = Some(self.indent as i32);
+        code_block.code_block_open_fence = Some(rest[..fence_len].to_vec().into());
+        self.close_unmatched_blocks();
+        self.add_child(code_block);
+        self.advance_next_nonspace();
+        self.advance_offset(fence_len, false);
+        Start::Leaf
+    }
+
+    /// `$$` 开始的数学公式块, 同一行内闭合的留给行级公式
+    fn start_math_block(&mut self, _container: NodeKey) -> Start {
+        let rest = self.rest();
+        if !self.options.math_block
+            || self.indented
+            || !rest.starts_with(b"$$")
+            || rest[2..].windows(2).any(|w| w == b"$$")
+        {
+            return Start::None;
+        }
+        let mut math_block = Node::new(NodeType::NodeMathBlock);
+        math_block.math_block_dollar_offset = Some(self.indent as i32);
+        self.close_unmatched_blocks();
+        self.add_child(math_block);
+        self.advance_next_nonspace();
+        self.advance_offset(2, false);
+        Start::Leaf
+    }
+
+    /// `{{{row` 或 `{{{col` 开始的超级块, 以单独一行的 `}}}` 结束
+    fn start_super_block(&mut self, _container: NodeKey) -> Start {
+        let rest = self.rest();
+        if !self.options.super_block || self.indented || !rest.starts_with(b"{{{") {
+            return Start::None;
+        }
+        let layout = match rest[3..].trim_ascii() {
+            b"" | b"row" => "row",
+            b"col" => "col",
+            _ => return Start::None,
+        };
+        self.close_unmatched_blocks();
+        let super_block = self.add_child(Node::new(NodeType::NodeSuperBlock));
+        self.append_marker(super_block, marker(NodeType::NodeSuperBlockOpenMarker, ""));
+        self.append_marker(
+            super_block,
+            marker(NodeType::NodeSuperBlockLayoutMarker, layout),
+        );
+        self.advance_to_line_end();
+        Start::Leaf
+    }
+
+    fn start_html_block(&mut self, container: NodeKey) -> Start {
+        if self.indented || self.peek(self.next_nonspace) != Some(b'<') {
+            return Start::None;
+        }
+        // 第 7 类不能打断段落, 包括段落的惰性延续行
+        let in_paragraph = self.node_type(container) == NodeType::NodeParagraph
+            || !self.all_closed
+                && !self.blank
+                && self.node_type(self.tip) == NodeType::NodeParagraph;
+        let Some(html_block_type) = html_block::start_type(self.rest(), in_paragraph) else {
+            return Start::None;
+        };
+        self.close_unmatched_blocks();
+        let mut html_block = Node::new(NodeType::NodeHtmlBlock);
+        html_block.html_block_type = Some(html_block_type);
+        // 缩进属于 HTML 块的内容, 不调整 offset
+        self.add_child(html_block);
+        Start::Leaf
+    }
+
+    fn start_setext_heading(&mut self, container: NodeKey) -> Start {
+        if !self.options.setext_heading
+            || self.indented
+            || self.node_type(container) != NodeType::NodeParagraph
+        {
+            return Start::None;
+        }
+        let rest = self.rest();
+        let level = match rest.first() {
+            Some(b'=') => 1,
+            Some(b'-') => 2,
+            _ => return Start::None,
+        };
+        let run = rest.iter().take_while(|&&b| b == rest[0]).count();
+        if !is_blank(&rest[run..]) {
+            return Start::None;
+        }
+        self.close_unmatched_blocks();
+        // 段落开头的链接引用定义不属于标题
+        self.resolve_link_ref_defs(container);
+        if self.node(container).tokens.is_empty() {
+            return Start::None;
+        }
+        let heading = self.node_mut(container);
+        heading.node_type = NodeType::NodeHeading;
+        heading.type_str = NodeType::NodeHeading.name().to_string();
+        heading.heading_level = Some(level);
+        heading.heading_setext = Some(true);
+        self.advance_to_line_end();
+        Start::Leaf
+    }
+
+    fn start_thematic_break(&mut self, _container: NodeKey) -> Start {
+        if self.indented {
+            return Start::None;
+        }
+        let rest = self.rest();
+        let Some(&c @ (b'*' | b'-' | b'_')) = rest.first() else {
+            return Start::None;
+        };
+        let count = rest.iter().filter(|&&b| b == c).count();
+        if count < 3 || !rest.iter().all(|&b| b == c || b == b' ' || b == b'\t') {
+            return Start::None;
+        }
+        self.close_unmatched_blocks();
+        self.add_child(Node::new(NodeType::NodeThematicBreak));
+        self.advance_to_line_end();
+        Start::Leaf
+    }
+
+    fn start_list_item(&mut self, container: NodeKey) -> Start {
+        if self.indented && self.node_type(container) != NodeType::NodeList {
+            return Start::None;
+        }
+        let Some(data) = self.parse_list_marker(container) else {
+            return Start::None;
+        };
+        self.close_unmatched_blocks();
+        let same_list = self.node_type(self.tip) == NodeType::NodeList
+            && self
+                .node(self.tip)
+                .list_data
+                .as_ref()
+                .is_some_and(|list_data| lists_match(list_data, &data));
+        if !same_list {
+            let mut list = Node::new(NodeType::NodeList);
+            list.list_data = Some(data.clone());
+            self.add_child(list);
+        }
+        let mut item = Node::new(NodeType::NodeListItem);
+        item.list_data = Some(data);
+        // 列表项的 IAL 紧跟在标记之后
+        if self.options.kramdown_block_ial {
+            if let Some((ial, len)) = parse_ial(&self.current_line[self.offset..]) {
+                set_ial(&mut item, ial);
+                self.advance_offset(len, false);
+            }
+        }
+        self.add_child(item);
+        Start::Container
+    }
+
+    /// 解析列表项标记, 成功时 offset 移动到内容开始处
+    fn parse_list_marker(&mut self, container: NodeKey) -> Option<ListData> {
+        if self.indent >= CODE_INDENT {
+            return None;
+        }
+        let in_paragraph = self.node_type(container) == NodeType::NodeParagraph;
+        let rest = self.rest();
+        let mut data = ListData {
+            marker_offset: Some(self.indent as i32),
+            tight: Some(true),
+            ..ListData::default()
+        };
+        let marker_len = match *rest.first()? {
+            c @ (b'*' | b'+' | b'-') => {
+                data.typ = Some(0);
+                data.bullet_char = Some(c);
+                1
+            }
+            b'0'..=b'9' => {
+                let digits = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+                let delimiter = *rest.get(digits)?;
+                if digits > 9 || !matches!(delimiter, b'.' | b')') {
+                    return None;
+                }
+                let start: i32 = std::str::from_utf8(&rest[..digits]).ok()?.parse().ok()?;
+                // 只有从 1 开始的有序列表可以打断段落
+                if in_paragraph && start != 1 {
+                    return None;
+                }
+                data.typ = Some(1);
+                data.start = Some(start);
+                data.num = Some(start);
+                data.delimiter = Some(delimiter);
+                digits + 1
+            }
+            _ => return None,
+        };
+        let after = &rest[marker_len..];
+        if !matches!(after.first(), None | Some(b' ' | b'\t')) {
+            return None;
+        }
+        // 空列表项不能打断段落
+        if in_paragraph && is_blank(after) {
+            return None;
+        }
+        data.marker = Some(rest[..marker_len].to_vec().into());
+
+        self.advance_next_nonspace();
+        self.advance_offset(marker_len, true);
+        let spaces_start_column = self.column;
+        let spaces_start_offset = self.offset;
+        loop {
+            self.advance_offset(1, true);
+            if !(self.column - spaces_start_column < 5 && is_space_or_tab(self.peek(self.offset))) {
+                break;
+            }
+        }
+        let blank_item = self.peek(self.offset).is_none();
+        let spaces_after_marker = self.column - spaces_start_column;
+        // 标记后超过 4 个空格时内容为缩进代码块, 只算一个空格
+        let padding = if !(1..5).contains(&spaces_after_marker) || blank_item {
+            self.column = spaces_start_column;
+            self.offset = spaces_start_offset;
+            if is_space_or_tab(self.peek(self.offset)) {
+                self.advance_offset(1, true);
+            }
+            marker_len + 1
+        } else {
+            marker_len + spaces_after_marker
+        };
+        data.padding = Some(padding as i32);
+        Some(data)
+    }
+
+    fn start_indented_code_block(&mut self, _container: NodeKey) -> Start {
+        if !self.options.indent_code_block
+            || !self.indented
+            || self.blank
+            || self.node_type(self.tip) == NodeType::NodeParagraph
+        {
+            return Start::None;
+        }
+        self.advance_offset(CODE_INDENT, true);
+        self.close_unmatched_blocks();
+        let mut code_block = Node::new(NodeType::NodeCodeBlock);
+        code_block.is_fenced_code_block = Some(false);
+        self.add_child(code_block);
+        Start::Leaf
+    }
+
+    /// 关闭块时按类型整理内容
+    pub(super) fn finalize_block(&mut self, key: NodeKey) {
+        match self.node_type(key) {
+            NodeType::NodeParagraph => self.finalize_paragraph(key),
+            NodeType::NodeCodeBlock => self.finalize_code_block(key),
+            NodeType::NodeHtmlBlock => {
+                let node = self.node_mut(key);
+                let tokens = std::mem::take(&mut node.tokens);
+                node.data =
+                    Some(String::from_utf8_lossy(trim_trailing_blank_lines(&tokens)).into_owned());
+            }
+            NodeType::NodeMathBlock => self.finalize_math_block(key),
+            NodeType::NodeListItem => self.finalize_task_list_item(key),
+            NodeType::NodeList => self.finalize_list(key),
+            NodeType::NodeSuperBlock => {
+                self.append_marker(key, marker(NodeType::NodeSuperBlockCloseMarker, ""));
+            }
+            _ => {}
+        }
+    }
+
+    /// 移除段落开头的链接引用定义
+    fn resolve_link_ref_defs(&mut self, key: NodeKey) {
+        if !self.options.link_ref {
+            return;
+        }
+        let tokens = std::mem::take(&mut self.node_mut(key).tokens);
+        let mut pos = 0;
+        while tokens.get(pos) == Some(&b'[') {
+            match parse_link_ref_def(&tokens[pos..], &mut self.link_refs) {
+                Some(len) => pos += len,
+                None => break,
+            }
+        }
+        self.node_mut(key).tokens = tokens[pos..].to_vec().into();
+    }
+
+    fn finalize_paragraph(&mut self, key: NodeKey) {
+        self.resolve_link_ref_defs(key);
+        let tokens = &self.node(key).tokens;
+        if tokens.trim_ascii().is_empty() {
+            // 只包含链接引用定义
+            self.tree.remove(key);
+            return;
+        }
+        if self.options.gfm_table {
+            if let Some(mut table) = parse_table(tokens) {
+                table.close = true;
+                self.tree.insert_after(key, table);
+                self.tree.remove(key);
+            }
+        }
+    }
+
+    fn finalize_code_block(&mut self, key: NodeKey) {
+        let node = self.node_mut(key);
+        let tokens = std::mem::take(&mut node.tokens);
+        if node.is_fenced_code_block != Some(true) {
+            let mut code = trim_trailing_blank_lines(&tokens).to_vec();
+            code.push(b'\n');
+            let code = String::from_utf8_lossy(&code).into_owned();
+            self.append_marker(key, marker(NodeType::NodeCodeBlockCode, &code));
+            return;
+        }
+
+        // 第一行为信息字符串
+        let newline = tokens
+            .iter()
+            .position(|&b| b == b'\n')
+            .unwrap_or(tokens.len());
+        let info = unescape_string(tokens[..newline].trim_ascii());
+        let code =
+            String::from_utf8_lossy(tokens.get(newline + 1..).unwrap_or_default()).into_owned();
+        let info = (!info.is_empty()).then(|| info.into_bytes().into());
+        node.code_block_info.clone_from(&info);
+        let open_fence = node.code_block_open_fence.clone().unwrap_or_default();
+        let close_fence = node
+            .code_block_close_fence
+            .get_or_insert_with(|| open_fence.clone())
+            .clone();
+
+        let fence_marker = |node_type, fence: &[u8]| {
+            let mut node = marker(node_type, &String::from_utf8_lossy(fence));
+            node.code_block_fence_len = Some(fence.len() as i32);
+            node
+        };
+        let mut info_marker = Node::new(NodeType::NodeCodeBlockFenceInfoMarker);
+        info_marker.code_block_info = info;
+        self.append_marker(
+            key,
+            fence_marker(NodeType::NodeCodeBlockFenceOpenMarker, &open_fence),
+        );
+        self.append_marker(key, info_marker);
+        self.append_marker(key, marker(NodeType::NodeCodeBlockCode, &code));
+        self.append_marker(
+            key,
+            fence_marker(NodeType::NodeCodeBlockFenceCloseMarker, &close_fence),
+        );
+    }
+
+    fn finalize_math_block(&mut self, key: NodeKey) {
+        let tokens = std::mem::take(&mut self.node_mut(key).tokens);
+        // 开始标记所在行 `$$` 之后的内容也属于公式
+        let newline = tokens
+            .iter()
+            .position(|&b| b == b'\n')
+            .unwrap_or(tokens.len());
+        let first_line = tokens[..newline].trim_ascii();
+        let mut content = first_line.to_vec();
+        if !content.is_empty() {
+            content.push(b'\n');
+        }
+        content.extend_from_slice(tokens.get(newline + 1..).unwrap_or_default());
+        let content = String::from_utf8_lossy(content.trim_ascii_end()).into_owned();
+        self.append_marker(key, marker(NodeType::NodeMathBlockOpenMarker, ""));
+        self.append_marker(key, marker(NodeType::NodeMathBlockContent, &content));
+        self.append_marker(key, marker(NodeType::NodeMathBlockCloseMarker, ""));
+    }
+
+    /// GFM 任务列表项: 第一段以 `[ ]`, `[x]` 或 `[X]` 加空白开头
+    fn finalize_task_list_item(&mut self, key: NodeKey) {
+        if !self.options.gfm_task_list_item {
+            return;
+        }
+        let Some(paragraph) = self
+            .tree
+            .first_child(key)
+            .filter(|&child| self.node_type(child) == NodeType::NodeParagraph)
+        else {
+            return;
+        };
+        let tokens = &self.node(paragraph).tokens;
+        if tokens.len() < 4
+            || tokens[0] != b'['
+            || !matches!(tokens[1], b' ' | b'x' | b'X')
+            || tokens[2] != b']'
+            || !matches!(tokens[3], b' ' | b'\t')
+        {
+            return;
+        }
+        let checked = tokens[1] != b' ';
+        let mut task_marker = marker(
+            NodeType::NodeTaskListItemMarker,
+            &String::from_utf8_lossy(&tokens[..3]),
+        );
+        task_marker.task_list_item_checked = Some(checked);
+        let rest = tokens[3..].to_vec();
+        self.node_mut(paragraph).tokens = rest.into();
+        self.append_marker(paragraph, task_marker);
+        if let Some(data) = self.node_mut(key).list_data.as_mut() {
+            data.typ = Some(3);
+            data.checked = Some(checked);
+        }
+    }
+
+    /// 计算列表是否紧凑, 并为有序列表项编号
+    fn finalize_list(&mut self, key: NodeKey) {
+        let items = self.tree.children(key).collect::<Vec<_>>();
+        let mut tight = true;
+        'items: for (i, &item) in items.iter().enumerate() {
+            let has_next = i + 1 < items.len();
+            if has_next && self.ends_with_blank_line(item) {
+                tight = false;
+                break;
+            }
+            let children = self.tree.children(item).collect::<Vec<_>>();
+            for (j, &child) in children.iter().enumerate() {
+                if (has_next || j + 1 < children.len()) && self.ends_with_blank_line(child) {
+                    tight = false;
+                    break 'items;
+                }
+            }
+        }
+
+        let task = items
+            .first()
+            .and_then(|&item| self.node(item).list_data.as_ref())
+            .is_some_and(|data| data.typ == Some(3));
+        let start = self
+            .node(key)
+            .list_data
+            .as_ref()
+            .and_then(|data| data.start);
+        for (i, &item) in items.iter().enumerate() {
+            if let Some(data) = self.node_mut(item).list_data.as_mut() {
+                data.tight = Some(tight);
+                data.num = start.map(|start| start + i as i32);
+            }
+        }
+        if let Some(data) = self.node_mut(key).list_data.as_mut() {
+            data.tight = Some(tight);
+            if task {
+                data.typ = Some(3);
+            }
+        }
+    }
+
+    /// 块是否以空行结尾, 对列表与列表项检查其最后一个子块
+    fn ends_with_blank_line(&mut self, mut key: NodeKey) -> bool {
+        loop {
+            let node = self.node_mut(key);
+            if node.last_line_blank {
+                return true;
+            }
+            let checked = node.last_line_checked;
+            node.last_line_checked = true;
+            let container = matches!(node.node_type, NodeType::NodeList | NodeType::NodeListItem);
+            match self.tree.last_child(key) {
+                Some(last) if container && !checked => key = last,
+                _ => return false,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_closing_sequence() {
+        assert_eq!(strip_closing_sequence(b"foo ##  "), b"foo");
+        assert_eq!(strip_closing_sequence(b"foo#"), b"foo#");
+        assert_eq!(strip_closing_sequence(b"###"), b"");
+        assert_eq!(strip_closing_sequence(b"foo \\#"), b"foo \\#");
+    }
+
+    #[test]
+    fn test_trim_trailing_blank_lines() {
+        assert_eq!(trim_trailing_blank_lines(b"a\n  \n\n"), b"a");
+        assert_eq!(trim_trailing_blank_lines(b"a\n\t\n"), b"a\n\t");
+    }
+}
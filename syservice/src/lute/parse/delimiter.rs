@@ -0,0 +1,256 @@
+//! 分隔符栈, 处理强调、删除线、标记与上下标的嵌套.
+//!
+//! 算法参考 CommonMark 规范附录 "Processing emphasis", 与 Lute 的 `delimiter.go` 一致.
+use super::inline::InlineContext;
+use crate::lute::arena::NodeKey;
+use crate::lute::node::{Node, NodeType};
+use std::collections::HashMap;
+
+/// 分隔符栈中的一项, 对应一段连续的 `*`, `_`, `~`, `=` 或 `^`
+pub(super) struct Delimiter {
+    /// 分隔符所在的文本节点
+    pub node: NodeKey,
+    /// 分隔符字符
+    pub typ: u8,
+    /// 尚未使用的分隔符数量
+    pub num: usize,
+    pub original_num: usize,
+    pub can_open: bool,
+    pub can_close: bool,
+    /// 处理过程中被移除
+    pub removed: bool,
+}
+
+/// 链接或图片的开方括号 `[` 与 `![`
+pub(super) struct Bracket {
+    /// 方括号所在的文本节点
+    pub node: NodeKey,
+    /// `[` 在 tokens 中的位置
+    pub index: usize,
+    pub image: bool,
+    /// 链接不能嵌套, 外层链接的方括号在内层链接匹配后失效
+    pub active: bool,
+    /// 其后是否还有开方括号, 有时不能作为简写的链接引用
+    pub bracket_after: bool,
+    /// 压入时分隔符栈的高度, 链接文本中的强调只处理该位置之上的分隔符
+    pub previous_delimiter: usize,
+}
+
+/// Unicode 标点或符号
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation() || !c.is_ascii() && !c.is_alphanumeric() && !c.is_whitespace()
+}
+
+impl InlineContext<'_> {
+    /// 扫描分隔符串, 返回数量以及能否开始、结束强调
+    fn scan_delims(&self, typ: u8) -> (usize, bool, bool) {
+        let tokens = self.tokens;
+        let num = tokens[self.pos..].iter().take_while(|&&b| b == typ).count();
+        let before = std::str::from_utf8(&tokens[..self.pos])
+            .ok()
+            .and_then(|s| s.chars().next_back())
+            .unwrap_or('\n');
+        let after = std::str::from_utf8(&tokens[self.pos + num..])
+            .ok()
+            .and_then(|s| s.chars().next())
+            .unwrap_or('\n');
+
+        let after_is_whitespace = after.is_whitespace();
+        let after_is_punctuation = is_punctuation(after);
+        let before_is_whitespace = before.is_whitespace();
+        let before_is_punctuation = is_punctuation(before);
+        let left_flanking = !after_is_whitespace
+            && (!after_is_punctuation || before_is_whitespace || before_is_punctuation);
+        let right_flanking = !before_is_whitespace
+            && (!before_is_punctuation || after_is_whitespace || after_is_punctuation);
+        if typ == b'_' {
+            (
+                num,
+                left_flanking && (!right_flanking || before_is_punctuation),
+                right_flanking && (!left_flanking || after_is_punctuation),
+            )
+        } else {
+            (num, left_flanking, right_flanking)
+        }
+    }
+
+    /// 分隔符串作为文本节点加入, 可能构成强调时压入分隔符栈
+    pub(super) fn handle_delim(&mut self, typ: u8) {
+        let (num, can_open, can_close) = self.scan_delims(typ);
+        let text = String::from_utf8_lossy(&self.tokens[self.pos..self.pos + num]).into_owned();
+        self.pos += num;
+        let node = self.append_text(&text);
+        let options = self.options;
+        let usable = match typ {
+            b'~' => match num {
+                1 => options.sub || options.gfm_strikethrough && options.gfm_strikethrough_single,
+                2 => options.gfm_strikethrough,
+                _ => false,
+            },
+            b'=' => num <= 2,
+            b'^' => num == 1,
+            _ => true,
+        };
+        if usable && (can_open || can_close) {
+            self.delimiters.push(Delimiter {
+                node,
+                typ,
+                num,
+                original_num: num,
+                can_open,
+                can_close,
+                removed: false,
+            });
+        }
+    }
+
+    /// `opener` 能否与 `closer` 配对
+    fn can_match(opener: &Delimiter, closer: &Delimiter) -> bool {
+        if opener.removed || !opener.can_open || opener.typ != closer.typ {
+            return false;
+        }
+        match opener.typ {
+            // 两者之一可开可闭时, 长度之和不能是 3 的倍数, 除非两者都是 3 的倍数
+            b'*' | b'_' => {
+                let odd_match = (closer.can_open || opener.can_close)
+                    && !closer.original_num.is_multiple_of(3)
+                    && (opener.original_num + closer.original_num).is_multiple_of(3);
+                !odd_match
+            }
+            // 删除线、标记与上下标要求长度一致
+            _ => opener.num == closer.num,
+        }
+    }
+
+    /// 处理 `stack_bottom` 之上的分隔符, 生成强调等节点, 处理完后出栈
+    pub(super) fn process_emphasis(&mut self, stack_bottom: usize) {
+        // 按分隔符类型记录已确定找不到开始分隔符的位置
+        let mut openers_bottom: HashMap<(u8, bool, usize), usize> = HashMap::new();
+        let mut closer = stack_bottom;
+        while closer < self.delimiters.len() {
+            let current = &self.delimiters[closer];
+            if current.removed || !current.can_close {
+                closer += 1;
+                continue;
+            }
+            let bottom_key = (current.typ, current.can_open, current.original_num % 3);
+            let lower = openers_bottom
+                .get(&bottom_key)
+                .copied()
+                .unwrap_or(stack_bottom)
+                .max(stack_bottom);
+            let opener = (lower..closer)
+                .rev()
+                .find(|&i| Self::can_match(&self.delimiters[i], current));
+
+            let Some(opener) = opener else {
+                openers_bottom.insert(bottom_key, closer);
+                if !current.can_open {
+                    self.delimiters[closer].removed = true;
+                }
+                closer += 1;
+                continue;
+            };
+
+            let typ = current.typ;
+            let used = match typ {
+                b'*' | b'_' if current.num >= 2 && self.delimiters[opener].num >= 2 => 2,
+                b'*' | b'_' => 1,
+                _ => current.num,
+            };
+            let (node_type, open_marker, close_marker) = self.emphasis_type(typ, used);
+            self.delimiters[opener].num -= used;
+            self.delimiters[closer].num -= used;
+            let opener_node = self.delimiters[opener].node;
+            let closer_node = self.delimiters[closer].node;
+            self.trim_delim_text(opener_node, used);
+            self.trim_delim_text(closer_node, used);
+
+            // 两个分隔符之间的节点移入新节点
+            let wrapper = self.tree.insert_after(opener_node, Node::new(node_type));
+            self.tree.append_child(wrapper, Node::new(open_marker));
+            while let Some(next) = self.tree.next(wrapper).filter(|&next| next != closer_node) {
+                let node = self.tree.remove(next).unwrap();
+                self.tree.append_child(wrapper, node);
+            }
+            self.tree.append_child(wrapper, Node::new(close_marker));
+
+            for delimiter in &mut self.delimiters[opener + 1..closer] {
+                delimiter.removed = true;
+            }
+            if self.delimiters[opener].num == 0 {
+                self.tree.remove(opener_node);
+                self.delimiters[opener].removed = true;
+            }
+            if self.delimiters[closer].num == 0 {
+                self.tree.remove(closer_node);
+                self.delimiters[closer].removed = true;
+                closer += 1;
+            }
+        }
+        self.delimiters.truncate(stack_bottom);
+    }
+
+    /// 分隔符字符与数量对应的节点类型及其开始、结束标记
+    fn emphasis_type(&self, typ: u8, used: usize) -> (NodeType, NodeType, NodeType) {
+        match (typ, used) {
+            (b'*', 1) => (
+                NodeType::NodeEmphasis,
+                NodeType::NodeEmA6kOpenMarker,
+                NodeType::NodeEmA6kCloseMarker,
+            ),
+            (b'*', _) => (
+                NodeType::NodeStrong,
+                NodeType::NodeStrongA6kOpenMarker,
+                NodeType::NodeStrongA6kCloseMarker,
+            ),
+            (b'_', 1) => (
+                NodeType::NodeEmphasis,
+                NodeType::NodeEmU8eOpenMarker,
+                NodeType::NodeEmU8eCloseMarker,
+            ),
+            (b'_', _) => (
+                NodeType::NodeStrong,
+                NodeType::NodeStrongU8eOpenMarker,
+                NodeType::NodeStrongU8eCloseMarker,
+            ),
+            (b'~', 1) if self.options.sub => (
+                NodeType::NodeSub,
+                NodeType::NodeSubOpenMarker,
+                NodeType::NodeSubCloseMarker,
+            ),
+            (b'~', 1) => (
+                NodeType::NodeStrikethrough,
+                NodeType::NodeStrikethrough1OpenMarker,
+                NodeType::NodeStrikethrough1CloseMarker,
+            ),
+            (b'~', _) => (
+                NodeType::NodeStrikethrough,
+                NodeType::NodeStrikethrough2OpenMarker,
+                NodeType::NodeStrikethrough2CloseMarker,
+            ),
+            (b'=', 1) => (
+                NodeType::NodeMark,
+                NodeType::NodeMark1OpenMarker,
+                NodeType::NodeMark1CloseMarker,
+            ),
+            (b'=', _) => (
+                NodeType::NodeMark,
+                NodeType::NodeMark2OpenMarker,
+                NodeType::NodeMark2CloseMarker,
+            ),
+            _ => (
+                NodeType::NodeSup,
+                NodeType::NodeSupOpenMarker,
+                NodeType::NodeSupCloseMarker,
+            ),
+        }
+    }
+
+    /// 分隔符文本节点去掉已使用的字符
+    fn trim_delim_text(&mut self, key: NodeKey, used: usize) {
+        if let Some(data) = self.tree.get_mut(key).and_then(|node| node.data.as_mut()) {
+            data.truncate(data.len() - used);
+        }
+    }
+}
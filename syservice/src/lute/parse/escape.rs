@@ -0,0 +1,86 @@
+//! 反斜杠转义与字符引用.
+use crate::lute::html_entity;
+
+/// 解析 `s` 开头的字符引用 (`&amp;`, `&#35;`, `&#x22;`), 返回对应文本与消耗的字节数
+pub(crate) fn parse_entity(s: &[u8]) -> Option<(String, usize)> {
+    if s.first() != Some(&b'&') {
+        return None;
+    }
+    if s.get(1) == Some(&b'#') {
+        let hex = matches!(s.get(2), Some(b'x' | b'X'));
+        let start = if hex { 3 } else { 2 };
+        let digits = s[start..]
+            .iter()
+            .take_while(|b| {
+                if hex {
+                    b.is_ascii_hexdigit()
+                } else {
+                    b.is_ascii_digit()
+                }
+            })
+            .count();
+        let max_digits = if hex { 6 } else { 7 };
+        if digits == 0 || digits > max_digits || s.get(start + digits) != Some(&b';') {
+            return None;
+        }
+        let text = std::str::from_utf8(&s[start..start + digits]).ok()?;
+        let code = u32::from_str_radix(text, if hex { 16 } else { 10 }).ok()?;
+        // 0 与无效码点替换为 U+FFFD
+        let c = char::from_u32(code)
+            .filter(|&c| c != '\0')
+            .unwrap_or('\u{fffd}');
+        return Some((c.to_string(), start + digits + 1));
+    }
+    let len = s[1..]
+        .iter()
+        .take_while(|b| b.is_ascii_alphanumeric())
+        .count();
+    if len == 0 || s.get(1 + len) != Some(&b';') {
+        return None;
+    }
+    let name = std::str::from_utf8(&s[1..1 + len]).ok()?;
+    html_entity::lookup(name).map(|text| (text.to_string(), len + 2))
+}
+
+/// 处理反斜杠转义与字符引用
+pub(crate) fn unescape_string(s: &[u8]) -> String {
+    let mut out = Vec::with_capacity(s.len());
+    let mut i = 0;
+    while i < s.len() {
+        match s[i] {
+            b'\\' if s.get(i + 1).is_some_and(u8::is_ascii_punctuation) => {
+                out.push(s[i + 1]);
+                i += 2;
+            }
+            b'&' => match parse_entity(&s[i..]) {
+                Some((text, len)) => {
+                    out.extend_from_slice(text.as_bytes());
+                    i += len;
+                }
+                None => {
+                    out.push(b'&');
+                    i += 1;
+                }
+            },
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unescape_string() {
+        assert_eq!(
+            unescape_string(br"\*a\b &amp; &#35; &#X22; &#0; &nope; &#12345678;"),
+            "*a\\b & # \" \u{fffd} &nope; &#12345678;"
+        );
+        assert_eq!(parse_entity(b"&ouml;x"), Some(("ö".to_string(), 6)));
+    }
+}
@@ -0,0 +1,267 @@
+//! HTML 块的 7 种开始与结束条件, 以及行级解析也会用到的 HTML 标签扫描.
+
+/// 第 6 类 HTML 块的标签名
+const BLOCK_TAGS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "base",
+    "basefont",
+    "blockquote",
+    "body",
+    "caption",
+    "center",
+    "col",
+    "colgroup",
+    "dd",
+    "details",
+    "dialog",
+    "dir",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "frame",
+    "frameset",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hr",
+    "html",
+    "iframe",
+    "legend",
+    "li",
+    "link",
+    "main",
+    "menu",
+    "menuitem",
+    "nav",
+    "noframes",
+    "ol",
+    "optgroup",
+    "option",
+    "p",
+    "param",
+    "search",
+    "section",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "title",
+    "tr",
+    "track",
+    "ul",
+];
+
+/// 第 1 类 HTML 块的标签名, 块内可以有空行
+const RAW_TAGS: &[&str] = &["pre", "script", "style", "textarea"];
+
+/// 判断 `line` 是否开始 HTML 块, 返回块的类型.
+/// 第 7 类不能打断段落, 由 `in_paragraph` 控制
+pub(super) fn start_type(line: &[u8], in_paragraph: bool) -> Option<i32> {
+    if line.first() != Some(&b'<') {
+        return None;
+    }
+    let rest = &line[1..];
+    if let Some(name) = tag_name(rest) {
+        if RAW_TAGS
+            .iter()
+            .any(|tag| name.eq_ignore_ascii_case(tag.as_bytes()))
+            && matches!(rest.get(name.len()), None | Some(b' ' | b'\t' | b'>'))
+        {
+            return Some(1);
+        }
+    }
+    if rest.starts_with(b"!--") {
+        return Some(2);
+    }
+    if rest.starts_with(b"?") {
+        return Some(3);
+    }
+    if rest.first() == Some(&b'!') && rest.get(1).is_some_and(u8::is_ascii_alphabetic) {
+        return Some(4);
+    }
+    if rest.starts_with(b"![CDATA[") {
+        return Some(5);
+    }
+    let name_start = if rest.first() == Some(&b'/') {
+        &rest[1..]
+    } else {
+        rest
+    };
+    if let Some(name) = tag_name(name_start) {
+        let after = &name_start[name.len()..];
+        if BLOCK_TAGS
+            .iter()
+            .any(|tag| name.eq_ignore_ascii_case(tag.as_bytes()))
+            && (matches!(after.first(), None | Some(b' ' | b'\t' | b'>'))
+                || after.starts_with(b"/>"))
+        {
+            return Some(6);
+        }
+    }
+    if !in_paragraph {
+        let len = scan_open_tag(line).or_else(|| scan_closing_tag(line))?;
+        let is_raw = tag_name(name_start).is_some_and(|name| {
+            RAW_TAGS
+                .iter()
+                .any(|tag| name.eq_ignore_ascii_case(tag.as_bytes()))
+        });
+        if !is_raw && line[len..].iter().all(|&b| b == b' ' || b == b'\t') {
+            return Some(7);
+        }
+    }
+    None
+}
+
+/// 第 1 到 5 类 HTML 块在包含结束条件的行结束
+pub(super) fn is_end(html_block_type: i32, line: &[u8]) -> bool {
+    let contains = |needle: &[u8]| {
+        line.windows(needle.len())
+            .any(|w| w.eq_ignore_ascii_case(needle))
+    };
+    match html_block_type {
+        1 => RAW_TAGS
+            .iter()
+            .any(|tag| contains(format!("</{tag}>").as_bytes())),
+        2 => contains(b"-->"),
+        3 => contains(b"?>"),
+        4 => contains(b">"),
+        5 => contains(b"]]>"),
+        _ => false,
+    }
+}
+
+/// 扫描标签名 `[A-Za-z][A-Za-z0-9-]*`
+fn tag_name(s: &[u8]) -> Option<&[u8]> {
+    if !s.first()?.is_ascii_alphabetic() {
+        return None;
+    }
+    let len = s
+        .iter()
+        .take_while(|b| b.is_ascii_alphanumeric() || **b == b'-')
+        .count();
+    Some(&s[..len])
+}
+
+fn skip_whitespace(s: &[u8], pos: usize) -> usize {
+    pos + s[pos..]
+        .iter()
+        .take_while(|b| matches!(b, b' ' | b'\t' | b'\n'))
+        .count()
+}
+
+/// 扫描开始标签 `<tag attr="value" />`, 返回标签长度
+pub(crate) fn scan_open_tag(s: &[u8]) -> Option<usize> {
+    if s.first() != Some(&b'<') {
+        return None;
+    }
+    let mut pos = 1 + tag_name(&s[1..])?.len();
+    loop {
+        let after_space = skip_whitespace(s, pos);
+        if after_space == pos {
+            break;
+        }
+        let Some(name_len) = attribute_name(&s[after_space..]) else {
+            break;
+        };
+        pos = after_space + name_len;
+        // 可选的属性值
+        let eq = skip_whitespace(s, pos);
+        if s.get(eq) == Some(&b'=') {
+            let value_start = skip_whitespace(s, eq + 1);
+            pos = value_start + attribute_value(&s[value_start..])?;
+        }
+    }
+    pos = skip_whitespace(s, pos);
+    if s.get(pos) == Some(&b'/') {
+        pos += 1;
+    }
+    (s.get(pos) == Some(&b'>')).then_some(pos + 1)
+}
+
+/// 扫描结束标签 `</tag>`, 返回标签长度
+pub(crate) fn scan_closing_tag(s: &[u8]) -> Option<usize> {
+    if !s.starts_with(b"</") {
+        return None;
+    }
+    let pos = skip_whitespace(s, 2 + tag_name(&s[2..])?.len());
+    (s.get(pos) == Some(&b'>')).then_some(pos + 1)
+}
+
+fn attribute_name(s: &[u8]) -> Option<usize> {
+    let first = *s.first()?;
+    if !(first.is_ascii_alphabetic() || first == b'_' || first == b':') {
+        return None;
+    }
+    Some(
+        s.iter()
+            .take_while(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b':' | b'-'))
+            .count(),
+    )
+}
+
+fn attribute_value(s: &[u8]) -> Option<usize> {
+    match *s.first()? {
+        quote @ (b'"' | b'\'') => s[1..].iter().position(|&b| b == quote).map(|end| end + 2),
+        _ => {
+            let len = s
+                .iter()
+                .take_while(|&&b| {
+                    b > b' ' && !matches!(b, b'"' | b'\'' | b'=' | b'<' | b'>' | b'`')
+                })
+                .count();
+            (len > 0).then_some(len)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_start_type() {
+        assert_eq!(start_type(b"<PRE class=\"x\">", false), Some(1));
+        assert_eq!(start_type(b"<pre-x>", false), Some(7));
+        assert_eq!(start_type(b"<!-- c", false), Some(2));
+        assert_eq!(start_type(b"<?php", false), Some(3));
+        assert_eq!(start_type(b"<!DOCTYPE html>", false), Some(4));
+        assert_eq!(start_type(b"<![CDATA[", false), Some(5));
+        assert_eq!(start_type(b"</div", true), Some(6));
+        assert_eq!(start_type(b"<a href=\"x\">", false), Some(7));
+        assert_eq!(start_type(b"<a href=\"x\">", true), None);
+        assert_eq!(start_type(b"<a href=\"x\"> text", false), None);
+        assert_eq!(start_type(b"<div*>", false), None);
+    }
+
+    #[test]
+    fn test_is_end() {
+        assert!(is_end(1, b"x</STYLE> y"));
+        assert!(is_end(2, b"-->"));
+        assert!(!is_end(3, b"? >"));
+        assert!(!is_end(6, b"</div>"));
+    }
+
+    #[test]
+    fn test_scan_tags() {
+        assert_eq!(scan_open_tag(b"<a b='1' c=2 d\n/>x"), Some(17));
+        assert_eq!(scan_open_tag(b"<a b=>"), None);
+        assert_eq!(scan_open_tag(b"<a\"b>"), None);
+        assert_eq!(scan_closing_tag(b"</a  >"), Some(6));
+        assert_eq!(scan_closing_tag(b"</a b>"), None);
+    }
+}
@@ -0,0 +1,1012 @@
+//! 行级内容解析, 移植自 Lute 的 parse 包 (即 commonmark.js 的 `inlines.js`).
+//!
+//! 解析过程中节点保存在 [`NodeTree`] 中, 处理强调与链接时需要把一段兄弟节点移入新节点.
+use super::delimiter::{Bracket, Delimiter};
+use super::escape::parse_entity;
+use super::html_block::{scan_closing_tag, scan_open_tag};
+use super::link_ref_def::{
+    normalize_label, parse_link_destination, parse_link_title, scan_link_label, skip_spnl,
+};
+use super::{LinkRefDef, Options};
+use crate::lute::arena::{NodeKey, NodeTree};
+use crate::lute::ial::{parse_ial, set_ial};
+use crate::lute::node::{is_node_id_pattern, Node, NodeType};
+use std::collections::HashMap;
+
+/// 行级解析的状态
+pub(super) struct InlineContext<'a> {
+    pub(super) options: &'a Options,
+    link_refs: &'a HashMap<String, LinkRefDef>,
+    pub(super) tokens: &'a [u8],
+    pub(super) pos: usize,
+    /// 根节点为占位的段落, 解析结果为其子节点
+    pub(super) tree: NodeTree,
+    /// 分隔符栈
+    pub(super) delimiters: Vec<Delimiter>,
+    /// 方括号栈
+    brackets: Vec<Bracket>,
+}
+
+/// 将段落、标题等块的内容解析为行级节点
+pub(super) fn parse_inline(
+    tokens: &[u8],
+    options: &Options,
+    link_refs: &HashMap<String, LinkRefDef>,
+) -> Vec<Node> {
+    let mut context = InlineContext {
+        options,
+        link_refs,
+        tokens,
+        pos: 0,
+        tree: NodeTree::from_node(Node::new(NodeType::NodeParagraph)),
+        delimiters: Vec::new(),
+        brackets: Vec::new(),
+    };
+    while context.pos < tokens.len() {
+        context.parse_one();
+    }
+    context.process_emphasis(0);
+    let mut children = context.tree.to_node().children;
+    if options.kramdown_span_ial {
+        attach_span_ials(&mut children);
+    }
+    if options.gfm_auto_link {
+        children = auto_link(children);
+    }
+    merge_text(&mut children);
+    children
+}
+
+fn text_node(text: &str) -> Node {
+    let mut node = Node::new(NodeType::NodeText);
+    node.data = Some(text.to_string());
+    node
+}
+
+fn data_node(node_type: NodeType, data: &str) -> Node {
+    let mut node = Node::new(node_type);
+    node.data = Some(data.to_string());
+    node
+}
+
+/// 合并相邻的文本节点
+fn merge_text(nodes: &mut Vec<Node>) {
+    let mut merged: Vec<Node> = Vec::with_capacity(nodes.len());
+    for mut node in nodes.drain(..) {
+        merge_text(&mut node.children);
+        match merged.last_mut() {
+            Some(last)
+                if last.node_type == NodeType::NodeText && node.node_type == NodeType::NodeText =>
+            {
+                let text = node.data.unwrap_or_default();
+                last.data.get_or_insert_with(String::new).push_str(&text);
+            }
+            _ => merged.push(node),
+        }
+    }
+    merged.retain(|node| {
+        node.node_type != NodeType::NodeText
+            || node.data.as_deref().is_some_and(|data| !data.is_empty())
+    });
+    *nodes = merged;
+}
+
+/// 行级 IAL 设置到前一个行级元素上, 前面是文本时保留为文本
+fn attach_span_ials(nodes: &mut Vec<Node>) {
+    let mut attached: Vec<Node> = Vec::with_capacity(nodes.len());
+    for mut node in nodes.drain(..) {
+        attach_span_ials(&mut node.children);
+        if node.node_type != NodeType::NodeKramdownSpanIal {
+            attached.push(node);
+            continue;
+        }
+        match attached.last_mut() {
+            Some(last) if last.node_type != NodeType::NodeText && last.kramdown_ial.is_empty() => {
+                set_ial(last, std::mem::take(&mut node.kramdown_ial));
+            }
+            _ => attached.push(text_node(node.data.as_deref().unwrap_or_default())),
+        }
+    }
+    *nodes = attached;
+}
+
+impl InlineContext<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn rest(&self) -> &[u8] {
+        &self.tokens[self.pos..]
+    }
+
+    fn text(&self, start: usize, end: usize) -> String {
+        String::from_utf8_lossy(&self.tokens[start..end]).into_owned()
+    }
+
+    fn append(&mut self, node: Node) -> NodeKey {
+        let root = self.tree.root();
+        self.tree.append_child(root, node)
+    }
+
+    pub(super) fn append_text(&mut self, text: &str) -> NodeKey {
+        self.append(text_node(text))
+    }
+
+    /// 需要单独处理的字符, 其余字符作为文本
+    fn is_special(&self, c: u8) -> bool {
+        let options = self.options;
+        match c {
+            b'\n' | b'\\' | b'`' | b'*' | b'_' | b'[' | b']' | b'!' | b'<' | b'&' => true,
+            b'~' => options.gfm_strikethrough || options.sub,
+            b'=' => options.mark,
+            b'^' => options.sup,
+            b'$' => options.inline_math,
+            b'#' => options.tag,
+            b'(' => options.block_ref,
+            b'{' => options.kramdown_span_ial,
+            _ => false,
+        }
+    }
+
+    /// 解析一个行级元素, 无法识别时作为文本
+    fn parse_one(&mut self) {
+        let c = self.tokens[self.pos];
+        let handled = match c {
+            b'\n' => self.parse_newline(),
+            b'\\' => self.parse_backslash(),
+            b'`' => self.parse_backticks(),
+            b'*' | b'_' => {
+                self.handle_delim(c);
+                true
+            }
+            b'~' | b'=' | b'^' if self.is_special(c) => {
+                self.handle_delim(c);
+                true
+            }
+            b'[' => self.parse_open_bracket(),
+            b'!' => self.parse_bang(),
+            b']' => self.parse_close_bracket(),
+            b'<' => self.parse_autolink() || self.parse_inline_html(),
+            b'&' => self.parse_entity(),
+            b'$' if self.options.inline_math => self.parse_inline_math(),
+            b'#' if self.options.tag => self.parse_tag(),
+            b'(' if self.options.block_ref => self.parse_block_ref(),
+            b'{' if self.options.kramdown_span_ial => self.parse_span_ial(),
+            _ => self.parse_string(),
+        };
+        if !handled {
+            let text = self.text(self.pos, self.pos + 1);
+            self.pos += 1;
+            self.append_text(&text);
+        }
+    }
+
+    /// 连续的普通字符
+    fn parse_string(&mut self) -> bool {
+        let start = self.pos;
+        self.pos += 1;
+        while self.peek().is_some_and(|c| !self.is_special(c)) {
+            self.pos += 1;
+        }
+        let text = self.text(start, self.pos);
+        self.append_text(&text);
+        true
+    }
+
+    /// 换行前有两个以上空格时为硬换行, 否则为软换行
+    fn parse_newline(&mut self) -> bool {
+        self.pos += 1;
+        let mut hard = false;
+        if let Some(last) = self.tree.last_child(self.tree.root()) {
+            let node = self.tree.get_mut(last).unwrap();
+            if node.node_type == NodeType::NodeText {
+                let data = node.data.get_or_insert_with(String::new);
+                let trimmed = data.trim_end_matches(' ').len();
+                hard = data.len() - trimmed >= 2;
+                data.truncate(trimmed);
+            }
+        }
+        let node_type = if hard {
+            NodeType::NodeHardBreak
+        } else {
+            NodeType::NodeSoftBreak
+        };
+        self.append(Node::new(node_type));
+        // 跳过下一行开头的空白
+        while matches!(self.peek(), Some(b' ' | b'\t')) {
+            self.pos += 1;
+        }
+        true
+    }
+
+    fn parse_backslash(&mut self) -> bool {
+        self.pos += 1;
+        match self.peek() {
+            Some(b'\n') => {
+                self.pos += 1;
+                self.append(Node::new(NodeType::NodeHardBreak));
+            }
+            Some(c) if c.is_ascii_punctuation() => {
+                self.pos += 1;
+                let mut backslash = Node::new(NodeType::NodeBackslash);
+                backslash.children.push(data_node(
+                    NodeType::NodeBackslashContent,
+                    &(c as char).to_string(),
+                ));
+                self.append(backslash);
+            }
+            _ => {
+                self.append_text("\\");
+            }
+        }
+        true
+    }
+
+    /// 行级代码, 结束标记的反引号数量必须与开始标记相同
+    fn parse_backticks(&mut self) -> bool {
+        let start = self.pos;
+        let len = self.rest().iter().take_while(|&&b| b == b'`').count();
+        let after_open = start + len;
+        let mut i = after_open;
+        while i < self.tokens.len() {
+            if self.tokens[i] != b'`' {
+                i += 1;
+                continue;
+            }
+            let run = self.tokens[i..].iter().take_while(|&&b| b == b'`').count();
+            if run == len {
+                let mut content = self.text(after_open, i).replace('\n', " ");
+                if content.len() > 1
+                    && content.starts_with(' ')
+                    && content.ends_with(' ')
+                    && !content.bytes().all(|b| b == b' ')
+                {
+                    content = content[1..content.len() - 1].to_string();
+                }
+                self.pos = i + run;
+                let mut code_span = Node::new(NodeType::NodeCodeSpan);
+                code_span.code_marker_len = Some(len as i32);
+                code_span.children = vec![
+                    Node::new(NodeType::NodeCodeSpanOpenMarker),
+                    data_node(NodeType::NodeCodeSpanContent, &content),
+                    Node::new(NodeType::NodeCodeSpanCloseMarker),
+                ];
+                self.append(code_span);
+                return true;
+            }
+            i += run;
+        }
+        // 没有匹配的结束标记, 反引号作为文本
+        self.pos = after_open;
+        let text = self.text(start, after_open);
+        self.append_text(&text);
+        true
+    }
+
+    fn push_bracket(&mut self, node: NodeKey, index: usize, image: bool) {
+        if let Some(last) = self.brackets.last_mut() {
+            last.bracket_after = true;
+        }
+        self.brackets.push(Bracket {
+            node,
+            index,
+            image,
+            active: true,
+            bracket_after: false,
+            previous_delimiter: self.delimiters.len(),
+        });
+    }
+
+    fn parse_open_bracket(&mut self) -> bool {
+        let index = self.pos;
+        self.pos += 1;
+        let node = self.append_text("[");
+        self.push_bracket(node, index, false);
+        true
+    }
+
+    fn parse_bang(&mut self) -> bool {
+        if self.tokens.get(self.pos + 1) != Some(&b'[') {
+            return false;
+        }
+        let index = self.pos + 1;
+        self.pos += 2;
+        let node = self.append_text("![");
+        self.push_bracket(node, index, true);
+        true
+    }
+
+    /// `]` 尝试与最近的开方括号组成链接或图片
+    fn parse_close_bracket(&mut self) -> bool {
+        let start = self.pos;
+        self.pos += 1;
+        let Some(opener) = self.brackets.last() else {
+            self.append_text("]");
+            return true;
+        };
+        if !opener.active {
+            self.brackets.pop();
+            self.append_text("]");
+            return true;
+        }
+        let image = opener.image;
+        let opener_index = opener.index;
+        let bracket_after = opener.bracket_after;
+
+        // 内联链接 [text](dest "title")
+        let mut link = self
+            .parse_inline_link()
+            .map(|(dest, title)| (dest, title, None));
+        if link.is_none() {
+            // 链接引用 [text][label], [text][] 或 [text]
+            let before_label = self.pos;
+            let label_len = scan_link_label(self.rest()).unwrap_or(0);
+            let label = if label_len > 2 {
+                Some(&self.tokens[before_label..before_label + label_len])
+            } else if !bracket_after {
+                Some(&self.tokens[opener_index..=start])
+            } else {
+                None
+            };
+            if label_len == 0 {
+                self.pos = before_label;
+            } else {
+                self.pos = before_label + label_len;
+            }
+            link = label
+                .and_then(|label| normalize_label(label).map(|key| (label, key)))
+                .and_then(|(label, key)| {
+                    self.link_refs.get(&key).map(|def| {
+                        let label = label[1..label.len() - 1].to_vec();
+                        (def.destination.clone(), def.title.clone(), Some(label))
+                    })
+                });
+        }
+
+        let Some((dest, title, ref_label)) = link else {
+            self.brackets.pop();
+            self.pos = start + 1;
+            self.append_text("]");
+            return true;
+        };
+
+        let opener = self.brackets.pop().unwrap();
+        self.process_emphasis(opener.previous_delimiter);
+        let mut node = Node::new(if image {
+            NodeType::NodeImage
+        } else {
+            NodeType::NodeLink
+        });
+        node.link_type = Some(if ref_label.is_some() { 3 } else { 0 });
+        node.link_ref_label = ref_label.map(Into::into);
+        if image {
+            node.children.push(Node::new(NodeType::NodeBang));
+        }
+        node.children.push(Node::new(NodeType::NodeOpenBracket));
+        let link_key = self.tree.insert_after(opener.node, node);
+        // 开方括号之后的节点为链接文本
+        while let Some(next) = self.tree.next(link_key) {
+            let child = self.tree.remove(next).unwrap();
+            self.tree.append_child(link_key, child);
+        }
+        self.tree.remove(opener.node);
+        for child in [
+            Node::new(NodeType::NodeCloseBracket),
+            Node::new(NodeType::NodeOpenParen),
+            data_node(NodeType::NodeLinkDest, &dest),
+        ] {
+            self.tree.append_child(link_key, child);
+        }
+        if !title.is_empty() {
+            self.tree
+                .append_child(link_key, Node::new(NodeType::NodeLinkSpace));
+            self.tree
+                .append_child(link_key, data_node(NodeType::NodeLinkTitle, &title));
+        }
+        self.tree
+            .append_child(link_key, Node::new(NodeType::NodeCloseParen));
+
+        // 链接中不能再有链接
+        if !image {
+            for bracket in &mut self.brackets {
+                if !bracket.image {
+                    bracket.active = false;
+                }
+            }
+        }
+        true
+    }
+
+    /// `(dest "title")` 部分, 失败时不移动位置
+    fn parse_inline_link(&mut self) -> Option<(String, String)> {
+        if self.peek() != Some(b'(') {
+            return None;
+        }
+        let tokens = self.tokens;
+        let mut pos = skip_spnl(tokens, self.pos + 1);
+        let (dest, len) = parse_link_destination(&tokens[pos..])?;
+        pos += len;
+        let before_title = pos;
+        pos = skip_spnl(tokens, pos);
+        let mut title = String::new();
+        if pos > before_title {
+            if let Some((text, len)) = parse_link_title(&tokens[pos..]) {
+                title = text;
+                pos = skip_spnl(tokens, pos + len);
+            }
+        }
+        if tokens.get(pos) != Some(&b')') {
+            return None;
+        }
+        self.pos = pos + 1;
+        Some((dest, title))
+    }
+
+    /// `<scheme:...>` 或 `<user@example.com>`
+    fn parse_autolink(&mut self) -> bool {
+        let rest = self.rest();
+        let Some(len) = scan_uri_autolink(rest).or_else(|| scan_email_autolink(rest)) else {
+            return false;
+        };
+        let dest = self.text(self.pos + 1, self.pos + len - 1);
+        self.pos += len;
+        let mut link = Node::new(NodeType::NodeLink);
+        link.link_type = Some(2);
+        link.children = vec![
+            Node::new(NodeType::NodeLess),
+            data_node(NodeType::NodeLinkDest, &dest),
+            Node::new(NodeType::NodeGreater),
+        ];
+        self.append(link);
+        true
+    }
+
+    fn parse_inline_html(&mut self) -> bool {
+        let rest = self.rest();
+        let Some(len) = scan_open_tag(rest)
+            .or_else(|| scan_closing_tag(rest))
+            .or_else(|| scan_html_comment(rest))
+            .or_else(|| scan_until(rest, b"<?", b"?>"))
+            .or_else(|| scan_until(rest, b"<![CDATA[", b"]]>"))
+            .or_else(|| scan_declaration(rest))
+        else {
+            return false;
+        };
+        let html = self.text(self.pos, self.pos + len);
+        self.pos += len;
+        self.append(data_node(NodeType::NodeInlineHtml, &html));
+        true
+    }
+
+    fn parse_entity(&mut self) -> bool {
+        let Some((text, len)) = parse_entity(self.rest()) else {
+            return false;
+        };
+        let mut entity = data_node(NodeType::NodeHtmlEntity, &text);
+        entity.html_entity_tokens = Some(self.rest()[..len].to_vec().into());
+        self.pos += len;
+        self.append(entity);
+        true
+    }
+
+    /// `$...$`, 开始标记后不能是空白, 结束标记前不能是空白
+    fn parse_inline_math(&mut self) -> bool {
+        let start = self.pos + 1;
+        match self.tokens.get(start) {
+            None | Some(b'$' | b' ' | b'\t' | b'\n') => return false,
+            Some(c) if c.is_ascii_digit() && !self.options.inline_math_allow_digit_after_open => {
+                return false
+            }
+            _ => {}
+        }
+        let mut i = start;
+        while i < self.tokens.len() {
+            match self.tokens[i] {
+                b'\\' => i += 2,
+                b'$' if !self.tokens[i - 1].is_ascii_whitespace() => {
+                    let content = self.text(start, i);
+                    self.pos = i + 1;
+                    let mut math = Node::new(NodeType::NodeInlineMath);
+                    math.children = vec![
+                        Node::new(NodeType::NodeInlineMathOpenMarker),
+                        data_node(NodeType::NodeInlineMathContent, &content),
+                        Node::new(NodeType::NodeInlineMathCloseMarker),
+                    ];
+                    self.append(math);
+                    return true;
+                }
+                _ => i += 1,
+            }
+        }
+        false
+    }
+
+    /// 思源标签 `#标签#`, 不能跨行, 首尾不能是空白
+    fn parse_tag(&mut self) -> bool {
+        let start = self.pos + 1;
+        let Some(end) = self.tokens[start..]
+            .iter()
+            .position(|&b| b == b'#' || b == b'\n')
+            .map(|i| start + i)
+        else {
+            return false;
+        };
+        let content = &self.tokens[start..end];
+        if self.tokens[end] != b'#'
+            || content.is_empty()
+            || content[0].is_ascii_whitespace()
+            || content[content.len() - 1].is_ascii_whitespace()
+        {
+            return false;
+        }
+        let text = self.text(start, end);
+        self.pos = end + 1;
+        let mut tag = Node::new(NodeType::NodeTag);
+        tag.children = vec![
+            Node::new(NodeType::NodeTagOpenMarker),
+            text_node(&text),
+            Node::new(NodeType::NodeTagCloseMarker),
+        ];
+        self.append(tag);
+        true
+    }
+
+    /// 行级 IAL `{: style="..."}`
+    fn parse_span_ial(&mut self) -> bool {
+        let Some((ial, len)) = parse_ial(self.rest()) else {
+            return false;
+        };
+        let mut node = data_node(
+            NodeType::NodeKramdownSpanIal,
+            &self.text(self.pos, self.pos + len),
+        );
+        node.kramdown_ial = ial;
+        self.pos += len;
+        self.append(node);
+        true
+    }
+
+    /// 块引用 `((id "锚文本"))`, 单引号为动态锚文本
+    fn parse_block_ref(&mut self) -> bool {
+        let rest = self.rest();
+        const ID_LEN: usize = 22;
+        if !rest.starts_with(b"((") || rest.len() < ID_LEN + 4 {
+            return false;
+        }
+        let id = String::from_utf8_lossy(&rest[2..2 + ID_LEN]).into_owned();
+        if !is_node_id_pattern(&id) {
+            return false;
+        }
+        let mut pos = 2 + ID_LEN;
+        let mut anchor = None;
+        if rest[pos] == b' ' {
+            let Some(&quote @ (b'"' | b'\'')) = rest.get(pos + 1) else {
+                return false;
+            };
+            let text_start = pos + 2;
+            let Some(len) = rest[text_start..]
+                .iter()
+                .position(|&b| b == quote || b == b'\n')
+                .filter(|&len| rest[text_start + len] == quote)
+            else {
+                return false;
+            };
+            let text = String::from_utf8_lossy(&rest[text_start..text_start + len]).into_owned();
+            anchor = Some((quote, text));
+            pos = text_start + len + 1;
+        }
+        if !rest[pos..].starts_with(b"))") {
+            return false;
+        }
+        self.pos += pos + 2;
+
+        let mut block_ref = Node::new(NodeType::NodeBlockRef);
+        block_ref.children = vec![
+            Node::new(NodeType::NodeOpenParen),
+            Node::new(NodeType::NodeOpenParen),
+            data_node(NodeType::NodeBlockRefId, &id),
+        ];
+        if let Some((quote, text)) = anchor {
+            let text_type = if quote == b'\'' {
+                NodeType::NodeBlockRefDynamicText
+            } else {
+                NodeType::NodeBlockRefText
+            };
+            block_ref
+                .children
+                .push(Node::new(NodeType::NodeBlockRefSpace));
+            block_ref.children.push(data_node(text_type, &text));
+        }
+        block_ref.children.extend([
+            Node::new(NodeType::NodeCloseParen),
+            Node::new(NodeType::NodeCloseParen),
+        ]);
+        self.append(block_ref);
+        true
+    }
+}
+
+/// `<scheme:...>`, scheme 为 2 到 32 个字符
+fn scan_uri_autolink(s: &[u8]) -> Option<usize> {
+    let scheme_len = s[1..]
+        .iter()
+        .take_while(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'.' | b'-'))
+        .count();
+    if !(2..=32).contains(&scheme_len)
+        || !s[1].is_ascii_alphabetic()
+        || s.get(1 + scheme_len) != Some(&b':')
+    {
+        return None;
+    }
+    let start = 2 + scheme_len;
+    let len = s[start..]
+        .iter()
+        .take_while(|&&b| b > b' ' && b != b'<' && b != b'>' && b != 0x7f)
+        .count();
+    (s.get(start + len) == Some(&b'>')).then_some(start + len + 1)
+}
+
+/// `<user@example.com>`
+fn scan_email_autolink(s: &[u8]) -> Option<usize> {
+    let local = s[1..]
+        .iter()
+        .take_while(|&&b| b.is_ascii_alphanumeric() || b".!#$%&'*+/=?^_`{|}~-".contains(&b))
+        .count();
+    if local == 0 || s.get(1 + local) != Some(&b'@') {
+        return None;
+    }
+    let mut pos = 2 + local;
+    loop {
+        // 域名的每一段以字母或数字开始和结束, 至多 63 个字符
+        let label = s[pos..]
+            .iter()
+            .take_while(|b| b.is_ascii_alphanumeric() || **b == b'-')
+            .count();
+        if label == 0 || label > 63 || s[pos] == b'-' || s[pos + label - 1] == b'-' {
+            return None;
+        }
+        pos += label;
+        match s.get(pos) {
+            Some(b'.') => pos += 1,
+            Some(b'>') => return Some(pos + 1),
+            _ => return None,
+        }
+    }
+}
+
+/// `<!-->`, `<!--->` 或 `<!-- ... -->`
+fn scan_html_comment(s: &[u8]) -> Option<usize> {
+    if !s.starts_with(b"<!--") {
+        return None;
+    }
+    if s[4..].starts_with(b">") {
+        return Some(5);
+    }
+    if s[4..].starts_with(b"->") {
+        return Some(6);
+    }
+    scan_until(s, b"<!--", b"-->")
+}
+
+/// 以 `open` 开始, 到第一个 `close` 结束
+fn scan_until(s: &[u8], open: &[u8], close: &[u8]) -> Option<usize> {
+    if !s.starts_with(open) {
+        return None;
+    }
+    s[open.len()..]
+        .windows(close.len())
+        .position(|w| w == close)
+        .map(|i| open.len() + i + close.len())
+}
+
+/// `<!DOCTYPE ...>` 等声明
+fn scan_declaration(s: &[u8]) -> Option<usize> {
+    if !s.starts_with(b"<!") || !s.get(2)?.is_ascii_alphabetic() {
+        return None;
+    }
+    s.iter().position(|&b| b == b'>').map(|i| i + 1)
+}
+
+/// GFM 扩展的自动链接: 文本中的 `www.`, `http://`, `https://` 与邮箱
+fn auto_link(nodes: Vec<Node>) -> Vec<Node> {
+    let mut result = Vec::with_capacity(nodes.len());
+    for mut node in nodes {
+        match node.node_type {
+            NodeType::NodeText => {
+                let text = node.data.take().unwrap_or_default();
+                result.extend(split_auto_links(&text));
+            }
+            // 链接与代码中不再识别
+            NodeType::NodeLink | NodeType::NodeImage | NodeType::NodeCodeSpan => result.push(node),
+            _ => {
+                node.children = auto_link(std::mem::take(&mut node.children));
+                result.push(node);
+            }
+        }
+    }
+    result
+}
+
+fn split_auto_links(text: &str) -> Vec<Node> {
+    let mut nodes = Vec::new();
+    let mut last = 0;
+    let mut i = 0;
+    let bytes = text.as_bytes();
+    while i < bytes.len() {
+        // 只在单词开头识别
+        let at_boundary = i == 0
+            || matches!(
+                bytes[i - 1],
+                b' ' | b'\t' | b'\n' | b'*' | b'_' | b'~' | b'('
+            );
+        let len = if at_boundary {
+            scan_extended_url(&bytes[i..])
+        } else {
+            None
+        }
+        .or_else(|| scan_extended_email(bytes, i));
+        match len {
+            Some((start, len)) => {
+                let start = i - start;
+                if start > last {
+                    nodes.push(text_node(&text[last..start]));
+                }
+                let mut link = Node::new(NodeType::NodeLink);
+                link.link_type = Some(2);
+                link.children = vec![data_node(NodeType::NodeLinkDest, &text[start..start + len])];
+                nodes.push(link);
+                last = start + len;
+                i = last;
+            }
+            None => i += 1,
+        }
+    }
+    if last < text.len() {
+        nodes.push(text_node(&text[last..]));
+    }
+    nodes
+}
+
+/// `www.` 或 `http(s)://` 开头的链接, 返回 (0, 长度)
+fn scan_extended_url(s: &[u8]) -> Option<(usize, usize)> {
+    let prefix = [&b"www."[..], b"http://", b"https://"]
+        .into_iter()
+        .find(|prefix| s.len() > prefix.len() && s[..prefix.len()].eq_ignore_ascii_case(prefix))?;
+    let mut end = s
+        .iter()
+        .position(|&b| b.is_ascii_whitespace() || b == b'<')
+        .unwrap_or(s.len());
+    // 域名至少一个 `.`, 最后两段不能有 `_`
+    let domain_end = s[prefix.len()..end]
+        .iter()
+        .position(|b| !(b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.')))
+        .map_or(end, |i| prefix.len() + i);
+    let domain = &s[prefix.len()..domain_end];
+    let segments = domain.split(|&b| b == b'.').collect::<Vec<_>>();
+    if domain.is_empty()
+        || (prefix != b"www." && segments.len() < 2)
+        || segments
+            .iter()
+            .rev()
+            .take(2)
+            .any(|segment| segment.contains(&b'_'))
+    {
+        return None;
+    }
+    // 去掉结尾的标点与未配对的右括号
+    loop {
+        match s[end - 1] {
+            b'?' | b'!' | b'.' | b',' | b':' | b'*' | b'_' | b'~' => end -= 1,
+            b')' => {
+                let open = s[..end].iter().filter(|&&b| b == b'(').count();
+                let close = s[..end].iter().filter(|&&b| b == b')').count();
+                if close > open {
+                    end -= 1;
+                } else {
+                    break;
+                }
+            }
+            b';' => {
+                // `&hl;` 形式的实体引用不属于链接
+                let amp = s[..end - 1]
+                    .iter()
+                    .rposition(|b| !b.is_ascii_alphanumeric())
+                    .filter(|&i| s[i] == b'&');
+                match amp {
+                    Some(i) => end = i,
+                    None => break,
+                }
+            }
+            _ => break,
+        }
+        if end <= domain_end.min(prefix.len() + 1) {
+            return None;
+        }
+    }
+    Some((0, end))
+}
+
+/// 文本中的邮箱, 返回 (`@` 之前部分的长度, 总长度)
+fn scan_extended_email(s: &[u8], at: usize) -> Option<(usize, usize)> {
+    if s[at] != b'@' {
+        return None;
+    }
+    let local = s[..at]
+        .iter()
+        .rev()
+        .take_while(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-' | b'_' | b'+'))
+        .count();
+    if local == 0 {
+        return None;
+    }
+    let mut end = at + 1;
+    end += s[end..]
+        .iter()
+        .take_while(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-' | b'_'))
+        .count();
+    while end > at + 1 && s[end - 1] == b'.' {
+        end -= 1;
+    }
+    let domain = &s[at + 1..end];
+    if !domain.contains(&b'.') || matches!(domain.last(), Some(b'-' | b'_')) {
+        return None;
+    }
+    Some((local, local + end - at))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(markdown: &str, options: &Options) -> Vec<Node> {
+        parse_inline(markdown.as_bytes(), options, &HashMap::new())
+    }
+
+    fn types(nodes: &[Node]) -> Vec<&'static str> {
+        nodes.iter().map(|node| node.node_type.name()).collect()
+    }
+
+    #[test]
+    fn test_emphasis() {
+        let nodes = parse("***a** b* _c_", &Options::commonmark());
+        assert_eq!(types(&nodes), ["NodeEmphasis", "NodeText", "NodeEmphasis"]);
+        let strong = &nodes[0].children[1];
+        assert_eq!(
+            types(&strong.children),
+            [
+                "NodeStrongA6kOpenMarker",
+                "NodeText",
+                "NodeStrongA6kCloseMarker"
+            ]
+        );
+        assert_eq!(nodes[1].data.as_deref(), Some(" "));
+        assert_eq!(
+            nodes[2].children[0].node_type,
+            NodeType::NodeEmU8eOpenMarker
+        );
+    }
+
+    #[test]
+    fn test_extensions() {
+        let siyuan = Options::siyuan();
+        let nodes = parse("~~s~~ ~b~ ==m== ^p^ $x^2$ #tag# ~~~x~~~", &siyuan);
+        assert_eq!(
+            types(&nodes),
+            [
+                "NodeStrikethrough",
+                "NodeText",
+                "NodeSub",
+                "NodeText",
+                "NodeMark",
+                "NodeText",
+                "NodeSup",
+                "NodeText",
+                "NodeInlineMath",
+                "NodeText",
+                "NodeTag",
+                "NodeText"
+            ]
+        );
+        assert_eq!(nodes[8].children[1].data.as_deref(), Some("x^2"));
+        assert_eq!(nodes[10].children[1].data.as_deref(), Some("tag"));
+        assert_eq!(nodes[11].data.as_deref(), Some(" ~~~x~~~"));
+
+        // 关闭扩展时为普通文本
+        let nodes = parse("~~s~~ ==m== $x$ #tag#", &Options::commonmark());
+        assert_eq!(types(&nodes), ["NodeText"]);
+
+        // GFM 的单个 `~` 为删除线
+        let nodes = parse("~s~", &Options::gfm());
+        assert_eq!(
+            nodes[0].children[0].node_type,
+            NodeType::NodeStrikethrough1OpenMarker
+        );
+    }
+
+    #[test]
+    fn test_block_ref() {
+        let nodes = parse(
+            "((20240301110000-aaaaaaa \"锚文本\")) ((20240301110000-bbbbbbb 'dyn')) ((x))",
+            &Options::siyuan(),
+        );
+        assert_eq!(
+            types(&nodes),
+            ["NodeBlockRef", "NodeText", "NodeBlockRef", "NodeText"]
+        );
+        assert_eq!(
+            types(&nodes[0].children),
+            [
+                "NodeOpenParen",
+                "NodeOpenParen",
+                "NodeBlockRefID",
+                "NodeBlockRefSpace",
+                "NodeBlockRefText",
+                "NodeCloseParen",
+                "NodeCloseParen"
+            ]
+        );
+        assert_eq!(
+            nodes[0].children[2].data.as_deref(),
+            Some("20240301110000-aaaaaaa")
+        );
+        assert_eq!(nodes[0].children[4].data.as_deref(), Some("锚文本"));
+        assert_eq!(
+            nodes[2].children[4].node_type,
+            NodeType::NodeBlockRefDynamicText
+        );
+        assert_eq!(nodes[3].data.as_deref(), Some(" ((x))"));
+    }
+
+    #[test]
+    fn test_links() {
+        let mut refs = HashMap::new();
+        refs.insert(
+            "REF".to_string(),
+            LinkRefDef {
+                destination: "/ref".to_string(),
+                title: String::new(),
+            },
+        );
+        let nodes = parse_inline(
+            b"[a *b*](/u \"t\") ![img][ref] <https://x.y> www.example.com/p).",
+            &Options::gfm(),
+            &refs,
+        );
+        assert_eq!(
+            types(&nodes),
+            [
+                "NodeLink",
+                "NodeText",
+                "NodeImage",
+                "NodeText",
+                "NodeLink",
+                "NodeText",
+                "NodeLink",
+                "NodeText"
+            ]
+        );
+        assert_eq!(
+            types(&nodes[0].children),
+            [
+                "NodeOpenBracket",
+                "NodeText",
+                "NodeEmphasis",
+                "NodeCloseBracket",
+                "NodeOpenParen",
+                "NodeLinkDest",
+                "NodeLinkSpace",
+                "NodeLinkTitle",
+                "NodeCloseParen"
+            ]
+        );
+        assert_eq!(nodes[2].link_type, Some(3));
+        assert_eq!(nodes[2].children[0].node_type, NodeType::NodeBang);
+        assert_eq!(nodes[2].children[5].data.as_deref(), Some("/ref"));
+        assert_eq!(nodes[4].children[1].data.as_deref(), Some("https://x.y"));
+        assert_eq!(
+            nodes[6].children[0].data.as_deref(),
+            Some("www.example.com/p")
+        );
+        assert_eq!(nodes[7].data.as_deref(), Some(")."));
+    }
+}
@@ -0,0 +1,220 @@
+//! 链接引用定义 `[label]: destination "title"`.
+//!
+//! 标签、目标与标题的扫描函数也供行级解析的链接使用.
+use super::escape::unescape_string;
+use std::collections::HashMap;
+
+/// 链接引用定义的目标与标题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkRefDef {
+    pub destination: String,
+    pub title: String,
+}
+
+/// 解析 `s` 开头的链接引用定义, 成功时返回消耗的字节数 (包含行尾换行)
+///
+/// 同一标签只保留第一次定义.
+pub(super) fn parse_link_ref_def(
+    s: &[u8],
+    refs: &mut HashMap<String, LinkRefDef>,
+) -> Option<usize> {
+    let label_len = scan_link_label(s)?;
+    let mut pos = label_len;
+    if s.get(pos) != Some(&b':') {
+        return None;
+    }
+    pos = skip_spnl(s, pos + 1);
+    let (destination, destination_len) = parse_link_destination(&s[pos..])?;
+    pos += destination_len;
+
+    let before_title = pos;
+    pos = skip_spnl(s, pos);
+    let mut title = None;
+    if pos != before_title {
+        if let Some((text, len)) = parse_link_title(&s[pos..]) {
+            title = Some(text);
+            pos += len;
+        }
+    }
+    if title.is_none() {
+        pos = before_title;
+    }
+
+    // 定义之后只能有空白
+    let at_line_end = |pos: usize| {
+        let end = pos
+            + s[pos..]
+                .iter()
+                .take_while(|&&b| b == b' ' || b == b'\t')
+                .count();
+        (end == s.len() || s[end] == b'\n').then_some(end)
+    };
+    let end = match at_line_end(pos) {
+        Some(end) => end,
+        // 标题之后还有内容时, 标题所在行不属于定义
+        None if title.is_some() => {
+            title = None;
+            at_line_end(before_title)?
+        }
+        None => return None,
+    };
+
+    let label = normalize_label(&s[..label_len])?;
+    refs.entry(label).or_insert(LinkRefDef {
+        destination,
+        title: title.unwrap_or_default(),
+    });
+    Some(if end < s.len() { end + 1 } else { end })
+}
+
+/// 扫描 `[...]` 形式的链接标签, 返回包含方括号的长度
+pub(crate) fn scan_link_label(s: &[u8]) -> Option<usize> {
+    if s.first() != Some(&b'[') {
+        return None;
+    }
+    let mut i = 1;
+    while i < s.len() {
+        match s[i] {
+            b'\\' if i + 1 < s.len() => i += 2,
+            b'[' => return None,
+            b']' => return (i <= 1000).then_some(i + 1),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// 规范化链接标签: 折叠空白并做 Unicode 大小写折叠, 空标签返回 `None`
+pub(crate) fn normalize_label(label: &[u8]) -> Option<String> {
+    let inner = String::from_utf8_lossy(&label[1..label.len() - 1]);
+    let collapsed = inner.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.is_empty() {
+        return None;
+    }
+    Some(collapsed.to_lowercase().to_uppercase())
+}
+
+/// 解析链接目标, 返回反转义后的目标与消耗的字节数
+pub(crate) fn parse_link_destination(s: &[u8]) -> Option<(String, usize)> {
+    if s.first() == Some(&b'<') {
+        let mut i = 1;
+        loop {
+            match s.get(i) {
+                None | Some(b'\n' | b'<') => return None,
+                Some(b'>') => return Some((unescape_string(&s[1..i]), i + 1)),
+                Some(b'\\') => match s.get(i + 1) {
+                    Some(b'\n') | None => return None,
+                    Some(_) => i += 2,
+                },
+                Some(_) => i += 1,
+            }
+        }
+    }
+
+    let mut i = 0;
+    let mut parens = 0;
+    while let Some(&c) = s.get(i) {
+        match c {
+            b'\\' if s.get(i + 1).is_some_and(u8::is_ascii_punctuation) => i += 2,
+            b'(' => {
+                parens += 1;
+                i += 1;
+            }
+            b')' => {
+                if parens == 0 {
+                    break;
+                }
+                parens -= 1;
+                i += 1;
+            }
+            c if c == b' ' || c.is_ascii_control() => break,
+            _ => i += 1,
+        }
+    }
+    if i == 0 && s.first() != Some(&b')') || parens != 0 {
+        return None;
+    }
+    Some((unescape_string(&s[..i]), i))
+}
+
+/// 解析 `"..."`, `'...'` 或 `(...)` 形式的链接标题
+pub(crate) fn parse_link_title(s: &[u8]) -> Option<(String, usize)> {
+    let close = match s.first()? {
+        b'"' => b'"',
+        b'\'' => b'\'',
+        b'(' => b')',
+        _ => return None,
+    };
+    let mut i = 1;
+    while i < s.len() {
+        let c = s[i];
+        if c == b'\\' && i + 1 < s.len() {
+            i += 2;
+            continue;
+        }
+        if c == close {
+            return Some((unescape_string(&s[1..i]), i + 1));
+        }
+        if close == b')' && c == b'(' {
+            return None;
+        }
+        i += 1;
+    }
+    None
+}
+
+/// 跳过空白与至多一个换行
+pub(crate) fn skip_spnl(s: &[u8], mut pos: usize) -> usize {
+    let skip_spaces = |pos: usize| {
+        pos + s[pos..]
+            .iter()
+            .take_while(|&&b| b == b' ' || b == b'\t')
+            .count()
+    };
+    pos = skip_spaces(pos);
+    if s.get(pos) == Some(&b'\n') {
+        pos = skip_spaces(pos + 1);
+    }
+    pos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_link_ref_def() {
+        let mut refs = HashMap::new();
+        let s = b"[Foo  Bar]:\n  </url\\>> 'ti\\'tle'\nrest";
+        assert_eq!(parse_link_ref_def(s, &mut refs), Some(s.len() - 4));
+        assert_eq!(
+            refs["FOO BAR"],
+            LinkRefDef {
+                destination: "/url>".to_string(),
+                title: "ti'tle".to_string()
+            }
+        );
+
+        // 标题后有多余内容时标题行不属于定义
+        let s = b"[a]: /b\n\"t\" x";
+        assert_eq!(parse_link_ref_def(s, &mut refs), Some(8));
+        assert_eq!(refs["A"].title, "");
+
+        // 重复定义保留第一个
+        assert!(parse_link_ref_def(b"[foo bar]: /other", &mut refs).is_some());
+        assert_eq!(refs["FOO BAR"].destination, "/url>");
+
+        assert_eq!(parse_link_ref_def(b"[]: /x", &mut refs), None);
+        assert_eq!(parse_link_ref_def(b"[x]: /a b", &mut refs), None);
+        assert_eq!(parse_link_ref_def(b"[x]:", &mut refs), None);
+    }
+
+    #[test]
+    fn test_normalize_label() {
+        assert_eq!(
+            normalize_label("[ẞ\t x ]".as_bytes()).as_deref(),
+            Some("SS X")
+        );
+        assert_eq!(normalize_label(b"[ \n ]"), None);
+    }
+}
@@ -0,0 +1,125 @@
+//! GFM 表格. 与 Lute 一致, 在段落关闭时检查段落内容是否构成表格.
+use crate::lute::node::{Node, NodeType};
+
+/// 将段落内容解析为表格, 第一行为表头, 第二行为分隔行
+pub(super) fn parse_table(content: &[u8]) -> Option<Node> {
+    let mut lines = content
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.is_empty());
+    let head = lines.next()?;
+    let delimiter = lines.next()?;
+    if !contains_pipe(head) || !contains_pipe(delimiter) {
+        return None;
+    }
+    let aligns = split_cells(delimiter)
+        .iter()
+        .map(|cell| parse_align(cell))
+        .collect::<Option<Vec<_>>>()?;
+    let head_cells = split_cells(head);
+    if head_cells.len() != aligns.len() {
+        return None;
+    }
+
+    let mut table = Node::new(NodeType::NodeTable);
+    let mut table_head = Node::new(NodeType::NodeTableHead);
+    table_head.data = Some("thead".to_string());
+    table_head.children.push(row(head_cells, &aligns, "th"));
+    table.children.push(table_head);
+    for line in lines {
+        table.children.push(row(split_cells(line), &aligns, "td"));
+    }
+    table.table_aligns = aligns;
+    Some(table)
+}
+
+/// 按分隔行的列数生成一行, 缺少的单元格补空, 多余的丢弃
+fn row(mut cells: Vec<Vec<u8>>, aligns: &[i32], tag: &str) -> Node {
+    cells.resize(aligns.len(), Vec::new());
+    let mut row = Node::new(NodeType::NodeTableRow);
+    row.data = Some("tr".to_string());
+    for (content, &align) in cells.into_iter().zip(aligns) {
+        let mut cell = Node::new(NodeType::NodeTableCell);
+        cell.data = Some(tag.to_string());
+        if align != 0 {
+            cell.table_cell_align = Some(align);
+        }
+        cell.tokens = content.into();
+        row.children.push(cell);
+    }
+    row
+}
+
+/// `:---` 为 1 (左), `:---:` 为 2 (中), `---:` 为 3 (右), `---` 为 0
+fn parse_align(cell: &[u8]) -> Option<i32> {
+    let left = cell.first() == Some(&b':');
+    let right = cell.len() > 1 && cell.last() == Some(&b':');
+    let dashes = &cell[left as usize..cell.len() - right as usize];
+    if dashes.is_empty() || dashes.iter().any(|&b| b != b'-') {
+        return None;
+    }
+    Some(match (left, right) {
+        (true, true) => 2,
+        (true, false) => 1,
+        (false, true) => 3,
+        (false, false) => 0,
+    })
+}
+
+fn contains_pipe(line: &[u8]) -> bool {
+    (0..line.len()).any(|i| line[i] == b'|' && (i == 0 || line[i - 1] != b'\\'))
+}
+
+/// 按未转义的 `|` 切分单元格, 去掉首尾的 `|` 与单元格两侧的空白, `\|` 还原为 `|`
+fn split_cells(line: &[u8]) -> Vec<Vec<u8>> {
+    let line = line.trim_ascii();
+    let line = line.strip_prefix(b"|").unwrap_or(line);
+    let mut cells = Vec::new();
+    let mut cell = Vec::new();
+    let mut i = 0;
+    while i < line.len() {
+        match line[i] {
+            b'\\' if line.get(i + 1) == Some(&b'|') => {
+                cell.push(b'|');
+                i += 2;
+                continue;
+            }
+            b'|' => cells.push(std::mem::take(&mut cell).trim_ascii().to_vec()),
+            b => cell.push(b),
+        }
+        i += 1;
+    }
+    // 结尾没有 `|` 时最后一个单元格
+    if !cell.trim_ascii().is_empty() || !line.ends_with(b"|") {
+        cells.push(cell.trim_ascii().to_vec());
+    }
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_cells() {
+        assert_eq!(
+            split_cells(b"| a | b\\|c |  |"),
+            [b"a".to_vec(), b"b|c".to_vec(), Vec::new()]
+        );
+        assert_eq!(split_cells(b"a|b"), [b"a".to_vec(), b"b".to_vec()]);
+        assert_eq!(split_cells(b"|a|"), [b"a".to_vec()]);
+    }
+
+    #[test]
+    fn test_parse_table() {
+        let table = parse_table(b"| a | b | c |\n| :-- | :-: | --: |\n| 1 |\n").unwrap();
+        assert_eq!(table.table_aligns, [1, 2, 3]);
+        let body = &table.children[1];
+        assert_eq!(body.children.len(), 3);
+        assert_eq!(&body.children[0].tokens[..], b"1");
+        assert_eq!(body.children[2].table_cell_align, Some(3));
+
+        assert!(parse_table(b"| a | b |\n| --- |\n").is_none());
+        assert!(parse_table(b"| a |\n| -x- |\n").is_none());
+        assert!(parse_table(b"a\n---\n").is_none());
+    }
+}
@@ -0,0 +1,504 @@
+//! 将解析得到的 Markdown 树转换为思源的块结构, 对应 Lute 的 protyle 模式.
+//!
+//! 思源不使用强调、链接等行级节点, 而是统一为 `NodeTextMark`, 嵌套的格式合并到
+//! 以空格分隔的 `TextMarkType` 中, 例如 `***a***` 为 `em strong`. 块节点需要有 ID 与
+//! `updated` 属性.
+use crate::lute::node::{new_node_id, Node, NodeType};
+use crate::lute::parse::{parse, Options};
+use crate::lute::walk::{walk_mut, WalkStatus};
+use std::collections::HashMap;
+
+/// 解析 Markdown 并转换为思源的文档节点, 使用思源编辑器的语法
+pub fn markdown_to_protyle(markdown: &str) -> Node {
+    let mut root = parse("", markdown, &Options::siyuan()).root;
+    root.spec = Some("1".to_string());
+    to_protyle(&mut root);
+    root
+}
+
+/// 为没有 ID 的块分配 ID 并设置 `updated`, 行级节点转换为文本标记
+pub fn to_protyle(root: &mut Node) {
+    walk_mut(root, &mut |node, entering| {
+        if !entering {
+            return WalkStatus::Continue;
+        }
+        if node.node_type.is_block() && node.id.is_none() {
+            let id = new_node_id();
+            let properties = node.properties.get_or_insert_with(HashMap::new);
+            properties.insert("id".to_string(), id.clone());
+            // 与内核一致, 新块的更新时间取自 ID 的时间部分
+            properties
+                .entry("updated".to_string())
+                .or_insert_with(|| id[..14].to_string());
+            node.id = Some(id);
+        }
+        match node.node_type {
+            NodeType::NodeParagraph | NodeType::NodeHeading | NodeType::NodeTableCell => {
+                node.children = text_marks(std::mem::take(&mut node.children));
+                WalkStatus::SkipChildren
+            }
+            _ => WalkStatus::Continue,
+        }
+    });
+}
+
+/// 行级节点转换为文本与文本标记
+fn text_marks(nodes: Vec<Node>) -> Vec<Node> {
+    let mut out = Vec::new();
+    for node in nodes {
+        flatten(node, None, &mut out);
+    }
+    out
+}
+
+/// 子节点中去掉首尾的开始、结束标记
+fn inner(mut children: Vec<Node>) -> Vec<Node> {
+    if children.len() >= 2 {
+        children.pop();
+        children.remove(0);
+    }
+    children
+}
+
+fn child_data(node: &Node, node_type: NodeType) -> String {
+    node.children
+        .iter()
+        .find(|child| child.node_type == node_type)
+        .and_then(|child| child.data.clone())
+        .unwrap_or_default()
+}
+
+/// 节点的纯文本
+fn plain_text(node: &Node, out: &mut String) {
+    for child in &node.children {
+        match child.node_type {
+            NodeType::NodeText
+            | NodeType::NodeCodeSpanContent
+            | NodeType::NodeInlineMathContent
+            | NodeType::NodeHtmlEntity
+            | NodeType::NodeBackslashContent => {
+                out.push_str(child.data.as_deref().unwrap_or_default())
+            }
+            NodeType::NodeSoftBreak | NodeType::NodeHardBreak => out.push('\n'),
+            NodeType::NodeLinkDest | NodeType::NodeLinkTitle => {}
+            _ => plain_text(child, out),
+        }
+    }
+}
+
+/// 在外层文本标记的基础上增加一种类型
+fn with_type(outer: Option<&Node>, mark_type: &str) -> Node {
+    let mut mark = outer
+        .cloned()
+        .unwrap_or_else(|| Node::new(NodeType::NodeTextMark));
+    let types = mark.text_mark_type.get_or_insert_with(String::new);
+    if !types.split(' ').any(|t| t == mark_type) {
+        if !types.is_empty() {
+            types.push(' ');
+        }
+        types.push_str(mark_type);
+    }
+    mark
+}
+
+/// 递归展开行级节点, `outer` 为外层格式合并成的文本标记
+fn flatten(mut node: Node, outer: Option<&Node>, out: &mut Vec<Node>) {
+    // 行级 IAL 的属性由展开得到的文本标记继承
+    if let Some(properties) = node.properties.take().filter(|_| {
+        matches!(
+            node.node_type,
+            NodeType::NodeEmphasis
+                | NodeType::NodeStrong
+                | NodeType::NodeStrikethrough
+                | NodeType::NodeMark
+                | NodeType::NodeSup
+                | NodeType::NodeSub
+                | NodeType::NodeLink
+                | NodeType::NodeCodeSpan
+                | NodeType::NodeInlineMath
+                | NodeType::NodeTag
+                | NodeType::NodeBlockRef
+        )
+    }) {
+        let mut mark = outer
+            .cloned()
+            .unwrap_or_else(|| Node::new(NodeType::NodeTextMark));
+        mark.properties
+            .get_or_insert_with(HashMap::new)
+            .extend(properties);
+        node.kramdown_ial.clear();
+        flatten(node, Some(&mark), out);
+        return;
+    }
+    let mark_type = match node.node_type {
+        NodeType::NodeEmphasis => "em",
+        NodeType::NodeStrong => "strong",
+        NodeType::NodeStrikethrough => "s",
+        NodeType::NodeMark => "mark",
+        NodeType::NodeSup => "sup",
+        NodeType::NodeSub => "sub",
+        NodeType::NodeText | NodeType::NodeHtmlEntity => {
+            push_text(node.data.as_deref().unwrap_or_default(), outer, out);
+            return;
+        }
+        NodeType::NodeSoftBreak | NodeType::NodeHardBreak => {
+            push_text("\n", outer, out);
+            return;
+        }
+        NodeType::NodeBackslash if outer.is_some() => {
+            let text = child_data(&node, NodeType::NodeBackslashContent);
+            push_text(&text, outer, out);
+            return;
+        }
+        NodeType::NodeLink => {
+            flatten_link(node, outer, out);
+            return;
+        }
+        NodeType::NodeCodeSpan => {
+            let mut mark = with_type(outer, "code");
+            mark.text_mark_text_content = Some(child_data(&node, NodeType::NodeCodeSpanContent));
+            out.push(mark);
+            return;
+        }
+        NodeType::NodeInlineMath => {
+            let mut mark = with_type(outer, "inline-math");
+            mark.text_mark_text_content = Some(String::new());
+            mark.text_mark_inline_math_content =
+                Some(child_data(&node, NodeType::NodeInlineMathContent));
+            out.push(mark);
+            return;
+        }
+        NodeType::NodeTag => {
+            let mut mark = with_type(outer, "tag");
+            let mut text = String::new();
+            plain_text(&node, &mut text);
+            mark.text_mark_text_content = Some(text);
+            out.push(mark);
+            return;
+        }
+        NodeType::NodeBlockRef => {
+            out.push(block_ref(&node, outer));
+            return;
+        }
+        NodeType::NodeImage => {
+            out.push(image(node));
+            return;
+        }
+        _ => {
+            out.push(node);
+            return;
+        }
+    };
+    let mark = with_type(outer, mark_type);
+    for child in inner(node.children) {
+        flatten(child, Some(&mark), out);
+    }
+}
+
+fn flatten_link(node: Node, outer: Option<&Node>, out: &mut Vec<Node>) {
+    let mut mark = with_type(outer, "a");
+    let dest = child_data(&node, NodeType::NodeLinkDest);
+    let title = child_data(&node, NodeType::NodeLinkTitle);
+    mark.text_mark_a_title = (!title.is_empty()).then_some(title);
+
+    // 自动链接的文本即为地址
+    if node.link_type == Some(2) {
+        mark.text_mark_a_href = Some(if dest.contains(':') {
+            dest.clone()
+        } else if dest.contains('@') {
+            format!("mailto:{dest}")
+        } else {
+            format!("http://{dest}")
+        });
+        push_mark(mark, &dest, out);
+        return;
+    }
+    mark.text_mark_a_href = Some(dest);
+    let text = node
+        .children
+        .into_iter()
+        .skip_while(|child| child.node_type != NodeType::NodeOpenBracket)
+        .skip(1)
+        .take_while(|child| child.node_type != NodeType::NodeCloseBracket);
+    for child in text {
+        flatten(child, Some(&mark), out);
+    }
+}
+
+/// 块引用, 没有锚文本时为动态引用, 锚文本留空, 由 [`RefResolver`](crate::block_ref::RefResolver)
+/// 或内核根据被引用块填写
+fn block_ref(node: &Node, outer: Option<&Node>) -> Node {
+    let mut mark = with_type(outer, "block-ref");
+    let id = child_data(node, NodeType::NodeBlockRefId);
+    let (subtype, text) = match node.children.iter().find(|child| {
+        matches!(
+            child.node_type,
+            NodeType::NodeBlockRefText | NodeType::NodeBlockRefDynamicText
+        )
+    }) {
+        Some(text) if text.node_type == NodeType::NodeBlockRefText => {
+            ("s", text.data.clone().unwrap_or_default())
+        }
+        Some(text) => ("d", text.data.clone().unwrap_or_default()),
+        None => ("d", String::new()),
+    };
+    mark.text_mark_block_ref_id = Some(id);
+    mark.text_mark_block_ref_subtype = Some(subtype.to_string());
+    mark.text_mark_text_content = Some(text);
+    mark
+}
+
+/// 图片的替代文本合并为一个 `NodeLinkText`
+fn image(mut node: Node) -> Node {
+    let children = std::mem::take(&mut node.children);
+    let mut alt = String::new();
+    let mut in_text = false;
+    for child in children {
+        match child.node_type {
+            NodeType::NodeOpenBracket => {
+                in_text = true;
+                node.children.push(child);
+            }
+            NodeType::NodeCloseBracket => {
+                in_text = false;
+                let mut link_text = Node::new(NodeType::NodeLinkText);
+                link_text.data = Some(std::mem::take(&mut alt));
+                node.children.push(link_text);
+                node.children.push(child);
+            }
+            _ if in_text => {
+                let mut wrapper = Node::default();
+                wrapper.children.push(child);
+                plain_text(&wrapper, &mut alt);
+            }
+            _ => node.children.push(child),
+        }
+    }
+    node
+}
+
+fn push_text(text: &str, outer: Option<&Node>, out: &mut Vec<Node>) {
+    if text.is_empty() {
+        return;
+    }
+    match outer {
+        Some(outer) => push_mark(outer.clone(), text, out),
+        None => match out.last_mut() {
+            Some(last) if last.node_type == NodeType::NodeText => {
+                last.data.get_or_insert_with(String::new).push_str(text);
+            }
+            _ => {
+                let mut node = Node::new(NodeType::NodeText);
+                node.data = Some(text.to_string());
+                out.push(node);
+            }
+        },
+    }
+}
+
+/// 加入文本标记, 与前一个格式相同的标记合并
+fn push_mark(mut mark: Node, text: &str, out: &mut Vec<Node>) {
+    if let Some(last) = out.last_mut().filter(|last| mergeable(last, &mark)) {
+        last.text_mark_text_content
+            .get_or_insert_with(String::new)
+            .push_str(text);
+        return;
+    }
+    mark.text_mark_text_content = Some(text.to_string());
+    out.push(mark);
+}
+
+/// 只有纯格式与链接可以合并, 公式、代码、标签与引用各自独立
+fn mergeable(last: &Node, mark: &Node) -> bool {
+    last.node_type == NodeType::NodeTextMark
+        && last.text_mark_type == mark.text_mark_type
+        && last.text_mark_a_href == mark.text_mark_a_href
+        && last.text_mark_a_title == mark.text_mark_a_title
+        && last.properties == mark.properties
+        && !last.text_marks().is_atomic()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lute::node::is_node_id_pattern;
+    use crate::lute::render::{render_markdown, MarkdownOptions};
+
+    fn marks(node: &Node) -> Vec<(String, String)> {
+        node.children
+            .iter()
+            .map(|child| match child.node_type {
+                NodeType::NodeTextMark => (
+                    child.text_mark_type.clone().unwrap_or_default(),
+                    child.text_mark_text_content.clone().unwrap_or_default(),
+                ),
+                node_type => (
+                    node_type.name().to_string(),
+                    child.data.clone().unwrap_or_default(),
+                ),
+            })
+            .collect()
+    }
+
+    fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+        expected
+            .iter()
+            .map(|(a, b)| (a.to_string(), b.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_block_ids() {
+        let mut root = markdown_to_protyle("# T\n\n> q\n\n* a\n");
+        let mut ids = Vec::new();
+        walk_mut(&mut root, &mut |node, entering| {
+            if entering && node.node_type.is_block() {
+                let id = node.id.clone().unwrap();
+                let properties = node.properties.as_ref().unwrap();
+                assert!(is_node_id_pattern(&id));
+                assert_eq!(properties["id"], id);
+                assert_eq!(properties["updated"], id[..14]);
+                ids.push(id);
+            }
+            WalkStatus::Continue
+        });
+        // 文档, 标题, 引述, 段落, 列表, 列表项, 段落
+        assert_eq!(ids.len(), 7);
+        assert_eq!(root.spec.as_deref(), Some("1"));
+
+        // 已有的 ID 保留
+        let mut node = Node::new(NodeType::NodeParagraph);
+        node.id = Some("20240301110000-aaaaaaa".to_string());
+        to_protyle(&mut node);
+        assert_eq!(node.id.as_deref(), Some("20240301110000-aaaaaaa"));
+        assert!(node.properties.is_none());
+    }
+
+    #[test]
+    fn test_nested_marks() {
+        let root = markdown_to_protyle("a ***b** c* ~~d ==e==~~ `f` $g$ #h#\n");
+        let paragraph = &root.children[0];
+        assert_eq!(
+            marks(paragraph),
+            pairs(&[
+                ("NodeText", "a "),
+                ("em strong", "b"),
+                ("em", " c"),
+                ("NodeText", " "),
+                ("s", "d "),
+                ("s mark", "e"),
+                ("NodeText", " "),
+                ("code", "f"),
+                ("NodeText", " "),
+                ("inline-math", ""),
+                ("NodeText", " "),
+                ("tag", "h"),
+            ])
+        );
+        assert_eq!(
+            paragraph.children[9]
+                .text_mark_inline_math_content
+                .as_deref(),
+            Some("g")
+        );
+    }
+
+    #[test]
+    fn test_links_and_refs() {
+        let root = markdown_to_protyle(
+            "[x **y**](/u \"t\") www.a.com ((20240301110000-aaaaaaa 'r')) ((20240301110000-bbbbbbb \"s\"))\nnext\n",
+        );
+        let paragraph = &root.children[0];
+        assert_eq!(
+            marks(paragraph),
+            pairs(&[
+                ("a", "x "),
+                ("a strong", "y"),
+                ("NodeText", " "),
+                ("a", "www.a.com"),
+                ("NodeText", " "),
+                ("block-ref", "r"),
+                ("NodeText", " "),
+                ("block-ref", "s"),
+                ("NodeText", "\nnext"),
+            ])
+        );
+        let link = &paragraph.children[1];
+        assert_eq!(link.text_mark_a_href.as_deref(), Some("/u"));
+        assert_eq!(link.text_mark_a_title.as_deref(), Some("t"));
+        assert_eq!(
+            paragraph.children[3].text_mark_a_href.as_deref(),
+            Some("http://www.a.com")
+        );
+        assert_eq!(
+            paragraph.children[5].text_mark_block_ref_subtype.as_deref(),
+            Some("d")
+        );
+        assert_eq!(
+            paragraph.children[7].text_mark_block_ref_subtype.as_deref(),
+            Some("s")
+        );
+    }
+
+    #[test]
+    fn test_unresolved_ref() {
+        // 没有锚文本的引用留空, 等待解析, 输出时也不写锚文本
+        let root = markdown_to_protyle("see ((20240301110000-ccccccc))\n");
+        let paragraph = &root.children[0];
+        assert_eq!(
+            marks(paragraph),
+            pairs(&[("NodeText", "see "), ("block-ref", "")])
+        );
+        assert_eq!(paragraph.children[1].block_ref_subtype(), Some("d"));
+        assert_eq!(
+            render_markdown(&root, &MarkdownOptions::default()),
+            "see ((20240301110000-ccccccc))\n"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let markdown = "## 标题\n\n**粗体**与[链接](https://b3log.org)\n\n![图 *1*](a.png)\n\n| a | b |\n| --- | :-: |\n| `c` | d |\n";
+        let root = markdown_to_protyle(markdown);
+        assert_eq!(
+            render_markdown(&root, &MarkdownOptions::default()),
+            "## 标题\n\n**粗体**与[链接](https://b3log.org)\n\n![图 1](a.png)\n\n| a | b |\n| --- | :---: |\n| `c` | d |\n"
+        );
+    }
+
+    #[test]
+    fn test_kramdown_ial() {
+        let markdown = "3. {: id=\"20240301110002-ccccccc\" updated=\"20240301140000\"}third\n   \
+                        {: id=\"20240301110003-ddddddd\" updated=\"20240301140000\"}\n\
+                        {: id=\"20240301110001-bbbbbbb\" updated=\"20240301140000\"}\n\n\
+                        > **bold**{: style=\"color: red;\"} and `code`{: custom-a=\"&quot;x&quot;\"}\n\
+                        > {: id=\"20240301110008-iiiiiii\" updated=\"20240301140000\"}\n\
+                        {: id=\"20240301110007-hhhhhhh\" updated=\"20240301140000\"}\n\
+                        {: id=\"20240301110000-aaaaaaa\" title=\"Lists\" type=\"doc\" updated=\"20240301140000\"}\n";
+        let root = markdown_to_protyle(markdown);
+        assert_eq!(root.id.as_deref(), Some("20240301110000-aaaaaaa"));
+        assert_eq!(root.properties.as_ref().unwrap()["title"], "Lists");
+        let list = &root.children[0];
+        assert_eq!(list.id.as_deref(), Some("20240301110001-bbbbbbb"));
+        assert_eq!(
+            list.children[0].id.as_deref(),
+            Some("20240301110002-ccccccc")
+        );
+        let paragraph = &root.children[1].children[1];
+        assert_eq!(paragraph.id.as_deref(), Some("20240301110008-iiiiiii"));
+        assert_eq!(
+            paragraph.children[0].properties.as_ref().unwrap()["style"],
+            "color: red;"
+        );
+
+        let options = MarkdownOptions { keep_ial: true };
+        assert_eq!(render_markdown(&root, &options), markdown);
+
+        // 不能设置到前一个元素上的行级 IAL 保留为文本
+        let root = markdown_to_protyle("a{: style=\"x\"}\n");
+        assert_eq!(
+            marks(&root.children[0]),
+            pairs(&[("NodeText", "a{: style=\"x\"}")])
+        );
+    }
+}
@@ -0,0 +1,25 @@
+//! 将 [`Node`] 树渲染为其他格式, 均基于 [`walk`](super::walk) 的进入/离开回调.
+mod html;
+mod markdown;
+
+pub(crate) use html::escape_html;
+pub use html::{render_html, HtmlOptions};
+pub use markdown::{render_markdown, MarkdownOptions};
+
+use crate::lute::ial::{ial_text, properties_ial};
+use crate::lute::node::{Node, NodeType};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// 节点类型. 从 `.sy` 加载后未调用 `set_node_type_for_tree` 时按 `Type` 字段解析
+pub(crate) fn node_type(node: &Node) -> NodeType {
+    match node.node_type {
+        NodeType::Default => NodeType::from_str(&node.type_str).unwrap_or_default(),
+        node_type => node_type,
+    }
+}
+
+/// 块属性对应的 kramdown IAL, 例如 `{: id="..." updated="..."}`
+pub(crate) fn kramdown_ial(properties: &HashMap<String, String>) -> String {
+    ial_text(&properties_ial(properties))
+}
@@ -0,0 +1,611 @@
+//! 渲染为 HTML.
+//!
+//! 标准 Markdown 节点的输出与 commonmark.js 一致; 块节点有 ID 时输出 `id` 属性, 块引用渲染为指向
+//! 该属性的锚点. 公式保留 TeX 源码, 放在 `language-math` 元素中由页面自行渲染.
+use super::node_type;
+use crate::lute::node::{Node, NodeType, TextMarks};
+use crate::lute::walk::{walk, WalkStatus};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default)]
+pub struct HtmlOptions {
+    /// 块 ID 到所在页面的映射, 用于跨页面的块引用. 不在其中的块视为当前页面中的块
+    pub block_pages: HashMap<String, String>,
+    /// 当前页面, 引用同一页面中的块时只输出锚点
+    pub page: String,
+}
+
+/// 将 `node` 及其后代渲染为 HTML 片段
+pub fn render_html(node: &Node, options: &HtmlOptions) -> String {
+    let mut renderer = HtmlRenderer {
+        options,
+        out: String::new(),
+        tight: Vec::new(),
+        table_aligns: Vec::new(),
+        column: 0,
+        in_table_head: false,
+        in_table_body: false,
+    };
+    walk(node, &mut |node, entering| renderer.visit(node, entering));
+    renderer.out
+}
+
+/// 转义 HTML 特殊字符
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// 与 mdurl 的 `encode` 一致: 保留合法的 `%XX`, 其余不安全字符按 UTF-8 编码
+fn normalize_uri(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut out = String::new();
+    for (i, &b) in bytes.iter().enumerate() {
+        let escaped = b == b'%'
+            && bytes.get(i + 1).is_some_and(u8::is_ascii_hexdigit)
+            && bytes.get(i + 2).is_some_and(u8::is_ascii_hexdigit);
+        if b.is_ascii_alphanumeric() || b";/?:@&=+$,-_.!~*'()#".contains(&b) || escaped {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
+
+/// 自动链接的地址: 邮箱加 `mailto:`, `www.` 开头的加 `http://`
+fn auto_link_href(dest: &str) -> String {
+    if dest.contains(':') {
+        dest.to_string()
+    } else if dest.contains('@') {
+        format!("mailto:{dest}")
+    } else {
+        format!("http://{dest}")
+    }
+}
+
+fn data(node: &Node) -> &str {
+    node.data.as_deref().unwrap_or_default()
+}
+
+fn child_data(node: &Node, child_type: NodeType) -> &str {
+    node.children
+        .iter()
+        .find(|child| node_type(child) == child_type)
+        .map(data)
+        .unwrap_or_default()
+}
+
+/// 图片替代文本等只需要文字的场合
+fn plain_text(node: &Node, out: &mut String) {
+    for child in &node.children {
+        match node_type(child) {
+            NodeType::NodeText
+            | NodeType::NodeLinkText
+            | NodeType::NodeCodeSpanContent
+            | NodeType::NodeInlineMathContent
+            | NodeType::NodeHtmlEntity
+            | NodeType::NodeBackslashContent => out.push_str(&escape_html(data(child))),
+            NodeType::NodeTextMark => out.push_str(&escape_html(
+                child.text_mark_text_content.as_deref().unwrap_or_default(),
+            )),
+            NodeType::NodeSoftBreak | NodeType::NodeHardBreak => out.push('\n'),
+            NodeType::NodeLinkDest | NodeType::NodeLinkTitle => {}
+            _ => plain_text(child, out),
+        }
+    }
+}
+
+fn align_attr(align: i32) -> &'static str {
+    match align {
+        1 => " align=\"left\"",
+        2 => " align=\"center\"",
+        3 => " align=\"right\"",
+        _ => "",
+    }
+}
+
+struct HtmlRenderer<'o> {
+    options: &'o HtmlOptions,
+    out: String,
+    /// 段落所在的列表是否为紧凑列表, 紧凑列表中的段落不输出 `<p>`
+    tight: Vec<bool>,
+    table_aligns: Vec<i32>,
+    column: usize,
+    in_table_head: bool,
+    in_table_body: bool,
+}
+
+impl HtmlRenderer<'_> {
+    fn write(&mut self, text: &str) {
+        self.out.push_str(text);
+    }
+
+    /// 不在行首时换行
+    fn cr(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+    }
+
+    /// 独占一行的标签
+    fn line(&mut self, text: &str) {
+        self.cr();
+        self.write(text);
+        self.cr();
+    }
+
+    /// 块 ID 作为 `id` 属性
+    fn id_attr(node: &Node) -> String {
+        match &node.id {
+            Some(id) => format!(" id=\"{}\"", escape_html(id)),
+            None => String::new(),
+        }
+    }
+
+    /// 块引用的链接地址
+    fn ref_href(&self, id: &str) -> String {
+        match self.options.block_pages.get(id) {
+            Some(page) if *page != self.options.page => {
+                format!("{}#{}", escape_html(page), escape_html(id))
+            }
+            _ => format!("#{}", escape_html(id)),
+        }
+    }
+
+    fn block_ref(&self, id: &str, text: &str) -> String {
+        let text = if text.is_empty() { id } else { text };
+        format!(
+            "<a href=\"{}\" class=\"block-ref\">{}</a>",
+            self.ref_href(id),
+            escape_html(text)
+        )
+    }
+
+    fn visit(&mut self, node: &Node, entering: bool) -> WalkStatus {
+        let node_type = node_type(node);
+        // 以下节点在进入时整体输出
+        let whole = matches!(
+            node_type,
+            NodeType::NodeThematicBreak
+                | NodeType::NodeTaskListItemMarker
+                | NodeType::NodeText
+                | NodeType::NodeHtmlEntity
+                | NodeType::NodeBackslashContent
+                | NodeType::NodeInlineHtml
+                | NodeType::NodeEmojiUnicode
+                | NodeType::NodeSoftBreak
+                | NodeType::NodeHardBreak
+                | NodeType::NodeBr
+                | NodeType::NodeCodeBlock
+                | NodeType::NodeMathBlock
+                | NodeType::NodeCodeSpan
+                | NodeType::NodeInlineMath
+                | NodeType::NodeImage
+                | NodeType::NodeBlockRef
+                | NodeType::NodeTextMark
+        ) || node_type == NodeType::NodeLink && node.link_type == Some(2);
+        if whole && !entering {
+            return WalkStatus::Continue;
+        }
+        match node_type {
+            // 块
+            NodeType::NodeDocument | NodeType::NodeBlockquote | NodeType::NodeSuperBlock => {
+                if entering {
+                    self.tight.push(false);
+                } else {
+                    self.tight.pop();
+                }
+                match node_type {
+                    NodeType::NodeBlockquote if entering => {
+                        self.line(&format!("<blockquote{}>", Self::id_attr(node)))
+                    }
+                    NodeType::NodeBlockquote => self.line("</blockquote>"),
+                    NodeType::NodeSuperBlock if entering => {
+                        self.line(&format!("<div class=\"sb\"{}>", Self::id_attr(node)))
+                    }
+                    NodeType::NodeSuperBlock => self.line("</div>"),
+                    _ => {}
+                }
+            }
+            NodeType::NodeParagraph => {
+                if self.tight.last() == Some(&true) {
+                    return WalkStatus::Continue;
+                }
+                if entering {
+                    self.cr();
+                    self.write(&format!("<p{}>", Self::id_attr(node)));
+                } else {
+                    self.write("</p>");
+                    self.cr();
+                }
+            }
+            NodeType::NodeHeading => {
+                let level = node.heading_level.unwrap_or(1).clamp(1, 6);
+                if entering {
+                    self.cr();
+                    self.write(&format!("<h{level}{}>", Self::id_attr(node)));
+                } else {
+                    self.write(&format!("</h{level}>"));
+                    self.cr();
+                }
+            }
+            NodeType::NodeThematicBreak => {
+                self.line(&format!("<hr{} />", Self::id_attr(node)));
+            }
+            NodeType::NodeList => {
+                let list_data = node.list_data.clone().unwrap_or_default();
+                let tag = if list_data.typ == Some(1) { "ol" } else { "ul" };
+                if entering {
+                    let start = match list_data.start {
+                        Some(start) if tag == "ol" && start != 1 => format!(" start=\"{start}\""),
+                        _ => String::new(),
+                    };
+                    self.line(&format!("<{tag}{start}{}>", Self::id_attr(node)));
+                    self.tight.push(list_data.tight == Some(true));
+                } else {
+                    self.tight.pop();
+                    self.line(&format!("</{tag}>"));
+                }
+            }
+            NodeType::NodeListItem => {
+                if entering {
+                    self.write(&format!("<li{}>", Self::id_attr(node)));
+                } else {
+                    self.write("</li>");
+                    self.cr();
+                }
+            }
+            NodeType::NodeTaskListItemMarker => {
+                let checked = match node.task_list_item_checked {
+                    Some(true) => "checked=\"\" ",
+                    _ => "",
+                };
+                self.write(&format!(
+                    "<input {checked}disabled=\"\" type=\"checkbox\" /> "
+                ));
+            }
+            NodeType::NodeCodeBlock => {
+                // 思源的信息字符串保存在信息标记节点上
+                let info = node
+                    .code_block_info
+                    .as_deref()
+                    .or_else(|| {
+                        node.children
+                            .iter()
+                            .find_map(|child| child.code_block_info.as_deref())
+                    })
+                    .map(|info| String::from_utf8_lossy(info).into_owned())
+                    .unwrap_or_default();
+                let class = match info.split_whitespace().next() {
+                    Some(lang) => format!(" class=\"language-{}\"", escape_html(lang)),
+                    None => String::new(),
+                };
+                let code = child_data(node, NodeType::NodeCodeBlockCode);
+                self.cr();
+                self.write(&format!(
+                    "<pre{}><code{class}>{}</code></pre>",
+                    Self::id_attr(node),
+                    escape_html(code)
+                ));
+                self.cr();
+                return WalkStatus::SkipChildren;
+            }
+            NodeType::NodeMathBlock => {
+                let tex = child_data(node, NodeType::NodeMathBlockContent);
+                self.line(&format!(
+                    "<div class=\"language-math\"{}>{}</div>",
+                    Self::id_attr(node),
+                    escape_html(tex)
+                ));
+                return WalkStatus::SkipChildren;
+            }
+            NodeType::NodeHtmlBlock
+            | NodeType::NodeIFrame
+            | NodeType::NodeVideo
+            | NodeType::NodeAudio
+            | NodeType::NodeWidget => {
+                if entering {
+                    self.line(data(node));
+                }
+                return WalkStatus::SkipChildren;
+            }
+            NodeType::NodeTable => {
+                if entering {
+                    self.table_aligns = node.table_aligns.clone();
+                    self.in_table_body = false;
+                    self.line(&format!("<table{}>", Self::id_attr(node)));
+                } else {
+                    if self.in_table_body {
+                        self.line("</tbody>");
+                    }
+                    self.line("</table>");
+                }
+            }
+            NodeType::NodeTableHead => {
+                self.in_table_head = entering;
+                self.line(if entering { "<thead>" } else { "</thead>" });
+            }
+            NodeType::NodeTableRow => {
+                if entering {
+                    self.column = 0;
+                    if !self.in_table_head && !self.in_table_body {
+                        self.line("<tbody>");
+                        self.in_table_body = true;
+                    }
+                    self.line("<tr>");
+                } else {
+                    self.line("</tr>");
+                }
+            }
+            NodeType::NodeTableCell => {
+                let tag = if data(node) == "th" { "th" } else { "td" };
+                if entering {
+                    let align = node
+                        .table_cell_align
+                        .or_else(|| self.table_aligns.get(self.column).copied())
+                        .unwrap_or(0);
+                    self.write(&format!("<{tag}{}>", align_attr(align)));
+                } else {
+                    self.write(&format!("</{tag}>"));
+                    self.cr();
+                    self.column += 1;
+                }
+            }
+            NodeType::NodeYamlFrontMatter
+            | NodeType::NodeBlockQueryEmbed
+            | NodeType::NodeAttributeView
+            | NodeType::NodeKramdownBlockIal
+            | NodeType::NodeKramdownSpanIal => return WalkStatus::SkipChildren,
+
+            // 行级
+            NodeType::NodeText | NodeType::NodeHtmlEntity | NodeType::NodeBackslashContent => {
+                self.write(&escape_html(data(node)));
+            }
+            NodeType::NodeInlineHtml | NodeType::NodeEmojiUnicode => {
+                self.write(data(node));
+            }
+            NodeType::NodeSoftBreak => {
+                self.write("\n");
+            }
+            NodeType::NodeHardBreak => {
+                self.write("<br />\n");
+            }
+            NodeType::NodeBr => {
+                self.write("<br />");
+            }
+            NodeType::NodeEmphasis
+            | NodeType::NodeStrong
+            | NodeType::NodeStrikethrough
+            | NodeType::NodeMark
+            | NodeType::NodeSup
+            | NodeType::NodeSub
+            | NodeType::NodeKbd
+            | NodeType::NodeUnderline => {
+                let tag = match node_type {
+                    NodeType::NodeEmphasis => "em",
+                    NodeType::NodeStrong => "strong",
+                    NodeType::NodeStrikethrough => "del",
+                    NodeType::NodeMark => "mark",
+                    NodeType::NodeSup => "sup",
+                    NodeType::NodeSub => "sub",
+                    NodeType::NodeKbd => "kbd",
+                    _ => "u",
+                };
+                self.write(&if entering {
+                    format!("<{tag}>")
+                } else {
+                    format!("</{tag}>")
+                });
+            }
+            NodeType::NodeTag => {
+                self.write(if entering {
+                    "<span class=\"tag\">"
+                } else {
+                    "</span>"
+                });
+            }
+            NodeType::NodeCodeSpan => {
+                let code = child_data(node, NodeType::NodeCodeSpanContent);
+                self.write(&format!("<code>{}</code>", escape_html(code)));
+                return WalkStatus::SkipChildren;
+            }
+            NodeType::NodeInlineMath => {
+                let tex = child_data(node, NodeType::NodeInlineMathContent);
+                self.write(&format!(
+                    "<span class=\"language-math\">{}</span>",
+                    escape_html(tex)
+                ));
+                return WalkStatus::SkipChildren;
+            }
+            NodeType::NodeLink => {
+                let dest = child_data(node, NodeType::NodeLinkDest);
+                if node.link_type == Some(2) {
+                    let href = escape_html(&normalize_uri(&auto_link_href(dest)));
+                    self.write(&format!("<a href=\"{href}\">{}</a>", escape_html(dest)));
+                    return WalkStatus::SkipChildren;
+                }
+                if entering {
+                    let href = escape_html(&normalize_uri(dest));
+                    let title = child_data(node, NodeType::NodeLinkTitle);
+                    let title = match title {
+                        "" => String::new(),
+                        title => format!(" title=\"{}\"", escape_html(title)),
+                    };
+                    self.write(&format!("<a href=\"{href}\"{title}>"));
+                } else {
+                    self.write("</a>");
+                }
+            }
+            NodeType::NodeImage => {
+                let src = escape_html(&normalize_uri(child_data(node, NodeType::NodeLinkDest)));
+                let mut alt = String::new();
+                plain_text(node, &mut alt);
+                let title = match child_data(node, NodeType::NodeLinkTitle) {
+                    "" => String::new(),
+                    title => format!(" title=\"{}\"", escape_html(title)),
+                };
+                self.write(&format!("<img src=\"{src}\" alt=\"{alt}\"{title} />"));
+                return WalkStatus::SkipChildren;
+            }
+            NodeType::NodeBlockRef => {
+                let id = child_data(node, NodeType::NodeBlockRefId);
+                let text = node
+                    .children
+                    .iter()
+                    .find(|child| {
+                        matches!(
+                            super::node_type(child),
+                            NodeType::NodeBlockRefText | NodeType::NodeBlockRefDynamicText
+                        )
+                    })
+                    .map(data)
+                    .unwrap_or_default();
+                let html = self.block_ref(id, text);
+                self.write(&html);
+                return WalkStatus::SkipChildren;
+            }
+            NodeType::NodeTextMark => {
+                let html = self.text_mark(node);
+                self.write(&html);
+                return WalkStatus::SkipChildren;
+            }
+
+            // 其余为标识符节点, 以及链接目标、标题等已在父节点中输出的内容
+            _ => {}
+        }
+        WalkStatus::Continue
+    }
+
+    /// 思源的行级元素, `TextMarkType` 中靠前的类型在外层
+    fn text_mark(&self, node: &Node) -> String {
+        let marks = node.text_marks();
+        let text = node.text_mark_text_content.as_deref().unwrap_or_default();
+
+        let mut output = if marks.contains(TextMarks::INLINE_MATH) {
+            let tex = node.inline_math_content().unwrap_or_default();
+            format!("<span class=\"language-math\">{}</span>", escape_html(tex))
+        } else if marks.contains(TextMarks::CODE) {
+            format!("<code>{}</code>", escape_html(text))
+        } else if marks.contains(TextMarks::BLOCK_REF) {
+            let id = node.block_ref_id().unwrap_or_default();
+            self.block_ref(id, text)
+        } else if marks.contains(TextMarks::A) {
+            let href = node.a_href().unwrap_or_default();
+            let title = match node.a_title() {
+                None | Some("") => String::new(),
+                Some(title) => format!(" title=\"{}\"", escape_html(title)),
+            };
+            format!(
+                "<a href=\"{}\"{title}>{}</a>",
+                escape_html(&normalize_uri(href)),
+                escape_html(text)
+            )
+        } else {
+            escape_html(text)
+        };
+
+        for mark in node.text_mark_list().rev() {
+            let (open, close) = match mark {
+                TextMarks::STRONG
+                | TextMarks::EM
+                | TextMarks::MARK
+                | TextMarks::SUP
+                | TextMarks::SUB
+                | TextMarks::U
+                | TextMarks::KBD => (format!("<{mark}>"), format!("</{mark}>")),
+                TextMarks::S => ("<del>".to_string(), "</del>".to_string()),
+                TextMarks::TAG => ("<span class=\"tag\">".to_string(), "</span>".to_string()),
+                TextMarks::FILE_ANNOTATION_REF => (
+                    "<span class=\"file-annotation-ref\">".to_string(),
+                    "</span>".to_string(),
+                ),
+                _ => continue,
+            };
+            output = format!("{open}{output}{close}");
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lute::protyle::markdown_to_protyle;
+    use crate::mock::load_fixture;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_sy_blocks() {
+        assert_eq!(
+            render_html(&load_fixture("code_table.sy"), &HtmlOptions::default()),
+            "<h2 id=\"20240301100001-bbbbbbb\">Code</h2>\n\
+             <pre id=\"20240301100002-ccccccc\"><code class=\"language-rust\">fn main() {\n    println!(&quot;&lt;&amp;&gt;&quot;);\n}\n</code></pre>\n\
+             <table id=\"20240301100003-ddddddd\">\n<thead>\n<tr>\n<th>Name</th>\n<th align=\"center\">Count</th>\n</tr>\n</thead>\n\
+             <tbody>\n<tr>\n<td>苹果</td>\n<td align=\"center\">42</td>\n</tr>\n</tbody>\n</table>\n\
+             <div class=\"language-math\" id=\"20240301100004-eeeeeee\">E = mc^2</div>\n\
+             <hr id=\"20240301100005-fffffff\" />\n"
+        );
+    }
+
+    #[test]
+    fn test_render_text_marks() {
+        let mark = |types: &str, extra: serde_json::Value| {
+            let mut value =
+                json!({"Type": "NodeTextMark", "TextMarkType": types, "TextMarkTextContent": "x<"});
+            value
+                .as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            value
+        };
+        let paragraph: Node = serde_json::from_value(json!({
+            "Type": "NodeParagraph",
+            "Children": [
+                mark("strong em", json!({})),
+                mark("inline-math", json!({"TextMarkInlineMathContent": "a<b"})),
+                mark("s a", json!({"TextMarkAHref": "https://example.com/a b", "TextMarkATitle": "t"})),
+                mark("block-ref", json!({"TextMarkBlockRefID": "20240301110003-ddddddd"})),
+                mark("block-ref", json!({"TextMarkBlockRefID": "20240301110004-eeeeeee"})),
+                mark("tag code", json!({})),
+            ]
+        }))
+        .unwrap();
+        let options = HtmlOptions {
+            block_pages: HashMap::from([
+                ("20240301110003-ddddddd".to_string(), "a.html".to_string()),
+                ("20240301110004-eeeeeee".to_string(), "b.html".to_string()),
+            ]),
+            page: "a.html".to_string(),
+        };
+        assert_eq!(
+            render_html(&paragraph, &options),
+            "<p><strong><em>x&lt;</em></strong>\
+             <span class=\"language-math\">a&lt;b</span>\
+             <del><a href=\"https://example.com/a%20b\" title=\"t\">x&lt;</a></del>\
+             <a href=\"#20240301110003-ddddddd\" class=\"block-ref\">x&lt;</a>\
+             <a href=\"b.html#20240301110004-eeeeeee\" class=\"block-ref\">x&lt;</a>\
+             <span class=\"tag\"><code>x&lt;</code></span></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_render_markdown_extensions() {
+        let root = markdown_to_protyle(
+            "* [x] done\n\n{{{row\na\n\nb $c$\n}}}\n\n((20240301110000-aaaaaaa \"ref\")) ![i *m*](p.png \"t\")\n",
+        );
+        let html = render_html(&root, &HtmlOptions::default())
+            .split(" id=\"")
+            .map(|part| part.split_once("\"").map_or(part, |(_, rest)| rest))
+            .collect::<String>();
+        assert_eq!(
+            html,
+            "<ul>\n<li><input checked=\"\" disabled=\"\" type=\"checkbox\" /> done</li>\n</ul>\n\
+             <div class=\"sb\">\n<p>a</p>\n<p>b <span class=\"language-math\">c</span></p>\n</div>\n\
+             <p><a href=\"#20240301110000-aaaaaaa\" class=\"block-ref\">ref</a> \
+             <img src=\"p.png\" alt=\"i m\" title=\"t\" /></p>\n"
+        );
+    }
+}
@@ -0,0 +1,679 @@
+//! 渲染为思源风格的 Markdown.
+//!
+//! 容器块 (引述, 列表项, 脚注定义) 的内容先渲染到单独的缓冲区, 离开时再统一加上前缀或缩进.
+use super::{kramdown_ial, node_type};
+use crate::lute::node::{Node, NodeType, TextMarks};
+use crate::lute::walk::{walk, WalkStatus};
+
+#[derive(Debug, Clone, Default)]
+pub struct MarkdownOptions {
+    /// 在块后输出 `{: id="..."}` 形式的 kramdown IAL, 导入思源时可以保留块 ID 与属性
+    pub keep_ial: bool,
+}
+
+/// 将 `node` 及其后代渲染为 Markdown
+pub fn render_markdown(node: &Node, options: &MarkdownOptions) -> String {
+    let mut renderer = MarkdownRenderer {
+        options,
+        buffers: vec![String::new()],
+        tight: Vec::new(),
+        table_aligns: Vec::new(),
+        in_table_cell: false,
+        code_marker_len: 1,
+    };
+    walk(node, &mut |node, entering| renderer.visit(node, entering));
+    let mut output = renderer.buffers.swap_remove(0);
+    trim_newlines(&mut output);
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    output
+}
+
+struct MarkdownRenderer<'o> {
+    options: &'o MarkdownOptions,
+    /// 最后一个为当前输出的缓冲区
+    buffers: Vec<String>,
+    /// 外层列表是否为紧凑列表
+    tight: Vec<bool>,
+    table_aligns: Vec<i32>,
+    in_table_cell: bool,
+    code_marker_len: usize,
+}
+
+impl MarkdownRenderer<'_> {
+    fn visit(&mut self, node: &Node, entering: bool) -> WalkStatus {
+        let node_type = node_type(node);
+        match node_type {
+            NodeType::Default
+            | NodeType::NodeMaxVal
+            | NodeType::NodeHeadingC8hMarker
+            | NodeType::NodeBlockquoteMarker
+            | NodeType::NodeKramdownBlockIal
+            | NodeType::NodeVditorCaret
+            | NodeType::NodeEmojiImg => {}
+
+            // 块
+            NodeType::NodeDocument => {
+                if !entering {
+                    self.trim_newlines();
+                    self.write("\n");
+                    self.write_ial(node);
+                }
+            }
+            NodeType::NodeParagraph
+            | NodeType::NodeSuperBlock
+            | NodeType::NodeBlockQueryEmbed
+            | NodeType::NodeLinkRefDefBlock
+            | NodeType::NodeFootnotesDefBlock
+            | NodeType::NodeGitConflict
+            | NodeType::NodeYamlFrontMatter => {
+                if !entering {
+                    self.end_block(node);
+                }
+            }
+            NodeType::NodeHeading => {
+                if entering {
+                    let level = node.heading_level.unwrap_or(1).clamp(1, 6) as usize;
+                    self.write(&"#".repeat(level));
+                    self.write(" ");
+                } else {
+                    self.end_block(node);
+                }
+            }
+            NodeType::NodeHeadingId => {
+                self.write_entering(entering, &format!(" {{#{}}}", data(node)))
+            }
+            NodeType::NodeThematicBreak => {
+                if entering {
+                    self.write("---");
+                } else {
+                    self.end_block(node);
+                }
+            }
+            NodeType::NodeBlockquote => {
+                if entering {
+                    self.buffers.push(String::new());
+                } else {
+                    let content = self.pop_buffer();
+                    self.write(&prefix_lines(&content, "> ", "> "));
+                    self.end_block(node);
+                }
+            }
+            NodeType::NodeList => {
+                if entering {
+                    let tight = node.list_data.as_ref().and_then(|data| data.tight);
+                    self.tight.push(tight.unwrap_or(false));
+                } else {
+                    self.tight.pop();
+                    self.end_block(node);
+                }
+            }
+            NodeType::NodeListItem => {
+                if entering {
+                    self.buffers.push(String::new());
+                } else {
+                    let content = self.pop_buffer();
+                    let marker = list_marker(node);
+                    let indent = " ".repeat(marker.chars().count() + 1);
+                    let mut first = format!("{} ", marker);
+                    if let Some(props) = node.properties.as_ref().filter(|_| self.options.keep_ial)
+                    {
+                        first.push_str(&kramdown_ial(props));
+                    }
+                    self.write(&prefix_lines(&content, &first, &indent));
+                    let tight = self.tight.last().copied().unwrap_or(false);
+                    self.write(if tight { "\n" } else { "\n\n" });
+                }
+            }
+            NodeType::NodeTaskListItemMarker => {
+                let checked = node.task_list_item_checked.unwrap_or(false);
+                self.write_entering(entering, if checked { "[X] " } else { "[ ] " });
+            }
+            NodeType::NodeCodeBlock => {
+                if !entering {
+                    self.end_block(node);
+                    return WalkStatus::Continue;
+                }
+                self.write(&code_block(node));
+                return WalkStatus::SkipChildren;
+            }
+            NodeType::NodeMathBlock => {
+                if !entering {
+                    self.end_block(node);
+                    return WalkStatus::Continue;
+                }
+                let content = child_data(node, NodeType::NodeMathBlockContent);
+                let marker = NodeType::NodeMathBlockOpenMarker.marker();
+                self.write(&format!("{marker}\n{content}\n{marker}"));
+                return WalkStatus::SkipChildren;
+            }
+            NodeType::NodeHtmlBlock
+            | NodeType::NodeIFrame
+            | NodeType::NodeVideo
+            | NodeType::NodeAudio
+            | NodeType::NodeWidget => {
+                if entering {
+                    self.write(data(node));
+                } else {
+                    self.end_block(node);
+                }
+            }
+            NodeType::NodeAttributeView => {
+                if entering {
+                    self.write(&format!(
+                        "<div data-type=\"NodeAttributeView\" data-av-id=\"{}\" data-av-type=\"{}\"></div>",
+                        node.attribute_view_id.as_deref().unwrap_or_default(),
+                        node.attribute_view_type.as_deref().unwrap_or("table"),
+                    ));
+                } else {
+                    self.end_block(node);
+                }
+            }
+            NodeType::NodeCustomBlock => {
+                if entering {
+                    self.write(&format!(
+                        ";;;{}\n{}\n;;;",
+                        node.custom_block_info.as_deref().unwrap_or_default(),
+                        data(node)
+                    ));
+                } else {
+                    self.end_block(node);
+                }
+            }
+            NodeType::NodeToc => {
+                if entering {
+                    self.write("[toc]");
+                } else {
+                    self.end_block(node);
+                }
+            }
+            NodeType::NodeYamlFrontMatterContent => {
+                self.write_entering(entering, &format!("\n{}\n", data(node)))
+            }
+            NodeType::NodeSuperBlockLayoutMarker => {
+                let layout = node.data.as_deref().unwrap_or("row");
+                self.write_entering(entering, &format!("{}\n", layout));
+            }
+            NodeType::NodeSuperBlockCloseMarker => {
+                if entering {
+                    self.trim_newlines();
+                    self.write("\n");
+                    self.write(node_type.marker());
+                }
+            }
+            NodeType::NodeGitConflictOpenMarker | NodeType::NodeGitConflictContent => {
+                self.write_entering(entering, &format!("{}\n", data(node)))
+            }
+            NodeType::NodeLinkRefDef => {
+                if !entering {
+                    self.write("\n");
+                    return WalkStatus::Continue;
+                }
+                let label = bytes_text(node.link_ref_label.as_deref());
+                let dest = descendant_data(node, NodeType::NodeLinkDest);
+                self.write(&format!("[{}]: {}", label, dest));
+                return WalkStatus::SkipChildren;
+            }
+            NodeType::NodeFootnotesDef => {
+                if entering {
+                    self.buffers.push(String::new());
+                } else {
+                    let content = self.pop_buffer();
+                    let label = bytes_text(node.footnotes_ref_label.as_deref());
+                    self.write(&prefix_lines(&content, &format!("[^{}]: ", label), "    "));
+                    self.write("\n");
+                }
+            }
+
+            // 表格
+            NodeType::NodeTable => {
+                if entering {
+                    self.table_aligns = node.table_aligns.clone();
+                } else {
+                    self.end_block(node);
+                }
+            }
+            NodeType::NodeTableHead => {
+                if !entering {
+                    let delimiters: String = self
+                        .table_aligns
+                        .iter()
+                        .map(|align| match align {
+                            1 => "| :--- ",
+                            2 => "| :---: ",
+                            3 => "| ---: ",
+                            _ => "| --- ",
+                        })
+                        .collect();
+                    self.write(&delimiters);
+                    self.write("|\n");
+                }
+            }
+            NodeType::NodeTableRow => {
+                if !entering {
+                    self.write("|\n");
+                }
+            }
+            NodeType::NodeTableCell => {
+                self.in_table_cell = entering;
+                self.write(if entering { "| " } else { " " });
+            }
+
+            // 行级
+            NodeType::NodeText => {
+                if entering {
+                    let text = data(node);
+                    if self.in_table_cell {
+                        self.write(&text.replace('|', "\\|"));
+                    } else {
+                        self.write(text);
+                    }
+                }
+            }
+            NodeType::NodeTextMark => {
+                if entering {
+                    self.write(&text_mark(node));
+                    // 文本标记的属性输出为行级 IAL
+                    if let Some(props) = node.properties.as_ref().filter(|_| self.options.keep_ial)
+                    {
+                        self.write(&kramdown_ial(props));
+                    }
+                }
+                return WalkStatus::SkipChildren;
+            }
+            NodeType::NodeKramdownSpanIal => {
+                if self.options.keep_ial {
+                    self.write_entering(entering, data(node));
+                }
+            }
+            NodeType::NodeCodeSpan => {
+                self.code_marker_len = node.code_marker_len.unwrap_or(1).max(1) as usize;
+            }
+            NodeType::NodeCodeSpanOpenMarker | NodeType::NodeCodeSpanCloseMarker => {
+                self.write_entering(entering, &node_type.marker().repeat(self.code_marker_len))
+            }
+            NodeType::NodeHtmlEntity => {
+                let entity = match &node.html_entity_tokens {
+                    Some(tokens) => String::from_utf8_lossy(tokens).to_string(),
+                    None => data(node).to_string(),
+                };
+                self.write_entering(entering, &entity);
+            }
+            NodeType::NodeLinkSpace
+            | NodeType::NodeBlockRefSpace
+            | NodeType::NodeFileAnnotationRefSpace => self.write_entering(entering, " "),
+            NodeType::NodeLinkTitle
+            | NodeType::NodeBlockRefText
+            | NodeType::NodeFileAnnotationRefText => {
+                self.write_entering(entering, &format!("\"{}\"", data(node)))
+            }
+            NodeType::NodeBlockRefDynamicText => {
+                self.write_entering(entering, &format!("'{}'", data(node)))
+            }
+            NodeType::NodeFootnotesRef => {
+                if entering {
+                    let label = bytes_text(node.footnotes_ref_label.as_deref());
+                    self.write(&format!("[^{}]", label));
+                }
+                return WalkStatus::SkipChildren;
+            }
+            NodeType::NodeHardBreak => self.write_entering(entering, "\\\n"),
+            NodeType::NodeSoftBreak => self.write_entering(entering, "\n"),
+            NodeType::NodeBr => self.write_entering(entering, "<br />"),
+            NodeType::NodeEmojiUnicode => {
+                self.write_entering(entering, data(node));
+                return WalkStatus::SkipChildren;
+            }
+            NodeType::NodeCodeBlockCode
+            | NodeType::NodeCodeSpanContent
+            | NodeType::NodeMathBlockContent
+            | NodeType::NodeInlineMathContent
+            | NodeType::NodeInlineHtml
+            | NodeType::NodeHtmlTag
+            | NodeType::NodeHtmlTagOpen
+            | NodeType::NodeHtmlTagClose
+            | NodeType::NodeLinkText
+            | NodeType::NodeLinkDest
+            | NodeType::NodeBlockRefId
+            | NodeType::NodeFileAnnotationRefId
+            | NodeType::NodeEmojiAlias
+            | NodeType::NodeBackslashContent
+            | NodeType::NodeBlockQueryEmbedScript
+            | NodeType::NodeGitConflictCloseMarker => self.write_entering(entering, data(node)),
+
+            // 其余为标识符节点或只包含子节点的行级容器
+            _ => self.write_entering(entering, node_type.marker()),
+        }
+        WalkStatus::Continue
+    }
+
+    fn out(&mut self) -> &mut String {
+        self.buffers.last_mut().unwrap()
+    }
+
+    fn write(&mut self, text: &str) {
+        self.out().push_str(text);
+    }
+
+    fn write_entering(&mut self, entering: bool, text: &str) {
+        if entering {
+            self.write(text);
+        }
+    }
+
+    fn trim_newlines(&mut self) {
+        trim_newlines(self.out());
+    }
+
+    fn pop_buffer(&mut self) -> String {
+        let mut content = self.buffers.pop().unwrap();
+        trim_newlines(&mut content);
+        content
+    }
+
+    fn write_ial(&mut self, node: &Node) {
+        if let Some(props) = node.properties.as_ref().filter(|_| self.options.keep_ial) {
+            self.write(&kramdown_ial(props));
+            self.write("\n");
+        }
+    }
+
+    /// 结束一个块: 换行, 按需输出 IAL, 再空一行
+    fn end_block(&mut self, node: &Node) {
+        self.trim_newlines();
+        self.write("\n");
+        self.write_ial(node);
+        self.write("\n");
+    }
+}
+
+fn trim_newlines(text: &mut String) {
+    let len = text.trim_end_matches('\n').len();
+    text.truncate(len);
+}
+
+/// 首行加 `first` 前缀, 其余行加 `rest` 前缀, 空行只保留前缀的非空白部分
+fn prefix_lines(content: &str, first: &str, rest: &str) -> String {
+    content
+        .split('\n')
+        .enumerate()
+        .map(|(i, line)| {
+            let prefix = if i == 0 { first } else { rest };
+            if line.is_empty() {
+                prefix.trim_end().to_string()
+            } else {
+                format!("{}{}", prefix, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn data(node: &Node) -> &str {
+    node.data.as_deref().unwrap_or_default()
+}
+
+fn bytes_text(bytes: Option<&Vec<u8>>) -> String {
+    String::from_utf8_lossy(bytes.map(Vec::as_slice).unwrap_or_default()).to_string()
+}
+
+fn child_data(node: &Node, child_type: NodeType) -> &str {
+    node.children
+        .iter()
+        .find(|child| node_type(child) == child_type)
+        .map(data)
+        .unwrap_or_default()
+}
+
+fn descendant_data(node: &Node, target: NodeType) -> String {
+    let mut found = String::new();
+    walk(node, &mut |child, entering| {
+        if entering && node_type(child) == target {
+            found = data(child).to_string();
+            return WalkStatus::Stop;
+        }
+        WalkStatus::Continue
+    });
+    found
+}
+
+/// 列表项标记, 优先使用解析时记录的原始标记
+fn list_marker(node: &Node) -> String {
+    let Some(list_data) = &node.list_data else {
+        return "*".to_string();
+    };
+    if let Some(marker) = list_data
+        .marker
+        .as_ref()
+        .filter(|marker| !marker.is_empty())
+    {
+        return String::from_utf8_lossy(marker).to_string();
+    }
+    match list_data.typ {
+        Some(1) => format!(
+            "{}{}",
+            list_data.num.or(list_data.start).unwrap_or(1),
+            list_data.delimiter.map_or('.', char::from)
+        ),
+        _ => list_data.bullet_char.map_or('*', char::from).to_string(),
+    }
+}
+
+/// 围栏代码块, 围栏长度大于代码中最长的连续反引号
+fn code_block(node: &Node) -> String {
+    let info = node
+        .children
+        .iter()
+        .find(|child| node_type(child) == NodeType::NodeCodeBlockFenceInfoMarker)
+        .and_then(|child| child.code_block_info.as_deref())
+        .or(node.code_block_info.as_deref());
+    let code = child_data(node, NodeType::NodeCodeBlockCode);
+    let fence = "`".repeat(longest_backtick_run(code).max(2) + 1);
+    let newline = if code.is_empty() || code.ends_with('\n') {
+        ""
+    } else {
+        "\n"
+    };
+    format!("{fence}{}\n{code}{newline}{fence}", bytes_text(info).trim())
+}
+
+fn longest_backtick_run(text: &str) -> usize {
+    text.split(|c| c != '`').map(str::len).max().unwrap_or(0)
+}
+
+/// 行内代码, 内容包含反引号时使用更长的标记
+fn code_span(text: &str) -> String {
+    let marker = "`".repeat(longest_backtick_run(text) + 1);
+    let pad = if text.starts_with('`') || text.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+    format!("{marker}{pad}{text}{pad}{marker}")
+}
+
+/// 思源的行级元素. `TextMarkType` 可能包含多个以空格分隔的类型, 例如 `strong em`,
+/// 靠前的类型在外层
+fn text_mark(node: &Node) -> String {
+    let marks = node.text_marks();
+    let text = node.text_mark_text_content.as_deref().unwrap_or_default();
+
+    let mut output = if marks.contains(TextMarks::INLINE_MATH) {
+        let marker = NodeType::NodeInlineMathOpenMarker.marker();
+        let content = node.inline_math_content().unwrap_or_default();
+        format!("{marker}{content}{marker}")
+    } else if marks.contains(TextMarks::CODE) {
+        code_span(text)
+    } else if marks.contains(TextMarks::BLOCK_REF) {
+        let id = node.block_ref_id().unwrap_or_default();
+        // 动态锚文本使用单引号, 尚未解析的动态引用不写锚文本
+        if text.is_empty() {
+            format!("(({}))", id)
+        } else if node.block_ref_subtype() == Some("d") {
+            format!("(({} '{}'))", id, text.replace('\'', "&apos;"))
+        } else {
+            format!("(({} \"{}\"))", id, text.replace('"', "&quot;"))
+        }
+    } else if marks.contains(TextMarks::FILE_ANNOTATION_REF) {
+        let id = node.file_annotation_ref_id().unwrap_or_default();
+        format!("<<{} \"{}\">>", id, text.replace('"', "&quot;"))
+    } else if marks.contains(TextMarks::A) {
+        let href = node.a_href().unwrap_or_default();
+        match node.a_title().filter(|t| !t.is_empty()) {
+            Some(title) => format!("[{}]({} \"{}\")", text, href, title.replace('"', "&quot;")),
+            None => format!("[{}]({})", text, href),
+        }
+    } else {
+        text.to_string()
+    };
+
+    for mark in node.text_mark_list().rev() {
+        let (open, close) = match mark {
+            TextMarks::STRONG => (
+                NodeType::NodeStrongA6kOpenMarker,
+                NodeType::NodeStrongA6kCloseMarker,
+            ),
+            TextMarks::EM => (
+                NodeType::NodeEmA6kOpenMarker,
+                NodeType::NodeEmA6kCloseMarker,
+            ),
+            TextMarks::S => (
+                NodeType::NodeStrikethrough2OpenMarker,
+                NodeType::NodeStrikethrough2CloseMarker,
+            ),
+            TextMarks::MARK => (
+                NodeType::NodeMark2OpenMarker,
+                NodeType::NodeMark2CloseMarker,
+            ),
+            TextMarks::SUP => (NodeType::NodeSupOpenMarker, NodeType::NodeSupCloseMarker),
+            TextMarks::SUB => (NodeType::NodeSubOpenMarker, NodeType::NodeSubCloseMarker),
+            TextMarks::U => (
+                NodeType::NodeUnderlineOpenMarker,
+                NodeType::NodeUnderlineCloseMarker,
+            ),
+            TextMarks::KBD => (NodeType::NodeKbdOpenMarker, NodeType::NodeKbdCloseMarker),
+            TextMarks::TAG => (NodeType::NodeTagOpenMarker, NodeType::NodeTagCloseMarker),
+            _ => continue,
+        };
+        output = format!("{}{}{}", open.marker(), output, close.marker());
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::load_fixture;
+    use serde_json::json;
+
+    fn render(node: &Node) -> String {
+        render_markdown(node, &MarkdownOptions::default())
+    }
+
+    #[test]
+    fn test_render_blocks() {
+        assert_eq!(
+            render(&load_fixture("code_table.sy")),
+            "## Code\n\n```rust\nfn main() {\n    println!(\"<&>\");\n}\n```\n\n\
+             | Name | Count |\n| --- | :---: |\n| 苹果 | 42 |\n\n$$\nE = mc^2\n$$\n\n---\n"
+        );
+        assert_eq!(
+            render(&load_fixture("lists.sy")),
+            "3. third\n\n* [X] done\n\n> quoted\n"
+        );
+    }
+
+    #[test]
+    fn test_render_text_marks() {
+        assert_eq!(
+            render(&load_fixture("marks.sy")),
+            "[link](https://example.com/?a=1&b=2 \"Example\") and ((20240301110003-ddddddd \"third\")), \
+             $x^2$, **bold**\n\n{{{col\nleft\n\nright\n}}}\n\n<div>\u{2028}</div>\n\n\
+             <div data-type=\"NodeAttributeView\" data-av-id=\"20240301120007-hhhhhhh\" data-av-type=\"table\"></div>\n\n\
+             <iframe src=\"https://example.com\"></iframe>\n"
+        );
+
+        let mark = |types: &str, extra: serde_json::Value| -> String {
+            let mut value =
+                json!({"Type": "NodeTextMark", "TextMarkType": types, "TextMarkTextContent": "x"});
+            value
+                .as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            render(&serde_json::from_value(value).unwrap())
+        };
+        assert_eq!(mark("strong em", json!({})), "***x***\n");
+        assert_eq!(mark("tag", json!({})), "#x#\n");
+        assert_eq!(
+            mark("code", json!({"TextMarkTextContent": "a`b"})),
+            "``a`b``\n"
+        );
+        assert_eq!(
+            mark(
+                "block-ref",
+                json!({"TextMarkBlockRefID": "20240301110003-ddddddd", "TextMarkBlockRefSubtype": "d"})
+            ),
+            "((20240301110003-ddddddd 'x'))\n"
+        );
+        assert_eq!(
+            mark("s a", json!({"TextMarkAHref": "https://example.com"})),
+            "~~[x](https://example.com)~~\n"
+        );
+    }
+
+    #[test]
+    fn test_render_commonmark_nodes() {
+        let node: Node = serde_json::from_value(json!({
+            "Type": "NodeParagraph",
+            "Children": [
+                {"Type": "NodeEmphasis", "Children": [
+                    {"Type": "NodeEmA6kOpenMarker"}, {"Type": "NodeText", "Data": "em"}, {"Type": "NodeEmA6kCloseMarker"}
+                ]},
+                {"Type": "NodeText", "Data": " "},
+                {"Type": "NodeCodeSpan", "CodeMarkerLen": 2, "Children": [
+                    {"Type": "NodeCodeSpanOpenMarker"}, {"Type": "NodeCodeSpanContent", "Data": "a`b"}, {"Type": "NodeCodeSpanCloseMarker"}
+                ]},
+                {"Type": "NodeSoftBreak"},
+                {"Type": "NodeImage", "Children": [
+                    {"Type": "NodeBang"}, {"Type": "NodeOpenBracket"}, {"Type": "NodeLinkText", "Data": "alt"},
+                    {"Type": "NodeCloseBracket"}, {"Type": "NodeOpenParen"}, {"Type": "NodeLinkDest", "Data": "a.png"},
+                    {"Type": "NodeLinkSpace"}, {"Type": "NodeLinkTitle", "Data": "t"}, {"Type": "NodeCloseParen"}
+                ]},
+                {"Type": "NodeBlockRef", "Children": [
+                    {"Type": "NodeOpenParen"}, {"Type": "NodeOpenParen"}, {"Type": "NodeBlockRefID", "Data": "20240301110003-ddddddd"},
+                    {"Type": "NodeBlockRefSpace"}, {"Type": "NodeBlockRefDynamicText", "Data": "third"},
+                    {"Type": "NodeCloseParen"}, {"Type": "NodeCloseParen"}
+                ]},
+                {"Type": "NodeHardBreak"},
+                {"Type": "NodeTag", "Children": [
+                    {"Type": "NodeTagOpenMarker"}, {"Type": "NodeText", "Data": "todo"}, {"Type": "NodeTagCloseMarker"}
+                ]}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(
+            render(&node),
+            "*em* ``a`b``\n![alt](a.png \"t\")((20240301110003-ddddddd 'third'))\\\n#todo#\n"
+        );
+    }
+
+    #[test]
+    fn test_keep_ial() {
+        let options = MarkdownOptions { keep_ial: true };
+        assert_eq!(
+            render_markdown(&load_fixture("lists.sy"), &options),
+            "3. {: id=\"20240301110002-ccccccc\" updated=\"20240301140000\"}third\n   \
+             {: id=\"20240301110003-ddddddd\" updated=\"20240301140000\"}\n\
+             {: id=\"20240301110001-bbbbbbb\" updated=\"20240301140000\"}\n\n\
+             * {: id=\"20240301110005-fffffff\" updated=\"20240301140000\"}[X] done\n  \
+             {: id=\"20240301110006-ggggggg\" updated=\"20240301140000\"}\n\
+             {: id=\"20240301110004-eeeeeee\" updated=\"20240301140000\"}\n\n\
+             > quoted\n> {: id=\"20240301110008-iiiiiii\" updated=\"20240301140000\"}\n\
+             {: id=\"20240301110007-hhhhhhh\" updated=\"20240301140000\"}\n\
+             {: id=\"20240301110000-aaaaaaa\" title=\"Lists\" type=\"doc\" updated=\"20240301140000\"}\n"
+        );
+        let marks = render_markdown(&load_fixture("marks.sy"), &options);
+        assert!(marks.contains("**bold**{: style=\"color: red;\"}"));
+    }
+}
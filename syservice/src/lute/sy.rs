@@ -0,0 +1,241 @@
+//! `.sy` 文件的序列化, 输出与内核写入的文件逐字节一致.
+//!
+//! 内核先用 Go 的 `encoding/json` 生成 JSON, 再以 tab 缩进, 因此:
+//! 字段按结构体中的声明顺序输出, map 按 key 排序, `[]byte` 编码为 base64,
+//! 字符串中的 `<`, `>`, `&`, U+2028 与 U+2029 转义为 `\uXXXX`.
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::ser::{Formatter, PrettyFormatter};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+
+/// 按 `.sy` 格式序列化, 末尾带换行
+pub fn to_sy_vec<T: Serialize + ?Sized>(value: &T) -> serde_json::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    let formatter = GoFormatter {
+        inner: PrettyFormatter::with_indent(b"\t"),
+    };
+    let mut serializer = serde_json::Serializer::with_formatter(&mut buf, formatter);
+    value.serialize(&mut serializer)?;
+    buf.push(b'\n');
+    Ok(buf)
+}
+
+/// 在 [`PrettyFormatter`] 的基础上按 Go 的规则转义字符串
+struct GoFormatter<'a> {
+    inner: PrettyFormatter<'a>,
+}
+
+impl Formatter for GoFormatter<'_> {
+    fn begin_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.begin_array(writer)
+    }
+
+    fn end_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.end_array(writer)
+    }
+
+    fn begin_array_value<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.inner.begin_array_value(writer, first)
+    }
+
+    fn end_array_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.end_array_value(writer)
+    }
+
+    fn begin_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.begin_object(writer)
+    }
+
+    fn end_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.end_object(writer)
+    }
+
+    fn begin_object_key<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.inner.begin_object_key(writer, first)
+    }
+
+    fn begin_object_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.begin_object_value(writer)
+    }
+
+    fn end_object_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.end_object_value(writer)
+    }
+
+    fn write_string_fragment<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        fragment: &str,
+    ) -> io::Result<()> {
+        let mut start = 0;
+        for (i, c) in fragment.char_indices() {
+            let escaped = match c {
+                '<' => "\\u003c",
+                '>' => "\\u003e",
+                '&' => "\\u0026",
+                '\u{2028}' => "\\u2028",
+                '\u{2029}' => "\\u2029",
+                _ => continue,
+            };
+            writer.write_all(&fragment.as_bytes()[start..i])?;
+            writer.write_all(escaped.as_bytes())?;
+            start = i + c.len_utf8();
+        }
+        writer.write_all(&fragment.as_bytes()[start..])
+    }
+}
+
+/// `Option<ByteBuf>` 与 Go `[]byte` 的 base64 字符串互转
+pub(crate) mod base64_bytes {
+    use super::*;
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use serde_bytes::ByteBuf;
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<ByteBuf>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(bytes) => serializer.serialize_str(&STANDARD.encode(bytes)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<ByteBuf>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|text| {
+                STANDARD
+                    .decode(text)
+                    .map(ByteBuf::from)
+                    .map_err(D::Error::custom)
+            })
+            .transpose()
+    }
+}
+
+/// 按 key 排序输出 `Properties`
+pub(crate) fn sorted_properties<S: Serializer>(
+    properties: &Option<HashMap<String, String>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match properties {
+        Some(properties) => {
+            serializer.serialize_some(&properties.iter().collect::<BTreeMap<_, _>>())
+        }
+        None => serializer.serialize_none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lute::node::Node;
+    use crate::mock::fixture_data_dir;
+    use serde_json::Value;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn sy_files(dir: &Path, files: &mut Vec<PathBuf>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                sy_files(&path, files);
+            } else if path.extension().is_some_and(|ext| ext == "sy") {
+                files.push(path);
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip_is_byte_identical() {
+        let mut files =
+            vec![Path::new(env!("CARGO_MANIFEST_DIR")).join("../tui/tests/resources/template.sy")];
+        sy_files(&fixture_data_dir(), &mut files);
+        for file in files {
+            let original = fs::read(&file).unwrap();
+            let node: Node = serde_json::from_slice(&original).unwrap();
+            let output = to_sy_vec(&node).unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                String::from_utf8(original).unwrap(),
+                "{}",
+                file.display()
+            );
+        }
+    }
+
+    #[test]
+    fn test_fidelity() {
+        let mut files = Vec::new();
+        sy_files(
+            &Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/resources/fidelity"),
+            &mut files,
+        );
+        assert!(!files.is_empty());
+        for file in files {
+            let original: Value = serde_json::from_slice(&fs::read(&file).unwrap()).unwrap();
+            let mut node: Node = serde_json::from_value(original.clone()).unwrap();
+            node.set_node_type_for_tree();
+            let saved: Value = serde_json::from_slice(&to_sy_vec(&node).unwrap()).unwrap();
+            assert_eq!(saved, original, "{}", file.display());
+        }
+    }
+
+    #[test]
+    fn test_unknown_fields_are_kept() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/resources/fidelity");
+        let node: Node =
+            serde_json::from_slice(&fs::read(path.join("future.sy")).unwrap()).unwrap();
+        assert_eq!(node.extra["DocumentSchema"], 2);
+        let paragraph = &node.children[0];
+        assert_eq!(paragraph.extra["FutureOptions"]["tags"][1], "b");
+        assert!(paragraph.properties.is_some());
+        let list_data = node.children[1].list_data.as_ref().unwrap();
+        assert_eq!(list_data.bullet_char, Some(b'-'));
+        assert_eq!(list_data.extra["Indent"], 4);
+
+        let node: Node = serde_json::from_slice(&fs::read(path.join("marks.sy")).unwrap()).unwrap();
+        let av = &node.children[3];
+        assert_eq!(
+            av.attribute_view_id.as_deref(),
+            Some("20240301120007-hhhhhhh")
+        );
+        assert!(av.extra.is_empty());
+    }
+
+    #[test]
+    fn test_go_escaping() {
+        let value = serde_json::json!({"b": "<a href=\"x\">&\u{2028}\t", "a": []});
+        let output = String::from_utf8(to_sy_vec(&value).unwrap()).unwrap();
+        assert_eq!(
+            output,
+            "{\n\t\"a\": [],\n\t\"b\": \"\\u003ca href=\\\"x\\\"\\u003e\\u0026\\u2028\\t\"\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_base64_bytes() {
+        let node: Node = serde_json::from_str(
+            r#"{"Type": "NodeCodeBlockFenceInfoMarker", "CodeBlockInfo": "cnVzdA=="}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            node.code_block_info.as_deref().map(Vec::as_slice),
+            Some(&b"rust"[..])
+        );
+        let output = String::from_utf8(to_sy_vec(&node).unwrap()).unwrap();
+        assert!(output.contains("\"CodeBlockInfo\": \"cnVzdA==\""));
+    }
+}
@@ -0,0 +1,121 @@
+//! 参考 Lute `ast.Walk` 的深度优先遍历.
+//!
+//! 每个节点会被访问两次: 进入时 `entering` 为 `true`, 子节点遍历完成后离开时为 `false`.
+//! 访问函数通过返回的 [`WalkStatus`] 控制遍历.
+use crate::lute::node::Node;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalkStatus {
+    /// 继续遍历
+    Continue,
+    /// 进入时返回, 不遍历子节点, 但仍会离开该节点
+    SkipChildren,
+    /// 立即终止遍历
+    Stop,
+}
+
+/// 遍历 `node` 及其后代, 返回 [`WalkStatus::Stop`] 表示遍历被终止
+pub fn walk<'a, F>(node: &'a Node, visitor: &mut F) -> WalkStatus
+where
+    F: FnMut(&'a Node, bool) -> WalkStatus,
+{
+    let status = visitor(node, true);
+    if status == WalkStatus::Stop {
+        return status;
+    }
+    if status != WalkStatus::SkipChildren {
+        for child in &node.children {
+            if walk(child, visitor) == WalkStatus::Stop {
+                return WalkStatus::Stop;
+            }
+        }
+    }
+    visitor(node, false)
+}
+
+/// 可修改节点的 [`walk`], 进入时修改的 `children` 会在随后被遍历
+pub fn walk_mut<F>(node: &mut Node, visitor: &mut F) -> WalkStatus
+where
+    F: FnMut(&mut Node, bool) -> WalkStatus,
+{
+    let status = visitor(node, true);
+    if status == WalkStatus::Stop {
+        return status;
+    }
+    if status != WalkStatus::SkipChildren {
+        for child in &mut node.children {
+            if walk_mut(child, visitor) == WalkStatus::Stop {
+                return WalkStatus::Stop;
+            }
+        }
+    }
+    visitor(node, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn tree() -> Node {
+        serde_json::from_value(json!({
+            "ID": "doc",
+            "Type": "NodeDocument",
+            "Children": [
+                {"ID": "p1", "Type": "NodeParagraph", "Children": [{"Type": "NodeText", "Data": "a"}]},
+                {"ID": "p2", "Type": "NodeParagraph", "Children": [{"Type": "NodeText", "Data": "b"}]},
+                {"ID": "p3", "Type": "NodeParagraph"}
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn trace(node: &Node, skip: &str, stop: &str) -> Vec<String> {
+        let mut events = Vec::new();
+        walk(node, &mut |n, entering| {
+            let name = n.id.clone().or_else(|| n.data.clone()).unwrap_or_default();
+            events.push(format!("{}{}", if entering { "+" } else { "-" }, name));
+            if name == stop {
+                WalkStatus::Stop
+            } else if entering && name == skip {
+                WalkStatus::SkipChildren
+            } else {
+                WalkStatus::Continue
+            }
+        });
+        events
+    }
+
+    #[test]
+    fn test_walk_order() {
+        assert_eq!(
+            trace(&tree(), "", ""),
+            ["+doc", "+p1", "+a", "-a", "-p1", "+p2", "+b", "-b", "-p2", "+p3", "-p3", "-doc"]
+        );
+    }
+
+    #[test]
+    fn test_walk_control() {
+        assert_eq!(
+            trace(&tree(), "p1", "b"),
+            ["+doc", "+p1", "-p1", "+p2", "+b"]
+        );
+    }
+
+    #[test]
+    fn test_walk_mut() {
+        let mut node = tree();
+        let status = walk_mut(&mut node, &mut |n, entering| {
+            if entering && n.id.as_deref() == Some("p3") {
+                return WalkStatus::Stop;
+            }
+            if let Some(data) = n.data.as_mut().filter(|_| entering) {
+                data.make_ascii_uppercase();
+            }
+            WalkStatus::Continue
+        });
+        assert_eq!(status, WalkStatus::Stop);
+        assert_eq!(node.children[0].children[0].data.as_deref(), Some("A"));
+        assert_eq!(node.children[1].children[0].data.as_deref(), Some("B"));
+    }
+}
@@ -0,0 +1,504 @@
+//! 本地内核替身, 用于测试与离线开发.
+//!
+//! 从工作空间的 data 目录加载笔记本与 `.sy` 文档, 建立内存中的 SQLite 索引,
+//! 并以与内核相同的格式响应 SQL, 文件树, 块, 属性, 笔记本与全文搜索接口.
+//! 块内容只支持 Markdown 段落与标题, 修改只保存在内存中.
+//!
+//! ```no_run
+//! # async fn run() -> anyhow::Result<()> {
+//! use syservice::mock::{fixture_data_dir, MockKernel};
+//!
+//! let kernel = MockKernel::start(fixture_data_dir()).await?;
+//! let client = kernel.client();
+//! # Ok(())
+//! # }
+//! ```
+pub(crate) mod http;
+mod workspace;
+
+use crate::attr::{API_GET_BLOCK_ATTRS, API_SET_BLOCK_ATTRS};
+use crate::backend::kernel::API_SYSTEM_VERSION;
+use crate::block::*;
+use crate::client::SiyuanClient;
+use crate::config::Profile;
+use crate::domain::*;
+use crate::file::API_GET_FILE;
+use crate::filetree::*;
+use crate::notebook::*;
+use crate::search::{highlight, API_FULL_TEXT_SEARCH_BLOCK};
+use crate::API_SQL_QUERY;
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use workspace::{Position, Workspace};
+
+/// 内核替身使用的 profile 名称
+pub const MOCK_PROFILE: &str = "mock";
+
+/// 仓库中自带的测试工作空间
+pub fn fixture_data_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/resources/workspace/data")
+}
+
+/// 读取 `tests/resources/fidelity` 下的 `.sy` 样例
+#[cfg(test)]
+pub(crate) fn load_fixture(name: &str) -> crate::lute::node::Node {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/resources/fidelity")
+        .join(name);
+    serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap()
+}
+
+pub struct MockKernel {
+    base_url: String,
+    data_dir: PathBuf,
+}
+
+impl MockKernel {
+    /// 在随机端口上启动
+    pub async fn start(data_dir: impl Into<PathBuf>) -> Result<MockKernel> {
+        Self::bind("127.0.0.1:0", data_dir).await
+    }
+
+    pub async fn bind(addr: &str, data_dir: impl Into<PathBuf>) -> Result<MockKernel> {
+        let data_dir = data_dir.into();
+        let workspace = Arc::new(Mutex::new(Workspace::load(&data_dir)?));
+        let listener = TcpListener::bind(addr).await?;
+        let base_url = format!("http://{}", listener.local_addr()?);
+        http::serve(
+            listener,
+            Arc::new(move |path, body| {
+                let mut workspace = workspace.lock().unwrap();
+                if path == API_GET_FILE {
+                    return Some(get_file(&workspace, body));
+                }
+                let result = route(&mut workspace, path, body)?;
+                Some(match result {
+                    Ok(data) => json!({"code": 0, "msg": "", "data": data}),
+                    Err(e) => json!({"code": -1, "msg": e.to_string(), "data": null}),
+                })
+            }),
+        );
+        Ok(MockKernel { base_url, data_dir })
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn profile(&self) -> Profile {
+        Profile {
+            name: MOCK_PROFILE.to_string(),
+            base_url: self.base_url.clone(),
+            data_dir: self.data_dir.clone(),
+            ..Profile::default()
+        }
+    }
+
+    pub fn client(&self) -> SiyuanClient {
+        SiyuanClient::new(self.profile()).expect("mock kernel client")
+    }
+}
+
+#[derive(Deserialize)]
+struct FilePath {
+    path: String,
+}
+
+#[derive(Deserialize)]
+struct Stmt {
+    stmt: String,
+}
+
+#[derive(Deserialize)]
+struct NotebookId {
+    notebook: String,
+}
+
+#[derive(Deserialize)]
+struct RenameNotebook {
+    notebook: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct SetNotebookConf {
+    notebook: String,
+    conf: NotebookConf,
+}
+
+#[derive(Deserialize)]
+struct SetBlockAttrs {
+    id: String,
+    attrs: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct SearchRequest {
+    query: String,
+    #[serde(default)]
+    page: u32,
+    #[serde(rename = "pageSize", default)]
+    page_size: u32,
+}
+
+fn parse<T: DeserializeOwned>(body: Value) -> Result<T> {
+    Ok(serde_json::from_value(body)?)
+}
+
+fn markdown(data: &BlockData) -> Result<&str> {
+    match data {
+        BlockData::Markdown(markdown) => Ok(markdown),
+        BlockData::Dom(_) => Err(anyhow!("mock kernel only supports markdown data")),
+    }
+}
+
+/// 块操作的返回值: 一个只包含 `doOperations` 的事务
+fn transactions(action: &str, operations: Vec<Value>) -> Value {
+    let operations: Vec<Value> = operations
+        .into_iter()
+        .map(|mut op| {
+            op["action"] = json!(action);
+            op
+        })
+        .collect();
+    json!([{"doOperations": operations, "undoOperations": null}])
+}
+
+/// `/api/file/getFile` 成功时直接返回文件内容, 只支持 data 目录下的文档
+fn get_file(ws: &Workspace, body: Value) -> Value {
+    let result = parse::<FilePath>(body).and_then(|req| {
+        let (box_id, path) = req
+            .path
+            .strip_prefix("/data/")
+            .and_then(|path| path.split_once('/'))
+            .ok_or_else(|| anyhow!("file [{}] not found", req.path))?;
+        let doc = ws.doc_by_path(box_id, &format!("/{}", path))?;
+        Ok(serde_json::to_value(&doc.node)?)
+    });
+    result.unwrap_or_else(|e| json!({"code": 404, "msg": e.to_string(), "data": null}))
+}
+
+/// 未知接口返回 `None`
+fn route(ws: &mut Workspace, path: &str, body: Value) -> Option<Result<Value>> {
+    let result = match path {
+        API_SYSTEM_VERSION => Ok(json!(env!("CARGO_PKG_VERSION"))),
+        API_SQL_QUERY => parse::<Stmt>(body).and_then(|req| Ok(json!(ws.query(&req.stmt)?))),
+
+        API_LS_NOTEBOOKS => {
+            let notebooks: Vec<Notebook> = ws.notebooks.iter().map(|n| n.to_notebook()).collect();
+            Ok(json!({ "notebooks": notebooks }))
+        }
+        API_OPEN_NOTEBOOK | API_CLOSE_NOTEBOOK => parse::<NotebookId>(body).and_then(|req| {
+            ws.notebook_mut(&req.notebook)?.conf.closed = path == API_CLOSE_NOTEBOOK;
+            Ok(Value::Null)
+        }),
+        API_RENAME_NOTEBOOK => parse::<RenameNotebook>(body).and_then(|req| {
+            ws.notebook_mut(&req.notebook)?.conf.name = req.name;
+            Ok(Value::Null)
+        }),
+        API_GET_NOTEBOOK_CONF => parse::<NotebookId>(body).and_then(|req| {
+            let notebook = ws.notebook_mut(&req.notebook)?;
+            Ok(json!({"box": notebook.id, "name": notebook.conf.name, "conf": notebook.conf}))
+        }),
+        API_SET_NOTEBOOK_CONF => parse::<SetNotebookConf>(body).and_then(|req| {
+            let notebook = ws.notebook_mut(&req.notebook)?;
+            notebook.conf = req.conf;
+            Ok(json!(notebook.conf))
+        }),
+
+        API_CREATE_DOC_WITH_MD => parse::<CreateDocWithMd>(body).and_then(|req| {
+            Ok(json!(ws.create_doc_with_md(
+                &req.notebook,
+                &req.path,
+                &req.markdown
+            )?))
+        }),
+        API_RENAME_DOC => parse::<RenameDoc>(body).and_then(|req| {
+            ws.rename_doc(&req.notebook, &req.path, &req.title)?;
+            Ok(Value::Null)
+        }),
+        API_REMOVE_DOC => parse::<RemoveDoc>(body).and_then(|req| {
+            ws.remove_doc(&req.notebook, &req.path)?;
+            Ok(Value::Null)
+        }),
+        API_MOVE_DOCS => parse::<MoveDocs>(body).and_then(|req| {
+            ws.move_docs(&req.from_paths, &req.to_notebook, &req.to_path)?;
+            Ok(Value::Null)
+        }),
+        API_GET_HPATH_BY_PATH => parse::<GetHPathByPath>(body).and_then(|req| {
+            let doc = ws.doc_by_path(&req.notebook, &req.path)?;
+            Ok(json!(ws.hpath(doc)))
+        }),
+        API_GET_HPATH_BY_ID => parse::<GetHPathById>(body).and_then(|req| {
+            let row = ws
+                .blocks
+                .iter()
+                .find(|row| row.id == req.id)
+                .ok_or_else(|| anyhow!("block [{}] not found", req.id))?;
+            Ok(json!(row.hpath))
+        }),
+        API_GET_IDS_BY_HPATH => {
+            parse::<GetIdsByHPath>(body).map(|req| json!(ws.ids_by_hpath(&req.notebook, &req.path)))
+        }
+        API_LIST_DOCS_BY_PATH => parse::<ListDocsByPath>(body).and_then(|req| {
+            let mut files = ws.list_docs(&req.notebook, &req.path)?;
+            if let Some(max) = req.max_list_count {
+                files.truncate(max as usize);
+            }
+            Ok(json!({"box": req.notebook, "path": req.path, "files": files}))
+        }),
+
+        API_INSERT_BLOCK => parse::<InsertBlock>(body).and_then(|req| {
+            let position = match (&req.previous_id, &req.next_id, &req.parent_id) {
+                (Some(id), _, _) if !id.is_empty() => Position::After(id),
+                (_, Some(id), _) if !id.is_empty() => Position::Before(id),
+                (_, _, Some(id)) => Position::FirstChild(id),
+                _ => return Err(anyhow!("missing anchor block")),
+            };
+            let ids = ws.insert_blocks(position, markdown(&req.data)?)?;
+            Ok(inserted(ws, "insert", ids))
+        }),
+        API_PREPEND_BLOCK | API_APPEND_BLOCK => parse::<ChildBlock>(body).and_then(|req| {
+            let position = match path {
+                API_PREPEND_BLOCK => Position::FirstChild(&req.parent_id),
+                _ => Position::LastChild(&req.parent_id),
+            };
+            let ids = ws.insert_blocks(position, markdown(&req.data)?)?;
+            Ok(inserted(ws, "insert", ids))
+        }),
+        API_UPDATE_BLOCK => parse::<UpdateBlock>(body).and_then(|req| {
+            ws.update_block(&req.id, markdown(&req.data)?)?;
+            Ok(transactions("update", vec![json!({"id": req.id})]))
+        }),
+        API_DELETE_BLOCK => parse::<BlockId>(body).and_then(|req| {
+            let parent_id = ws.parent_id(&req.id);
+            ws.delete_block(&req.id)?;
+            Ok(transactions(
+                "delete",
+                vec![json!({"id": req.id, "parentID": parent_id})],
+            ))
+        }),
+        API_MOVE_BLOCK => parse::<MoveBlock>(body).and_then(|req| {
+            let position = match (&req.previous_id, &req.parent_id) {
+                (Some(id), _) if !id.is_empty() => Position::After(id),
+                (_, Some(id)) => Position::FirstChild(id),
+                _ => return Err(anyhow!("missing anchor block")),
+            };
+            ws.move_block(&req.id, position)?;
+            Ok(transactions(
+                "move",
+                vec![json!({
+                    "id": req.id,
+                    "parentID": ws.parent_id(&req.id),
+                    "previousID": ws.previous_id(&req.id),
+                })],
+            ))
+        }),
+        API_GET_BLOCK_KRAMDOWN => parse::<BlockId>(body).and_then(|req| {
+            let kramdown = ws.kramdown(&req.id)?;
+            Ok(json!(BlockKramdown {
+                id: req.id,
+                kramdown
+            }))
+        }),
+
+        API_GET_BLOCK_ATTRS => {
+            parse::<BlockId>(body).and_then(|req| Ok(json!(ws.block_attrs(&req.id)?)))
+        }
+        API_SET_BLOCK_ATTRS => parse::<SetBlockAttrs>(body).and_then(|req| {
+            ws.set_block_attrs(&req.id, &req.attrs)?;
+            Ok(Value::Null)
+        }),
+
+        API_FULL_TEXT_SEARCH_BLOCK => parse::<SearchRequest>(body).map(|req| search(ws, &req)),
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn inserted(ws: &Workspace, action: &str, ids: Vec<String>) -> Value {
+    let operations = ids
+        .into_iter()
+        .map(|id| {
+            json!({
+                "parentID": ws.parent_id(&id),
+                "previousID": ws.previous_id(&id),
+                "id": id,
+            })
+        })
+        .collect();
+    transactions(action, operations)
+}
+
+/// 关键字搜索, 不区分大小写, 命中部分以 `<mark>` 包裹
+fn search(ws: &Workspace, req: &SearchRequest) -> Value {
+    let query = req.query.to_lowercase();
+    let matched: Vec<_> = ws
+        .blocks
+        .iter()
+        .filter(|row| !query.is_empty() && row.content.to_lowercase().contains(&query))
+        .collect();
+    let page_size = match req.page_size {
+        0 => 32,
+        size => size as usize,
+    };
+    let page = req.page.max(1) as usize;
+    let mut roots: Vec<&str> = matched.iter().map(|row| row.root_id.as_str()).collect();
+    roots.sort();
+    roots.dedup();
+    let blocks: Vec<Value> = matched
+        .iter()
+        .skip((page - 1) * page_size)
+        .take(page_size)
+        .map(|row| {
+            json!({
+                "id": row.id,
+                "box": row.box_id,
+                "path": row.path,
+                "hPath": row.hpath,
+                "rootID": row.root_id,
+                "parentID": row.parent_id,
+                "name": row.name,
+                "alias": row.alias,
+                "memo": row.memo,
+                "content": highlight(&row.content, &[&query]),
+                "type": row.node_type,
+                "subType": row.subtype,
+                "created": row.created,
+                "updated": row.updated,
+            })
+        })
+        .collect();
+    json!({
+        "blocks": blocks,
+        "matchedBlockCount": matched.len(),
+        "matchedRootCount": roots.len(),
+        "pageCount": matched.len().div_ceil(page_size),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attr::{get_block_attributes, set_block_attributes};
+    use crate::block::{append_block, delete_block, get_block_kramdown};
+    use crate::filetree::{create_doc_with_md, get_hpath_by_id, list_docs_by_path};
+    use crate::search::{full_text_search_block, FullTextSearch};
+    use crate::sql::Query;
+
+    const BOX: &str = "20210808180117-czj9bvb";
+    const RUST_DOC: &str = "20240107160843-8f02mqs";
+
+    async fn kernel() -> MockKernel {
+        MockKernel::start(fixture_data_dir()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sql_query() {
+        let kernel = kernel().await;
+        let stmt = Query::blocks()
+            .select(&["id", "hpath", "content", "type", "subtype"])
+            .in_root(RUST_DOC)
+            .order_by("sort", crate::sql::Order::Asc)
+            .build();
+        let rows: Vec<Value> = kernel
+            .client()
+            .call(API_SQL_QUERY, &json!({ "stmt": stmt }))
+            .await
+            .unwrap();
+        assert_eq!(rows[0]["type"], "d");
+        assert_eq!(rows[0]["hpath"], "/Draft/Rust notes");
+        assert_eq!(rows[1]["subtype"], "h2");
+        assert_eq!(rows[3]["content"], "See the draft and a removed block.");
+
+        let refs: Vec<Value> = kernel
+            .client()
+            .call(
+                API_SQL_QUERY,
+                &json!({"stmt": Query::refs().select(&["def_block_id", "def_block_root_id"]).build()}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(refs.len(), 2);
+        assert_eq!(refs[0]["def_block_root_id"], "20230620165438-1pqr39r");
+        assert_eq!(refs[1]["def_block_root_id"], "");
+    }
+
+    #[tokio::test]
+    async fn test_filetree() {
+        let kernel = kernel().await;
+        let client = kernel.client();
+        let create = CreateDocWithMd {
+            notebook: BOX.to_string(),
+            path: "/Draft/Rust notes/Traits".to_string(),
+            markdown: "# Traits\n\nShared behavior.".to_string(),
+        };
+        let id = create_doc_with_md(&client, &create).await.unwrap();
+        let hpath = get_hpath_by_id(&client, &GetHPathById { id })
+            .await
+            .unwrap();
+        assert_eq!(hpath, "/Draft/Rust notes/Traits");
+
+        let list = ListDocsByPath {
+            notebook: BOX.to_string(),
+            path: "/20230620165438-1pqr39r.sy".to_string(),
+            sort: None,
+            max_list_count: None,
+        };
+        let listed = list_docs_by_path(&client, &list).await.unwrap();
+        assert_eq!(listed.files.len(), 1);
+        // 与内核一致, `name` 为标题加 `.sy`, `name1` 为文档的命名
+        assert_eq!(listed.files[0].name, "Rust notes.sy");
+        assert_eq!(listed.files[0].name1, "");
+        assert_eq!(listed.files[0].title(), "Rust notes");
+        assert_eq!(listed.files[0].sub_file_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_block_and_attrs() {
+        let kernel = kernel().await;
+        let client = kernel.client();
+        let append = ChildBlock {
+            data: BlockData::Markdown("Borrowing".to_string()),
+            parent_id: RUST_DOC.to_string(),
+        };
+        let transactions = append_block(&client, &append).await.unwrap();
+        let op = &transactions[0].do_operations[0];
+        assert_eq!(op.action, OperationAction::Insert);
+        assert_eq!(op.parent_id, RUST_DOC);
+        assert_eq!(op.previous_id, "20240107160955-p0a9z8y");
+        let kramdown = get_block_kramdown(&client, &op.id).await.unwrap();
+        assert!(kramdown.kramdown.starts_with("Borrowing\n{: id=\""));
+        delete_block(&client, &op.id).await.unwrap();
+        assert!(get_block_kramdown(&client, &op.id).await.is_err());
+
+        let id = "20240301091510-m4n5b6v";
+        let before = get_block_attributes(&client, id).await.unwrap();
+        assert_eq!(before.custom("priority"), Some("high"));
+        let mut after = before.clone();
+        after.custom.remove("priority");
+        after.memo = Some("weekly".to_string());
+        set_block_attributes(&client, id, &before, &after)
+            .await
+            .unwrap();
+        assert_eq!(get_block_attributes(&client, id).await.unwrap(), after);
+    }
+
+    #[tokio::test]
+    async fn test_full_text_search() {
+        let kernel = kernel().await;
+        let result = full_text_search_block(&kernel.client(), &FullTextSearch::new("rust"))
+            .await
+            .unwrap();
+        assert_eq!(result.matched_block_count, 2);
+        assert_eq!(result.matched_root_count, 1);
+        assert_eq!(result.blocks()[0].highlighted_content().text, "Rust notes");
+        assert_eq!(result.blocks()[1].highlighted_content().matches, vec![0..4]);
+    }
+}
@@ -0,0 +1,805 @@
+//! 从工作空间 data 目录加载的笔记本与文档, 以及据此建立的 SQLite 索引.
+//!
+//! 修改只保存在内存中, 不会写回磁盘.
+use crate::backend::fs::{
+    block_type, read_doc, read_notebooks, sorted_entries, NotebookEntry,
+};
+use crate::content::{content, fcontent};
+use crate::domain::DocFile;
+use crate::lute::node::{is_node_id_pattern, new_node_id, Node, NodeType};
+use crate::lute::protyle::markdown_to_protyle;
+use anyhow::{anyhow, bail, Result};
+use chrono::Local;
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE blocks (
+    id TEXT, parent_id TEXT, root_id TEXT, hash TEXT, box TEXT, path TEXT, hpath TEXT,
+    name TEXT, alias TEXT, memo TEXT, tag TEXT, content TEXT, fcontent TEXT, markdown TEXT,
+    length INTEGER, type TEXT, subtype TEXT, ial TEXT, sort INTEGER, created TEXT, updated TEXT
+);
+CREATE TABLE attributes (
+    id TEXT, name TEXT, value TEXT, type TEXT, block_id TEXT, root_id TEXT, box TEXT, path TEXT
+);
+CREATE TABLE refs (
+    id TEXT, def_block_id TEXT, def_block_parent_id TEXT, def_block_root_id TEXT,
+    def_block_path TEXT, block_id TEXT, root_id TEXT, box TEXT, path TEXT, content TEXT,
+    markdown TEXT, type TEXT
+);
+CREATE TABLE spans (
+    id TEXT, block_id TEXT, root_id TEXT, box TEXT, path TEXT, content TEXT, markdown TEXT,
+    type TEXT, ial TEXT
+);
+";
+
+pub(crate) struct Doc {
+    pub box_id: String,
+    /// 存储路径, 例如 `/20200812220555-lj3enxa/20210808180320-fqgskfj.sy`
+    pub path: String,
+    pub node: Node,
+}
+
+impl Doc {
+    pub fn id(&self) -> &str {
+        self.node.id.as_deref().unwrap_or_default()
+    }
+
+    pub fn title(&self) -> &str {
+        self.property("title").unwrap_or_default()
+    }
+
+    fn property(&self, key: &str) -> Option<&str> {
+        self.node
+            .properties
+            .as_ref()
+            .and_then(|props| props.get(key))
+            .map(String::as_str)
+    }
+
+    /// 子文档所在目录, 例如 `/a.sy` 对应 `/a/`
+    fn child_dir(&self) -> String {
+        format!("{}/", self.path.trim_end_matches(".sy"))
+    }
+
+    /// 自身所在目录, 例如 `/a/b.sy` 对应 `/a/`
+    fn parent_dir(&self) -> &str {
+        &self.path[..=self.path.rfind('/').unwrap_or(0)]
+    }
+}
+
+/// 新块的插入位置
+pub(crate) enum Position<'a> {
+    Before(&'a str),
+    After(&'a str),
+    FirstChild(&'a str),
+    LastChild(&'a str),
+}
+
+impl Position<'_> {
+    fn anchor(&self) -> &str {
+        match self {
+            Position::Before(id)
+            | Position::After(id)
+            | Position::FirstChild(id)
+            | Position::LastChild(id) => id,
+        }
+    }
+}
+
+/// 索引后的块, 字段与 `blocks` 表一致
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BlockRow {
+    pub id: String,
+    pub parent_id: String,
+    pub root_id: String,
+    pub box_id: String,
+    pub path: String,
+    pub hpath: String,
+    pub name: String,
+    pub alias: String,
+    pub memo: String,
+    pub content: String,
+    pub fcontent: String,
+    pub block_type: &'static str,
+    pub subtype: String,
+    pub ial: String,
+    pub sort: i64,
+    pub created: String,
+    pub updated: String,
+    /// 节点类型, 例如 `NodeParagraph`
+    pub node_type: String,
+}
+
+pub(crate) struct Workspace {
+    pub notebooks: Vec<NotebookEntry>,
+    /// 文档 id -> 文档
+    pub docs: BTreeMap<String, Doc>,
+    pub blocks: Vec<BlockRow>,
+    db: Connection,
+}
+
+impl Workspace {
+    /// 加载 `data_dir` 下的全部笔记本
+    pub fn load(data_dir: &Path) -> Result<Workspace> {
+        let notebooks = read_notebooks(data_dir)?;
+        let mut docs = BTreeMap::new();
+        for notebook in &notebooks {
+            load_docs(&data_dir.join(&notebook.id), &notebook.id, "", &mut docs)?;
+        }
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(SCHEMA)?;
+        let mut workspace = Workspace {
+            notebooks,
+            docs,
+            blocks: Vec::new(),
+            db,
+        };
+        workspace.reindex()?;
+        Ok(workspace)
+    }
+
+    /// 重建全部索引
+    pub fn reindex(&mut self) -> Result<()> {
+        let mut blocks = Vec::new();
+        let mut spans = Vec::new();
+        for doc in self.docs.values() {
+            let hpath = self.hpath(doc);
+            let mut indexer = Indexer {
+                doc,
+                hpath: &hpath,
+                blocks: &mut blocks,
+                spans: &mut spans,
+                sort: 0,
+            };
+            indexer.index(&doc.node, "", "");
+        }
+
+        let tx = self.db.transaction()?;
+        tx.execute_batch(
+            "DELETE FROM blocks; DELETE FROM attributes; DELETE FROM refs; DELETE FROM spans;",
+        )?;
+        for row in &blocks {
+            tx.execute(
+                "INSERT INTO blocks VALUES (?1, ?2, ?3, '', ?4, ?5, ?6, ?7, ?8, ?9, '', ?10, ?11, '', ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+                params![
+                    row.id,
+                    row.parent_id,
+                    row.root_id,
+                    row.box_id,
+                    row.path,
+                    row.hpath,
+                    row.name,
+                    row.alias,
+                    row.memo,
+                    row.content,
+                    row.fcontent,
+                    row.content.chars().count() as i64,
+                    row.block_type,
+                    row.subtype,
+                    row.ial,
+                    row.sort,
+                    row.created,
+                    row.updated,
+                ],
+            )?;
+        }
+        for doc in self.docs.values() {
+            for_each_block(&doc.node, &mut |node| {
+                for (name, value) in node.properties.iter().flatten() {
+                    tx.execute(
+                        "INSERT INTO attributes VALUES (?1, ?2, ?3, 'b', ?4, ?5, ?6, ?7)",
+                        params![
+                            format!("{}-{}", node.id.as_deref().unwrap_or_default(), name),
+                            name,
+                            value,
+                            node.id,
+                            doc.id(),
+                            doc.box_id,
+                            doc.path,
+                        ],
+                    )?;
+                }
+                Ok(())
+            })?;
+        }
+        for span in &spans {
+            tx.execute(
+                "INSERT INTO spans VALUES (?1, ?2, ?3, ?4, ?5, ?6, '', ?7, '')",
+                params![
+                    new_node_id(),
+                    span.block_id,
+                    span.root_id,
+                    span.box_id,
+                    span.path,
+                    span.content,
+                    format!("textmark {}", span.mark_type),
+                ],
+            )?;
+            if let Some(def_id) = &span.ref_id {
+                let def = blocks.iter().find(|row| row.id == *def_id);
+                tx.execute(
+                    "INSERT INTO refs VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, '', 'ref_id')",
+                    params![
+                        new_node_id(),
+                        def_id,
+                        def.map(|row| row.parent_id.as_str()).unwrap_or_default(),
+                        def.map(|row| row.root_id.as_str()).unwrap_or_default(),
+                        def.map(|row| row.path.as_str()).unwrap_or_default(),
+                        span.block_id,
+                        span.root_id,
+                        span.box_id,
+                        span.path,
+                        span.content,
+                    ],
+                )?;
+            }
+        }
+        tx.commit()?;
+        self.blocks = blocks;
+        Ok(())
+    }
+
+    /// 执行 SQL, 每一行转换为以列名为 key 的对象
+    pub fn query(&self, stmt: &str) -> Result<Vec<Value>> {
+        let mut stmt = self.db.prepare(stmt)?;
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+        let mut rows = stmt.query([])?;
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            let mut object = Map::new();
+            for (i, column) in columns.iter().enumerate() {
+                let value = match row.get_ref(i)? {
+                    ValueRef::Null => Value::Null,
+                    ValueRef::Integer(n) => json!(n),
+                    ValueRef::Real(f) => json!(f),
+                    ValueRef::Text(text) => json!(String::from_utf8_lossy(text)),
+                    ValueRef::Blob(_) => Value::Null,
+                };
+                object.insert(column.clone(), value);
+            }
+            result.push(Value::Object(object));
+        }
+        Ok(result)
+    }
+
+    pub fn notebook_mut(&mut self, box_id: &str) -> Result<&mut NotebookEntry> {
+        self.notebooks
+            .iter_mut()
+            .find(|notebook| notebook.id == box_id)
+            .ok_or_else(|| anyhow!("notebook [{}] not found", box_id))
+    }
+
+    // -----------------------------------------------------------------------
+    // filetree
+    // -----------------------------------------------------------------------
+
+    /// 文档的人类可读路径
+    pub fn hpath(&self, doc: &Doc) -> String {
+        doc.path
+            .trim_end_matches(".sy")
+            .split('/')
+            .filter(|id| !id.is_empty())
+            .map(|id| self.docs.get(id).map(Doc::title).unwrap_or(id))
+            .fold(String::new(), |hpath, title| hpath + "/" + title)
+    }
+
+    pub fn doc_by_path(&self, box_id: &str, path: &str) -> Result<&Doc> {
+        self.docs
+            .values()
+            .find(|doc| doc.box_id == box_id && doc.path == path)
+            .ok_or_else(|| anyhow!("document [{}{}] not found", box_id, path))
+    }
+
+    fn doc_id_by_path(&self, box_id: &str, path: &str) -> Result<String> {
+        self.doc_by_path(box_id, path)
+            .map(|doc| doc.id().to_string())
+    }
+
+    pub fn ids_by_hpath(&self, box_id: &str, hpath: &str) -> Vec<String> {
+        self.docs
+            .values()
+            .filter(|doc| doc.box_id == box_id && self.hpath(doc) == hpath)
+            .map(|doc| doc.id().to_string())
+            .collect()
+    }
+
+    /// 按人类可读路径创建文档, 缺少的父文档会一并创建. 返回新文档 id.
+    pub fn create_doc_with_md(
+        &mut self,
+        box_id: &str,
+        hpath: &str,
+        markdown: &str,
+    ) -> Result<String> {
+        self.notebook(box_id)?;
+        let hpath = hpath.trim_end_matches('/');
+        let Some((parent_hpath, title)) = hpath.rsplit_once('/') else {
+            bail!("invalid path [{}]", hpath);
+        };
+        let parent_dir = if parent_hpath.is_empty() {
+            "/".to_string()
+        } else {
+            let parent_id = match self.ids_by_hpath(box_id, parent_hpath).first() {
+                Some(id) => id.clone(),
+                None => self.create_doc_with_md(box_id, parent_hpath, "")?,
+            };
+            self.docs[&parent_id].child_dir()
+        };
+        let id = new_node_id();
+        let mut children = markdown_blocks(markdown);
+        if children.is_empty() {
+            children.push(block_node("NodeParagraph", None, ""));
+        }
+        let node = new_node(
+            json!({
+                "ID": id,
+                "Spec": "1",
+                "Type": "NodeDocument",
+                "Properties": {"id": id, "title": title, "type": "doc", "updated": now()},
+            }),
+            children,
+        );
+        let doc = Doc {
+            box_id: box_id.to_string(),
+            path: format!("{}{}.sy", parent_dir, id),
+            node,
+        };
+        self.docs.insert(id.clone(), doc);
+        self.reindex()?;
+        Ok(id)
+    }
+
+    pub fn rename_doc(&mut self, box_id: &str, path: &str, title: &str) -> Result<()> {
+        let id = self.doc_id_by_path(box_id, path)?;
+        let doc = self.docs.get_mut(&id).unwrap();
+        let props = doc.node.properties.get_or_insert_with(HashMap::new);
+        props.insert("title".to_string(), title.to_string());
+        props.insert("updated".to_string(), now());
+        self.reindex()
+    }
+
+    /// 删除文档及其全部子文档
+    pub fn remove_doc(&mut self, box_id: &str, path: &str) -> Result<()> {
+        let doc = self.doc_by_path(box_id, path)?;
+        let child_dir = doc.child_dir();
+        let id = doc.id().to_string();
+        self.docs.retain(|doc_id, doc| {
+            *doc_id != id && !(doc.box_id == box_id && doc.path.starts_with(&child_dir))
+        });
+        self.reindex()
+    }
+
+    /// `from_paths` 中的路径可以带 `<box>` 前缀, 不带时按路径查找所在笔记本
+    pub fn move_docs(&mut self, from_paths: &[String], to_box: &str, to_path: &str) -> Result<()> {
+        self.notebook(to_box)?;
+        let to_dir = match to_path {
+            "/" | "" => "/".to_string(),
+            _ => self.doc_by_path(to_box, to_path)?.child_dir(),
+        };
+        for from in from_paths {
+            let (box_id, path) = match from.split_once('/') {
+                Some((box_id, path)) if !box_id.is_empty() => {
+                    (box_id.to_string(), format!("/{}", path))
+                }
+                _ => {
+                    let doc = self
+                        .docs
+                        .values()
+                        .find(|doc| doc.path == *from)
+                        .ok_or_else(|| anyhow!("document [{}] not found", from))?;
+                    (doc.box_id.clone(), from.clone())
+                }
+            };
+            let doc = self.doc_by_path(&box_id, &path)?;
+            let old_dir = doc.parent_dir().to_string();
+            let old_child_dir = doc.child_dir();
+            let id = doc.id().to_string();
+            for doc in self.docs.values_mut() {
+                if doc.box_id == box_id && (doc.id() == id || doc.path.starts_with(&old_child_dir))
+                {
+                    doc.path = format!("{}{}", to_dir, &doc.path[old_dir.len()..]);
+                    doc.box_id = to_box.to_string();
+                }
+            }
+        }
+        self.reindex()
+    }
+
+    /// 列出 `path` 下的直接子文档, 按 id(即创建时间) 排序
+    pub fn list_docs(&self, box_id: &str, path: &str) -> Result<Vec<DocFile>> {
+        self.notebook(box_id)?;
+        let dir = match path {
+            "/" | "" => "/".to_string(),
+            _ => self.doc_by_path(box_id, path)?.child_dir(),
+        };
+        let files = self
+            .docs
+            .values()
+            .filter(|doc| doc.box_id == box_id && doc.parent_dir() == dir)
+            .map(|doc| {
+                let child_dir = doc.child_dir();
+                DocFile {
+                    id: doc.id().to_string(),
+                    name: format!("{}.sy", doc.title()),
+                    name1: doc.property("name").unwrap_or_default().to_string(),
+                    path: doc.path.clone(),
+                    icon: doc.property("icon").unwrap_or_default().to_string(),
+                    alias: doc.property("alias").unwrap_or_default().to_string(),
+                    memo: doc.property("memo").unwrap_or_default().to_string(),
+                    bookmark: doc.property("bookmark").unwrap_or_default().to_string(),
+                    sub_file_count: self
+                        .docs
+                        .values()
+                        .filter(|sub| sub.box_id == box_id && sub.parent_dir() == child_dir)
+                        .count() as i32,
+                    ..DocFile::default()
+                }
+            })
+            .collect();
+        Ok(files)
+    }
+
+    fn notebook(&self, box_id: &str) -> Result<&NotebookEntry> {
+        self.notebooks
+            .iter()
+            .find(|notebook| notebook.id == box_id)
+            .ok_or_else(|| anyhow!("notebook [{}] not found", box_id))
+    }
+
+    // -----------------------------------------------------------------------
+    // block
+    // -----------------------------------------------------------------------
+
+    /// 包含块 `id` 的文档 id
+    fn doc_of_block(&self, id: &str) -> Result<String> {
+        self.docs
+            .values()
+            .find(|doc| find(&doc.node, id).is_some())
+            .map(|doc| doc.id().to_string())
+            .ok_or_else(|| anyhow!("block [{}] not found", id))
+    }
+
+    pub fn block(&self, id: &str) -> Result<&Node> {
+        let doc_id = self.doc_of_block(id)?;
+        Ok(find(&self.docs[&doc_id].node, id).unwrap())
+    }
+
+    fn block_mut(&mut self, id: &str) -> Result<&mut Node> {
+        let doc_id = self.doc_of_block(id)?;
+        Ok(find_mut(&mut self.docs.get_mut(&doc_id).unwrap().node, id).unwrap())
+    }
+
+    pub fn parent_id(&self, id: &str) -> Option<String> {
+        let doc_id = self.doc_of_block(id).ok()?;
+        parent_of(&self.docs[&doc_id].node, id).and_then(|parent| parent.id.clone())
+    }
+
+    pub fn previous_id(&self, id: &str) -> Option<String> {
+        let doc_id = self.doc_of_block(id).ok()?;
+        let parent = parent_of(&self.docs[&doc_id].node, id)?;
+        let index = parent
+            .children
+            .iter()
+            .position(|c| c.id.as_deref() == Some(id))?;
+        index
+            .checked_sub(1)
+            .and_then(|i| parent.children[i].id.clone())
+    }
+
+    /// 插入由 Markdown 生成的块, 返回新块的 id
+    pub fn insert_blocks(&mut self, position: Position, markdown: &str) -> Result<Vec<String>> {
+        let doc_id = self.doc_of_block(position.anchor())?;
+        let mut nodes = markdown_blocks(markdown);
+        let ids = nodes.iter().filter_map(|node| node.id.clone()).collect();
+        let root = &mut self.docs.get_mut(&doc_id).unwrap().node;
+        if !insert(root, &position, &mut nodes) {
+            bail!("block [{}] not found", position.anchor());
+        }
+        self.reindex()?;
+        Ok(ids)
+    }
+
+    /// 用 Markdown 的第一个块替换块内容, 保留块 id 与属性
+    pub fn update_block(&mut self, id: &str, markdown: &str) -> Result<()> {
+        let replacement = markdown_blocks(markdown).into_iter().next();
+        let node = self.block_mut(id)?;
+        if let Some(replacement) = replacement {
+            node.children = replacement.children;
+        }
+        node.properties
+            .get_or_insert_with(HashMap::new)
+            .insert("updated".to_string(), now());
+        self.reindex()
+    }
+
+    pub fn delete_block(&mut self, id: &str) -> Result<()> {
+        let doc_id = self.doc_of_block(id)?;
+        remove(&mut self.docs.get_mut(&doc_id).unwrap().node, id);
+        self.reindex()
+    }
+
+    pub fn move_block(&mut self, id: &str, position: Position) -> Result<()> {
+        let target_doc = self.doc_of_block(position.anchor())?;
+        let doc_id = self.doc_of_block(id)?;
+        let node = remove(&mut self.docs.get_mut(&doc_id).unwrap().node, id)
+            .ok_or_else(|| anyhow!("block [{}] not found", id))?;
+        let root = &mut self.docs.get_mut(&target_doc).unwrap().node;
+        if !insert(root, &position, &mut vec![node]) {
+            bail!("block [{}] not found", position.anchor());
+        }
+        self.reindex()
+    }
+
+    /// 块的 kramdown: 文本内容后跟 IAL
+    pub fn kramdown(&self, id: &str) -> Result<String> {
+        let row = self
+            .blocks
+            .iter()
+            .find(|row| row.id == id)
+            .ok_or_else(|| anyhow!("block [{}] not found", id))?;
+        Ok(format!("{}\n{}", row.content, row.ial))
+    }
+
+    // -----------------------------------------------------------------------
+    // attr
+    // -----------------------------------------------------------------------
+
+    pub fn block_attrs(&self, id: &str) -> Result<HashMap<String, String>> {
+        Ok(self.block(id)?.properties.clone().unwrap_or_default())
+    }
+
+    /// 设置块属性, 值为空字符串的属性会被删除
+    pub fn set_block_attrs(&mut self, id: &str, attrs: &HashMap<String, String>) -> Result<()> {
+        let node = self.block_mut(id)?;
+        let props = node.properties.get_or_insert_with(HashMap::new);
+        for (key, value) in attrs {
+            if value.is_empty() {
+                props.remove(key);
+            } else {
+                props.insert(key.clone(), value.clone());
+            }
+        }
+        self.reindex()
+    }
+}
+
+/// 递归加载 `dir` 下的 `.sy` 文件, `rel` 为 `dir` 相对笔记本目录的路径
+fn load_docs(dir: &Path, box_id: &str, rel: &str, docs: &mut BTreeMap<String, Doc>) -> Result<()> {
+    for (name, path) in sorted_entries(dir)? {
+        if path.is_dir() && is_node_id_pattern(&name) {
+            load_docs(&path, box_id, &format!("{}/{}", rel, name), docs)?;
+        } else if name.ends_with(".sy") {
+            let node = read_doc(&path)?;
+            let doc = Doc {
+                box_id: box_id.to_string(),
+                path: format!("{}/{}", rel, name),
+                node,
+            };
+            docs.insert(doc.id().to_string(), doc);
+        }
+    }
+    Ok(())
+}
+
+struct Span {
+    block_id: String,
+    root_id: String,
+    box_id: String,
+    path: String,
+    content: String,
+    mark_type: String,
+    /// 块引用指向的块
+    ref_id: Option<String>,
+}
+
+struct Indexer<'a> {
+    doc: &'a Doc,
+    hpath: &'a str,
+    blocks: &'a mut Vec<BlockRow>,
+    spans: &'a mut Vec<Span>,
+    sort: i64,
+}
+
+impl Indexer<'_> {
+    /// `block_id` 为最近的块祖先
+    fn index(&mut self, node: &Node, parent_id: &str, block_id: &str) {
+        let mut block_id = block_id;
+        if let (Some(id), Some(type_code)) =
+            (node.id.as_deref(), block_type(node).map(|t| t.code()))
+        {
+            let props = node.properties.clone().unwrap_or_default();
+            let prop = |key: &str| props.get(key).cloned().unwrap_or_default();
+            let created = id.get(..14).unwrap_or_default().to_string();
+            let updated = props.get("updated").cloned().unwrap_or(created.clone());
+            self.blocks.push(BlockRow {
+                id: id.to_string(),
+                parent_id: parent_id.to_string(),
+                root_id: self.doc.id().to_string(),
+                box_id: self.doc.box_id.clone(),
+                path: self.doc.path.clone(),
+                hpath: self.hpath.to_string(),
+                name: prop("name"),
+                alias: prop("alias"),
+                memo: prop("memo"),
+                content: content(node),
+                fcontent: fcontent(node),
+                block_type: type_code,
+                subtype: subtype(node),
+                ial: ial(&props),
+                sort: self.sort,
+                created,
+                updated,
+                node_type: node.type_str.clone(),
+            });
+            self.sort += 1;
+            block_id = id;
+        }
+        if let NodeType::NodeTextMark = node.node_type {
+            let mark_type = node.text_mark_type.clone().unwrap_or_default();
+            let ref_id = node.block_ref_id().map(str::to_string);
+            self.spans.push(Span {
+                block_id: block_id.to_string(),
+                root_id: self.doc.id().to_string(),
+                box_id: self.doc.box_id.clone(),
+                path: self.doc.path.clone(),
+                content: node
+                    .text_mark_text_content
+                    .clone()
+                    .or_else(|| node.text_mark_inline_math_content.clone())
+                    .unwrap_or_default(),
+                mark_type,
+                ref_id,
+            });
+        }
+        let parent_id = node.id.as_deref().unwrap_or(parent_id);
+        for child in &node.children {
+            self.index(child, parent_id, block_id);
+        }
+    }
+}
+
+fn subtype(node: &Node) -> String {
+    match node.node_type {
+        NodeType::NodeHeading => format!("h{}", node.heading_level.unwrap_or(1)),
+        NodeType::NodeList | NodeType::NodeListItem => {
+            match node.list_data.as_ref().and_then(|data| data.typ) {
+                Some(1) => "o",
+                Some(3) => "t",
+                _ => "u",
+            }
+            .to_string()
+        }
+        _ => String::new(),
+    }
+}
+
+/// 按 key 排序的 `{: key="value"}`
+fn ial(props: &HashMap<String, String>) -> String {
+    let props: BTreeMap<_, _> = props.iter().collect();
+    let attrs: Vec<String> = props
+        .iter()
+        .map(|(key, value)| format!("{}=\"{}\"", key, value))
+        .collect();
+    format!("{{: {}}}", attrs.join(" "))
+}
+
+fn for_each_block(
+    node: &Node,
+    f: &mut impl FnMut(&Node) -> rusqlite::Result<()>,
+) -> rusqlite::Result<()> {
+    if node.id.is_some() && block_type(node).is_some() {
+        f(node)?;
+    }
+    for child in &node.children {
+        for_each_block(child, f)?;
+    }
+    Ok(())
+}
+
+fn find<'a>(node: &'a Node, id: &str) -> Option<&'a Node> {
+    if node.id.as_deref() == Some(id) {
+        return Some(node);
+    }
+    node.children.iter().find_map(|child| find(child, id))
+}
+
+fn find_mut<'a>(node: &'a mut Node, id: &str) -> Option<&'a mut Node> {
+    if node.id.as_deref() == Some(id) {
+        return Some(node);
+    }
+    node.children
+        .iter_mut()
+        .find_map(|child| find_mut(child, id))
+}
+
+fn parent_of<'a>(node: &'a Node, id: &str) -> Option<&'a Node> {
+    if node
+        .children
+        .iter()
+        .any(|child| child.id.as_deref() == Some(id))
+    {
+        return Some(node);
+    }
+    node.children.iter().find_map(|child| parent_of(child, id))
+}
+
+fn remove(node: &mut Node, id: &str) -> Option<Node> {
+    if let Some(i) = node
+        .children
+        .iter()
+        .position(|c| c.id.as_deref() == Some(id))
+    {
+        return Some(node.children.remove(i));
+    }
+    node.children.iter_mut().find_map(|child| remove(child, id))
+}
+
+fn insert(node: &mut Node, position: &Position, nodes: &mut Vec<Node>) -> bool {
+    match position {
+        Position::FirstChild(id) | Position::LastChild(id) if node.id.as_deref() == Some(*id) => {
+            let at = match position {
+                Position::FirstChild(_) => 0,
+                _ => node.children.len(),
+            };
+            node.children.splice(at..at, nodes.drain(..));
+            return true;
+        }
+        Position::Before(id) | Position::After(id) => {
+            if let Some(i) = node
+                .children
+                .iter()
+                .position(|c| c.id.as_deref() == Some(*id))
+            {
+                let at = match position {
+                    Position::Before(_) => i,
+                    _ => i + 1,
+                };
+                node.children.splice(at..at, nodes.drain(..));
+                return true;
+            }
+        }
+        _ => {}
+    }
+    node.children
+        .iter_mut()
+        .any(|child| insert(child, position, nodes))
+}
+
+fn now() -> String {
+    Local::now().format("%Y%m%d%H%M%S").to_string()
+}
+
+fn new_node(value: Value, children: Vec<Node>) -> Node {
+    let mut node: Node = serde_json::from_value(value).expect("valid node json");
+    node.children = children;
+    node.set_node_type_for_tree();
+    node
+}
+
+/// 将 Markdown 转换为思源的块, 与内核一样分配新的块 ID
+fn markdown_blocks(markdown: &str) -> Vec<Node> {
+    markdown_to_protyle(markdown).children
+}
+
+fn block_node(node_type: &str, heading_level: Option<usize>, text: &str) -> Node {
+    let id = new_node_id();
+    let mut value = json!({
+        "ID": id,
+        "Type": node_type,
+        "Properties": {"id": id, "updated": now()},
+    });
+    if let Some(level) = heading_level {
+        value["HeadingLevel"] = json!(level);
+    }
+    let children = match text {
+        "" => Vec::new(),
+        _ => vec![new_node(
+            json!({"Type": "NodeText", "Data": text}),
+            Vec::new(),
+        )],
+    };
+    new_node(value, children)
+}
@@ -0,0 +1,184 @@
+//! 笔记本接口 `/api/notebook/*`
+use crate::client::SiyuanClient;
+use crate::domain::*;
+use crate::error::ApiResult;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+pub const API_LS_NOTEBOOKS: &str = "/api/notebook/lsNotebooks";
+pub const API_OPEN_NOTEBOOK: &str = "/api/notebook/openNotebook";
+pub const API_CLOSE_NOTEBOOK: &str = "/api/notebook/closeNotebook";
+pub const API_CREATE_NOTEBOOK: &str = "/api/notebook/createNotebook";
+pub const API_RENAME_NOTEBOOK: &str = "/api/notebook/renameNotebook";
+pub const API_REMOVE_NOTEBOOK: &str = "/api/notebook/removeNotebook";
+pub const API_GET_NOTEBOOK_CONF: &str = "/api/notebook/getNotebookConf";
+pub const API_SET_NOTEBOOK_CONF: &str = "/api/notebook/setNotebookConf";
+
+#[derive(Serialize)]
+struct NotebookId<'a> {
+    notebook: &'a str,
+}
+
+#[derive(Serialize)]
+struct RenameNotebook<'a> {
+    notebook: &'a str,
+    name: &'a str,
+}
+
+#[derive(Serialize)]
+struct SetNotebookConf<'a> {
+    notebook: &'a str,
+    conf: &'a NotebookConf,
+}
+
+#[derive(Deserialize)]
+struct CreatedNotebook {
+    notebook: Notebook,
+}
+
+/// 列出全部笔记本(包括已关闭的)
+pub async fn ls_notebooks(client: &SiyuanClient) -> ApiResult<Vec<Notebook>> {
+    client
+        .call::<_, Notebooks>(API_LS_NOTEBOOKS, &json!({}))
+        .await
+        .map(|resp| resp.notebooks)
+}
+
+pub async fn open_notebook(client: &SiyuanClient, notebook: &str) -> ApiResult<()> {
+    client
+        .call(API_OPEN_NOTEBOOK, &NotebookId { notebook })
+        .await
+}
+
+pub async fn close_notebook(client: &SiyuanClient, notebook: &str) -> ApiResult<()> {
+    client
+        .call(API_CLOSE_NOTEBOOK, &NotebookId { notebook })
+        .await
+}
+
+pub async fn create_notebook(client: &SiyuanClient, name: &str) -> ApiResult<Notebook> {
+    client
+        .call::<_, CreatedNotebook>(API_CREATE_NOTEBOOK, &json!({ "name": name }))
+        .await
+        .map(|resp| resp.notebook)
+}
+
+pub async fn rename_notebook(client: &SiyuanClient, notebook: &str, name: &str) -> ApiResult<()> {
+    client
+        .call(API_RENAME_NOTEBOOK, &RenameNotebook { notebook, name })
+        .await
+}
+
+pub async fn remove_notebook(client: &SiyuanClient, notebook: &str) -> ApiResult<()> {
+    client
+        .call(API_REMOVE_NOTEBOOK, &NotebookId { notebook })
+        .await
+}
+
+pub async fn get_notebook_conf(
+    client: &SiyuanClient,
+    notebook: &str,
+) -> ApiResult<NotebookConfResponse> {
+    client
+        .call(API_GET_NOTEBOOK_CONF, &NotebookId { notebook })
+        .await
+}
+
+/// 保存笔记本配置, 返回内核保存后的配置
+pub async fn set_notebook_conf(
+    client: &SiyuanClient,
+    notebook: &str,
+    conf: &NotebookConf,
+) -> ApiResult<NotebookConf> {
+    client
+        .call(API_SET_NOTEBOOK_CONF, &SetNotebookConf { notebook, conf })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub::StubServer;
+
+    #[tokio::test]
+    async fn test_ls_and_create_notebooks() {
+        let server = StubServer::start()
+            .await
+            .route(
+                API_LS_NOTEBOOKS,
+                json!({"notebooks": [
+                    {"id": "20210817205410-2kvfpfn", "name": "Work", "icon": "1f41b", "sort": 0, "closed": false},
+                    {"id": "20210808180117-czj9bvb", "name": "Personal", "icon": "", "sort": 1, "sortMode": 15, "closed": true}
+                ]}),
+            )
+            .route(
+                API_CREATE_NOTEBOOK,
+                json!({"notebook": {"id": "20220126215949-r1wvoch", "name": "Review", "closed": false}}),
+            );
+        let client = server.client();
+
+        let notebooks = ls_notebooks(&client).await.unwrap();
+        assert_eq!(notebooks.len(), 2);
+        assert_eq!(notebooks[0].name, "Work");
+        assert!(notebooks[1].closed);
+        assert_eq!(notebooks[1].sort_mode, 15);
+
+        let notebook = create_notebook(&client, "Review").await.unwrap();
+        assert_eq!(notebook.id, "20220126215949-r1wvoch");
+        assert_eq!(server.requests()[1].body, json!({"name": "Review"}));
+    }
+
+    #[tokio::test]
+    async fn test_notebook_operations() {
+        let id = "20210817205410-2kvfpfn";
+        let server = StubServer::start()
+            .await
+            .route(API_OPEN_NOTEBOOK, json!(null))
+            .route(API_CLOSE_NOTEBOOK, json!(null))
+            .route(API_RENAME_NOTEBOOK, json!(null))
+            .route(API_REMOVE_NOTEBOOK, json!(null));
+        let client = server.client();
+
+        open_notebook(&client, id).await.unwrap();
+        close_notebook(&client, id).await.unwrap();
+        rename_notebook(&client, id, "Work notes").await.unwrap();
+        remove_notebook(&client, id).await.unwrap();
+
+        let sent = server.requests();
+        assert_eq!(sent[0].body, json!({"notebook": id}));
+        assert_eq!(sent[2].body, json!({"notebook": id, "name": "Work notes"}));
+        assert_eq!(sent[3].path, API_REMOVE_NOTEBOOK);
+    }
+
+    #[tokio::test]
+    async fn test_notebook_conf_round_trip() {
+        let id = "20210817205410-2kvfpfn";
+        let conf = json!({
+            "name": "Work",
+            "closed": false,
+            "refCreateSavePath": "",
+            "docCreateSavePath": "",
+            "dailyNoteSavePath": "/daily note/{{now | date \"2006/01\"}}/{{now | date \"2006-01-02\"}}",
+            "dailyNoteTemplatePath": "",
+            "sortMode": 15
+        });
+        let server = StubServer::start()
+            .await
+            .route(
+                API_GET_NOTEBOOK_CONF,
+                json!({"box": id, "name": "Work", "conf": conf}),
+            )
+            .route(API_SET_NOTEBOOK_CONF, conf.clone());
+        let client = server.client();
+
+        let resp = get_notebook_conf(&client, id).await.unwrap();
+        assert_eq!(resp.box_id, id);
+        assert_eq!(resp.conf.extra.get("sortMode"), Some(&json!(15)));
+
+        set_notebook_conf(&client, id, &resp.conf).await.unwrap();
+        assert_eq!(
+            server.requests()[1].body,
+            json!({"notebook": id, "conf": conf})
+        );
+    }
+}
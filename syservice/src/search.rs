@@ -0,0 +1,445 @@
+//! 全文搜索接口 `/api/search/fullTextSearchBlock`
+//!
+//! 内核返回的 `content` 等字段是转义过的 HTML, 命中的关键字以 `<mark>` 包裹,
+//! [`Highlighted`] 将其还原为纯文本以及命中区间.
+use crate::client::SiyuanClient;
+use crate::domain::SyBlockType;
+use crate::error::ApiResult;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+
+pub const API_FULL_TEXT_SEARCH_BLOCK: &str = "/api/search/fullTextSearchBlock";
+
+const MARK_OPEN: &str = "<mark>";
+const MARK_CLOSE: &str = "</mark>";
+
+/// 搜索方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchMethod {
+    /// 关键字
+    #[default]
+    Keyword = 0,
+    /// 查询语法
+    Query = 1,
+    Sql = 2,
+    /// 正则表达式
+    Regex = 3,
+}
+
+/// 结果分组方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GroupBy {
+    #[default]
+    None = 0,
+    /// 按文档分组, 命中的块位于文档的 `children` 中
+    Document = 1,
+}
+
+/// 结果排序方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OrderBy {
+    /// 按块类型
+    #[default]
+    BlockType = 0,
+    CreatedAsc = 1,
+    CreatedDesc = 2,
+    UpdatedAsc = 3,
+    UpdatedDesc = 4,
+    /// 按原文内容顺序, 仅在按文档分组时有效
+    Content = 5,
+    RelevanceAsc = 6,
+    RelevanceDesc = 7,
+}
+
+macro_rules! serialize_as_u8 {
+    ($($ty:ty),*) => {
+        $(impl Serialize for $ty {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_u8(*self as u8)
+            }
+        })*
+    };
+}
+serialize_as_u8!(SearchMethod, GroupBy, OrderBy);
+
+/// 全文搜索请求
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FullTextSearch {
+    pub query: String,
+    pub method: SearchMethod,
+    /// 参与搜索的块类型, 为空时使用内核中的搜索设置
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub types: Option<BTreeMap<&'static str, bool>>,
+    /// 限定的笔记本
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub boxes: Vec<String>,
+    /// 限定的路径, 格式为 `box_id/path`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    pub group_by: GroupBy,
+    pub order_by: OrderBy,
+    /// 页码, 从 1 开始
+    pub page: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<u32>,
+}
+
+impl FullTextSearch {
+    pub fn new(query: impl Into<String>) -> Self {
+        Self {
+            query: query.into(),
+            page: 1,
+            ..Self::default()
+        }
+    }
+
+    pub fn method(mut self, method: SearchMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// 只搜索指定类型的块
+    pub fn types(mut self, types: &[SyBlockType]) -> Self {
+        self.types = Some(
+            types
+                .iter()
+                .filter_map(|block_type| type_filter_key(*block_type))
+                .map(|key| (key, true))
+                .collect(),
+        );
+        self
+    }
+
+    pub fn in_box(mut self, box_id: impl Into<String>) -> Self {
+        self.boxes.push(box_id.into());
+        self
+    }
+
+    pub fn under_path(mut self, box_id: &str, path: &str) -> Self {
+        self.paths.push(format!("{}{}", box_id, path));
+        self
+    }
+
+    pub fn group_by(mut self, group_by: GroupBy) -> Self {
+        self.group_by = group_by;
+        self
+    }
+
+    pub fn order_by(mut self, order_by: OrderBy) -> Self {
+        self.order_by = order_by;
+        self
+    }
+
+    pub fn page(mut self, page: u32) -> Self {
+        self.page = page.max(1);
+        self
+    }
+
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = Some(page_size);
+        self
+    }
+}
+
+/// 块类型在搜索设置 `types` 中对应的名称, 分割线不参与搜索
+pub(crate) fn type_filter_key(block_type: SyBlockType) -> Option<&'static str> {
+    let key = match block_type {
+        SyBlockType::Document => "document",
+        SyBlockType::Title => "heading",
+        SyBlockType::List => "list",
+        SyBlockType::ListItem => "listItem",
+        SyBlockType::Quote => "blockquote",
+        SyBlockType::SuperBlock => "superBlock",
+        SyBlockType::Paragraph => "paragraph",
+        SyBlockType::Code => "codeBlock",
+        SyBlockType::Method => "mathBlock",
+        SyBlockType::Table => "table",
+        SyBlockType::DataBlock => "databaseBlock",
+        SyBlockType::QueryEmbed => "embedBlock",
+        SyBlockType::Video => "videoBlock",
+        SyBlockType::Audio => "audioBlock",
+        SyBlockType::Widget => "widgetBlock",
+        SyBlockType::IFrame => "iframeBlock",
+        SyBlockType::Html => "htmlBlock",
+        SyBlockType::Tb => return None,
+    };
+    Some(key)
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FullTextSearchResult {
+    pub blocks: Option<Vec<SearchBlock>>,
+    pub matched_block_count: u32,
+    pub matched_root_count: u32,
+    pub page_count: u32,
+}
+
+impl FullTextSearchResult {
+    pub fn blocks(&self) -> &[SearchBlock] {
+        self.blocks.as_deref().unwrap_or_default()
+    }
+}
+
+/// 搜索结果中的块
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchBlock {
+    pub id: String,
+    #[serde(rename = "box")]
+    pub box_id: String,
+    /// 所在文档的存储路径
+    pub path: String,
+    pub h_path: String,
+    #[serde(rename = "rootID")]
+    pub root_id: String,
+    #[serde(rename = "parentID")]
+    pub parent_id: String,
+    pub name: String,
+    pub alias: String,
+    pub memo: String,
+    pub tag: String,
+    /// 带 `<mark>` 高亮的内容
+    pub content: String,
+    pub fcontent: String,
+    /// 节点类型, 例如 `NodeParagraph`
+    #[serde(rename = "type")]
+    pub node_type: String,
+    pub sub_type: String,
+    pub ial: Option<HashMap<String, String>>,
+    /// 按文档分组时, 文档下命中的块
+    pub children: Option<Vec<SearchBlock>>,
+    pub created: String,
+    pub updated: String,
+}
+
+impl SearchBlock {
+    pub fn highlighted_content(&self) -> Highlighted {
+        Highlighted::parse(&self.content)
+    }
+}
+
+/// 去除 `<mark>` 标记后的文本, 以及命中部分在 `text` 中的字节区间
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Highlighted {
+    pub text: String,
+    pub matches: Vec<Range<usize>>,
+}
+
+impl Highlighted {
+    /// 解析内核返回的高亮 HTML. 未闭合的 `<mark>` 延续到文本末尾.
+    pub fn parse(html: &str) -> Self {
+        let mut text = String::with_capacity(html.len());
+        let mut matches = Vec::new();
+        let mut start = None;
+        let mut rest = html;
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix(MARK_OPEN) {
+                start.get_or_insert(text.len());
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix(MARK_CLOSE) {
+                if let Some(start) = start.take() {
+                    if start < text.len() {
+                        matches.push(start..text.len());
+                    }
+                }
+                rest = after;
+            } else if rest.starts_with('&') {
+                let (c, len) = unescape_entity(rest).unwrap_or(('&', 1));
+                text.push(c);
+                rest = &rest[len..];
+            } else {
+                let c = rest.chars().next().unwrap();
+                text.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+        if let Some(start) = start {
+            if start < text.len() {
+                matches.push(start..text.len());
+            }
+        }
+        Self { text, matches }
+    }
+
+    /// 按命中区间切分文本, 返回 `(片段, 是否命中)`
+    pub fn segments(&self) -> Vec<(&str, bool)> {
+        let mut segments = Vec::new();
+        let mut pos = 0;
+        for range in &self.matches {
+            if pos < range.start {
+                segments.push((&self.text[pos..range.start], false));
+            }
+            segments.push((&self.text[range.clone()], true));
+            pos = range.end;
+        }
+        if pos < self.text.len() {
+            segments.push((&self.text[pos..], false));
+        }
+        segments
+    }
+}
+
+/// 解析 `s` 开头的 HTML 实体, 返回字符以及实体长度
+fn unescape_entity(s: &str) -> Option<(char, usize)> {
+    let end = s.bytes().take(12).position(|b| b == b';')?;
+    let entity = &s[1..end];
+    let c = match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        _ => {
+            let code = match entity.strip_prefix('#')? {
+                hex if hex.starts_with(['x', 'X']) => u32::from_str_radix(&hex[1..], 16).ok()?,
+                dec => dec.parse().ok()?,
+            };
+            char::from_u32(code)?
+        }
+    };
+    Some((c, end + 1))
+}
+
+/// 全文搜索
+pub async fn full_text_search_block(
+    client: &SiyuanClient,
+    search: &FullTextSearch,
+) -> ApiResult<FullTextSearchResult> {
+    client.call(API_FULL_TEXT_SEARCH_BLOCK, search).await
+}
+
+/// 转义 HTML 并用 `<mark>` 包裹 `keywords`(已转为小写)的每一处出现
+pub(crate) fn highlight(content: &str, keywords: &[&str]) -> String {
+    let escape = |s: &str| {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    };
+    let lower = content.to_lowercase();
+    // 大小写转换可能改变字节长度, 此时不做高亮
+    if lower.len() != content.len() {
+        return escape(content);
+    }
+    let mut ranges: Vec<Range<usize>> = keywords
+        .iter()
+        .filter(|keyword| !keyword.is_empty())
+        .flat_map(|keyword| {
+            lower
+                .match_indices(keyword)
+                .map(|(start, _)| start..start + keyword.len())
+        })
+        .collect();
+    ranges.sort_by_key(|range| range.start);
+    let mut html = String::new();
+    let mut pos = 0;
+    let mut iter = ranges.into_iter().peekable();
+    while let Some(mut range) = iter.next() {
+        if range.end <= pos {
+            continue;
+        }
+        range.start = range.start.max(pos);
+        // 合并相互重叠的命中
+        while let Some(next) = iter.next_if(|next| next.start <= range.end) {
+            range.end = range.end.max(next.end);
+        }
+        html.push_str(&escape(&content[pos..range.start]));
+        html.push_str("<mark>");
+        html.push_str(&escape(&content[range.clone()]));
+        html.push_str("</mark>");
+        pos = range.end;
+    }
+    html.push_str(&escape(&content[pos..]));
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub::StubServer;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_highlight() {
+        let highlighted =
+            Highlighted::parse("use <mark>Rust</mark> &amp; <mark>思源</mark>&#39;s &lt;API&gt;");
+        assert_eq!(highlighted.text, "use Rust & 思源's <API>");
+        assert_eq!(highlighted.matches, vec![4..8, 11..17]);
+        assert_eq!(
+            highlighted.segments(),
+            vec![
+                ("use ", false),
+                ("Rust", true),
+                (" & ", false),
+                ("思源", true),
+                ("'s <API>", false)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_highlight_edge_cases() {
+        assert_eq!(Highlighted::parse(""), Highlighted::default());
+        let plain = Highlighted::parse("AT&T &unknown; <b>");
+        assert_eq!(plain.text, "AT&T &unknown; <b>");
+        assert!(plain.matches.is_empty());
+
+        let unclosed = Highlighted::parse("a<mark>b<mark></mark>");
+        assert_eq!(unclosed.text, "ab");
+        assert_eq!(unclosed.matches, vec![1..2]);
+        assert_eq!(Highlighted::parse("<mark>x").matches, vec![0..1]);
+    }
+
+    #[tokio::test]
+    async fn test_full_text_search_block() {
+        let server = StubServer::start().await.route(
+            API_FULL_TEXT_SEARCH_BLOCK,
+            json!({
+                "blocks": [{
+                    "box": "20210808180117-czj9bvb",
+                    "path": "/20200812220555-lj3enxa.sy",
+                    "hPath": "/请从这里开始",
+                    "id": "20200813131152-0wk5akh",
+                    "rootID": "20200812220555-lj3enxa",
+                    "content": "<mark>思源</mark>笔记",
+                    "type": "NodeParagraph",
+                    "subType": "",
+                    "ial": null,
+                    "children": null
+                }],
+                "matchedBlockCount": 1,
+                "matchedRootCount": 1,
+                "pageCount": 1
+            }),
+        );
+        let search = FullTextSearch::new("思源")
+            .types(&[SyBlockType::Paragraph, SyBlockType::Title, SyBlockType::Tb])
+            .under_path("20210808180117-czj9bvb", "/20200812220555-lj3enxa.sy")
+            .group_by(GroupBy::Document)
+            .order_by(OrderBy::UpdatedDesc)
+            .page(2);
+        let result = full_text_search_block(&server.client(), &search)
+            .await
+            .unwrap();
+
+        assert_eq!(result.matched_block_count, 1);
+        let block = &result.blocks()[0];
+        assert_eq!(block.h_path, "/请从这里开始");
+        assert_eq!(block.highlighted_content().matches, vec![0..6]);
+        assert_eq!(
+            server.requests()[0].body,
+            json!({
+                "query": "思源",
+                "method": 0,
+                "types": {"heading": true, "paragraph": true},
+                "paths": ["20210808180117-czj9bvb/20200812220555-lj3enxa.sy"],
+                "groupBy": 1,
+                "orderBy": 4,
+                "page": 2
+            })
+        );
+    }
+}
@@ -0,0 +1,291 @@
+//! `/api/query/sql` 使用的 SQL 构造器.
+//!
+//! 所有来自用户输入的值都会作为字符串字面量转义后再拼接, 列名只接受
+//! `&'static str`, 避免把输入内容拼进 SQL 结构中.
+//!
+//! ```
+//! use syservice::domain::SyBlockType;
+//! use syservice::sql::{Order, Query};
+//!
+//! let stmt = Query::blocks()
+//!     .block_type(SyBlockType::Document)
+//!     .contains("content", "rust's")
+//!     .order_by("updated", Order::Desc)
+//!     .limit(20)
+//!     .build();
+//! assert_eq!(
+//!     stmt,
+//!     "SELECT * FROM blocks WHERE type = 'd' AND content LIKE '%rust''s%' ESCAPE '\\' \
+//!      ORDER BY updated DESC LIMIT 20"
+//! );
+//! ```
+use crate::domain::SyBlockType;
+
+/// 思源数据库中可查询的表
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Table {
+    Blocks,
+    Refs,
+    Attributes,
+    Spans,
+}
+
+impl Table {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Table::Blocks => "blocks",
+            Table::Refs => "refs",
+            Table::Attributes => "attributes",
+            Table::Spans => "spans",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+/// 单表查询
+#[derive(Debug, Clone)]
+pub struct Query {
+    table: Table,
+    columns: Vec<&'static str>,
+    conditions: Vec<String>,
+    order: Vec<(&'static str, Order)>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+impl Query {
+    pub fn new(table: Table) -> Self {
+        Self {
+            table,
+            columns: Vec::new(),
+            conditions: Vec::new(),
+            order: Vec::new(),
+            limit: None,
+            offset: None,
+        }
+    }
+
+    pub fn blocks() -> Self {
+        Self::new(Table::Blocks)
+    }
+
+    pub fn refs() -> Self {
+        Self::new(Table::Refs)
+    }
+
+    pub fn attributes() -> Self {
+        Self::new(Table::Attributes)
+    }
+
+    pub fn spans() -> Self {
+        Self::new(Table::Spans)
+    }
+
+    /// 指定查询的列, 默认为 `*`
+    pub fn select(mut self, columns: &[&'static str]) -> Self {
+        self.columns.extend_from_slice(columns);
+        self
+    }
+
+    /// `column = 'value'`
+    pub fn eq(mut self, column: &'static str, value: &str) -> Self {
+        self.conditions
+            .push(format!("{} = {}", column, quote(value)));
+        self
+    }
+
+    /// `column IN ('a', 'b')`, `values` 为空时不会匹配任何行
+    pub fn any_of<S: AsRef<str>>(mut self, column: &'static str, values: &[S]) -> Self {
+        if values.is_empty() {
+            self.conditions.push("0".to_string());
+        } else {
+            let values: Vec<String> = values.iter().map(|v| quote(v.as_ref())).collect();
+            self.conditions
+                .push(format!("{} IN ({})", column, values.join(", ")));
+        }
+        self
+    }
+
+    /// `column LIKE '%text%'`, `text` 中的 `%` 与 `_` 按字面匹配
+    pub fn contains(self, column: &'static str, text: &str) -> Self {
+        let pattern = format!("%{}%", escape_like(text));
+        self.like(column, &pattern)
+    }
+
+    /// `column LIKE 'prefix%'`
+    pub fn starts_with(self, column: &'static str, prefix: &str) -> Self {
+        let pattern = format!("{}%", escape_like(prefix));
+        self.like(column, &pattern)
+    }
+
+    fn like(mut self, column: &'static str, pattern: &str) -> Self {
+        self.conditions
+            .push(format!("{} LIKE {} ESCAPE '\\'", column, quote(pattern)));
+        self
+    }
+
+    pub fn block_type(self, block_type: SyBlockType) -> Self {
+        self.eq("type", block_type.code())
+    }
+
+    pub fn block_types(self, block_types: &[SyBlockType]) -> Self {
+        let codes: Vec<&str> = block_types.iter().map(SyBlockType::code).collect();
+        self.any_of("type", &codes)
+    }
+
+    /// 块子类型, 例如标题的 `h1`-`h6`, 列表的 `o`/`u`/`t`
+    pub fn subtype(self, subtype: &str) -> Self {
+        self.eq("subtype", subtype)
+    }
+
+    /// 限定笔记本
+    pub fn in_box(self, box_id: &str) -> Self {
+        self.eq("box", box_id)
+    }
+
+    /// 限定为某个文档下的块
+    pub fn in_root(self, root_id: &str) -> Self {
+        self.eq("root_id", root_id)
+    }
+
+    /// 限定存储路径前缀, 例如 `/20230620162729-levf2as/` 表示该文档的全部子文档
+    pub fn under_path(self, path_prefix: &str) -> Self {
+        self.starts_with("path", path_prefix)
+    }
+
+    pub fn order_by(mut self, column: &'static str, order: Order) -> Self {
+        self.order.push((column, order));
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: u32) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    pub fn build(&self) -> String {
+        let columns = if self.columns.is_empty() {
+            "*".to_string()
+        } else {
+            self.columns.join(", ")
+        };
+        let mut sql = format!("SELECT {} FROM {}", columns, self.table.name());
+        if !self.conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&self.conditions.join(" AND "));
+        }
+        if !self.order.is_empty() {
+            let order: Vec<String> = self
+                .order
+                .iter()
+                .map(|(column, order)| match order {
+                    Order::Asc => format!("{} ASC", column),
+                    Order::Desc => format!("{} DESC", column),
+                })
+                .collect();
+            sql.push_str(" ORDER BY ");
+            sql.push_str(&order.join(", "));
+        }
+        // SQLite 中 OFFSET 必须跟在 LIMIT 之后
+        match (self.limit, self.offset) {
+            (Some(limit), Some(offset)) => {
+                sql.push_str(&format!(" LIMIT {} OFFSET {}", limit, offset))
+            }
+            (Some(limit), None) => sql.push_str(&format!(" LIMIT {}", limit)),
+            (None, Some(offset)) => sql.push_str(&format!(" LIMIT -1 OFFSET {}", offset)),
+            (None, None) => {}
+        }
+        sql
+    }
+}
+
+/// 转义为 SQL 字符串字面量
+pub fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\0', "").replace('\'', "''"))
+}
+
+/// 转义 LIKE 模式中的通配符, 配合 `ESCAPE '\'` 使用
+pub fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("rust"), "'rust'");
+        assert_eq!(quote("it's"), "'it''s'");
+        assert_eq!(quote("' OR 1=1 --"), "''' OR 1=1 --'");
+    }
+
+    #[test]
+    fn test_contains_escapes_wildcards() {
+        let stmt = Query::blocks().contains("content", "100%_done\\").build();
+        assert_eq!(
+            stmt,
+            "SELECT * FROM blocks WHERE content LIKE '%100\\%\\_done\\\\%' ESCAPE '\\'"
+        );
+    }
+
+    #[test]
+    fn test_scoped_query() {
+        let stmt = Query::blocks()
+            .select(&["id", "content"])
+            .block_types(&[SyBlockType::Title, SyBlockType::Paragraph])
+            .subtype("h2")
+            .in_box("20210817205410-2kvfpfn")
+            .under_path("/20230620162729-levf2as/")
+            .order_by("sort", Order::Asc)
+            .order_by("updated", Order::Desc)
+            .limit(10)
+            .offset(20)
+            .build();
+        assert_eq!(
+            stmt,
+            "SELECT id, content FROM blocks WHERE type IN ('h', 'p') AND subtype = 'h2' \
+             AND box = '20210817205410-2kvfpfn' \
+             AND path LIKE '/20230620162729-levf2as/%' ESCAPE '\\' \
+             ORDER BY sort ASC, updated DESC LIMIT 10 OFFSET 20"
+        );
+    }
+
+    #[test]
+    fn test_other_tables() {
+        assert_eq!(
+            Query::refs()
+                .eq("def_block_id", "20250512161452-b4yltcf")
+                .build(),
+            "SELECT * FROM refs WHERE def_block_id = '20250512161452-b4yltcf'"
+        );
+        assert_eq!(
+            Query::attributes()
+                .starts_with("name", "custom-")
+                .offset(5)
+                .build(),
+            "SELECT * FROM attributes WHERE name LIKE 'custom-%' ESCAPE '\\' LIMIT -1 OFFSET 5"
+        );
+        assert_eq!(
+            Query::spans().any_of::<&str>("type", &[]).build(),
+            "SELECT * FROM spans WHERE 0"
+        );
+    }
+}
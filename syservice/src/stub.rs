@@ -0,0 +1,80 @@
+//! 测试用的本地内核替身: 按路径返回预设的响应, 并记录收到的请求.
+use crate::client::SiyuanClient;
+use crate::config::Profile;
+use crate::mock::http;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+
+/// 收到的一次请求
+#[derive(Debug, Clone)]
+pub(crate) struct RecordedRequest {
+    pub path: String,
+    pub body: Value,
+}
+
+#[derive(Default)]
+struct State {
+    /// 路径 -> 完整响应体
+    routes: HashMap<String, Value>,
+    requests: Vec<RecordedRequest>,
+}
+
+pub(crate) struct StubServer {
+    base_url: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl StubServer {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(State::default()));
+        let server_state = state.clone();
+        http::serve(
+            listener,
+            Arc::new(move |path, body| {
+                let mut state = server_state.lock().unwrap();
+                state.requests.push(RecordedRequest {
+                    path: path.to_string(),
+                    body,
+                });
+                state.routes.get(path).cloned()
+            }),
+        );
+        Self { base_url, state }
+    }
+
+    /// 为 `path` 设置成功响应, `data` 为响应中的 data 字段
+    pub fn route(self, path: &str, data: Value) -> Self {
+        self.respond(path, json!({"code": 0, "msg": "", "data": data}))
+    }
+
+    /// 为 `path` 设置内核错误响应
+    pub fn fail(self, path: &str, code: i32, msg: &str) -> Self {
+        self.respond(path, json!({"code": code, "msg": msg, "data": null}))
+    }
+
+    fn respond(self, path: &str, body: Value) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .routes
+            .insert(path.to_string(), body);
+        self
+    }
+
+    pub fn client(&self) -> SiyuanClient {
+        SiyuanClient::new(Profile {
+            base_url: self.base_url.clone(),
+            token: "stub-token".to_string(),
+            ..Profile::default()
+        })
+        .unwrap()
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
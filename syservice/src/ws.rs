@@ -0,0 +1,340 @@
+//! 订阅内核通过 `/ws?app=...&id=...` 推送的广播消息
+//!
+//! 连接断开后按指数退避自动重连, 收到的消息解码为 [`KernelEvent`] 后
+//! 通过 channel 交给调用方.
+use crate::config::Profile;
+use crate::domain::Transaction;
+use crate::lute::node::rand_str;
+use futures_util::StreamExt;
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+
+pub const WS_PATH: &str = "/ws";
+/// 连接时上报的 app 名称
+pub const DEFAULT_APP: &str = "rsy-scribe";
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// 内核推送的事件
+#[derive(Debug, Clone)]
+pub enum KernelEvent {
+    /// 连接(或重连)成功. 断开期间的事件不会补发, 需要时应重新拉取数据.
+    Connected,
+    /// 块事务, 桌面端的编辑都会以事务的形式推送
+    Transactions(Vec<Transaction>),
+    /// 文档重命名
+    Rename {
+        box_id: String,
+        id: String,
+        path: String,
+        title: String,
+    },
+    /// 文档被删除
+    RemoveDoc { ids: Vec<String> },
+    /// 后台任务进度
+    Progress { msg: String },
+    /// 清除进度提示
+    ClearProgress,
+    /// 提示消息, `code` 小于 0 表示错误
+    Message { code: i32, msg: String },
+    /// 未建模的事件
+    Other { cmd: String, data: Value },
+}
+
+/// 推送消息的原始结构
+#[derive(Debug, Deserialize)]
+struct PushMessage {
+    cmd: String,
+    #[serde(default)]
+    code: i32,
+    #[serde(default)]
+    msg: String,
+    #[serde(default)]
+    data: Value,
+}
+
+#[derive(Deserialize)]
+struct RenameData {
+    #[serde(rename = "box", default)]
+    box_id: String,
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    path: String,
+    #[serde(default)]
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct RemoveDocData {
+    #[serde(default)]
+    ids: Vec<String>,
+}
+
+impl KernelEvent {
+    pub fn decode(text: &str) -> serde_json::Result<KernelEvent> {
+        let message: PushMessage = serde_json::from_str(text)?;
+        let event = match message.cmd.as_str() {
+            "transactions" => KernelEvent::Transactions(serde_json::from_value(message.data)?),
+            "rename" => {
+                let data: RenameData = serde_json::from_value(message.data)?;
+                KernelEvent::Rename {
+                    box_id: data.box_id,
+                    id: data.id,
+                    path: data.path,
+                    title: data.title,
+                }
+            }
+            "removeDoc" => {
+                let data: RemoveDocData = serde_json::from_value(message.data)?;
+                KernelEvent::RemoveDoc { ids: data.ids }
+            }
+            "progress" => KernelEvent::Progress { msg: message.msg },
+            "cprogress" => KernelEvent::ClearProgress,
+            "msg" => KernelEvent::Message {
+                code: message.code,
+                msg: message.msg,
+            },
+            _ => KernelEvent::Other {
+                cmd: message.cmd,
+                data: message.data,
+            },
+        };
+        Ok(event)
+    }
+}
+
+/// 指数退避
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    current: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(INITIAL_BACKOFF, MAX_BACKOFF)
+    }
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            current: initial,
+        }
+    }
+
+    /// 返回本次需要等待的时间, 并将下一次的等待时间加倍
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.current;
+        self.current = (self.current * 2).min(self.max);
+        delay
+    }
+
+    pub fn reset(&mut self) {
+        self.current = self.initial;
+    }
+}
+
+/// 订阅的连接参数
+#[derive(Debug, Clone)]
+pub struct Subscription {
+    profile: Profile,
+    app: String,
+    /// 会话 id, 内核以此区分不同的连接
+    id: String,
+    backoff: Backoff,
+}
+
+impl Subscription {
+    pub fn new(profile: Profile) -> Self {
+        Self {
+            profile,
+            app: DEFAULT_APP.to_string(),
+            id: rand_str(7),
+            backoff: Backoff::default(),
+        }
+    }
+
+    pub fn app(mut self, app: impl Into<String>) -> Self {
+        self.app = app.into();
+        self
+    }
+
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// `http(s)://host` 对应的 `ws(s)://host/ws?app=..&id=..`
+    pub fn url(&self) -> String {
+        let base = self.profile.base_url.trim_end_matches('/');
+        let base = match base.split_once("://") {
+            Some(("https", rest)) => format!("wss://{}", rest),
+            Some((_, rest)) => format!("ws://{}", rest),
+            None => format!("ws://{}", base),
+        };
+        format!("{}{}?app={}&id={}", base, WS_PATH, self.app, self.id)
+    }
+
+    /// 在后台保持连接, 接收端被丢弃后任务结束
+    pub fn spawn(self) -> (Receiver<KernelEvent>, JoinHandle<()>) {
+        let (tx, rx) = mpsc::channel(256);
+        let handle = tokio::spawn(self.run(tx));
+        (rx, handle)
+    }
+
+    async fn run(mut self, tx: Sender<KernelEvent>) {
+        loop {
+            let mut request = match self.url().into_client_request() {
+                Ok(request) => request,
+                Err(_) => return,
+            };
+            if !self.profile.token.is_empty() {
+                if let Ok(value) = HeaderValue::from_str(&format!("Token {}", self.profile.token)) {
+                    request.headers_mut().insert("Authorization", value);
+                }
+            }
+            if let Ok((mut stream, _)) = tokio_tungstenite::connect_async(request).await {
+                self.backoff.reset();
+                if tx.send(KernelEvent::Connected).await.is_err() {
+                    return;
+                }
+                while let Some(Ok(message)) = stream.next().await {
+                    let event = match message {
+                        Message::Text(text) => KernelEvent::decode(&text),
+                        Message::Close(_) => break,
+                        _ => continue,
+                    };
+                    // 无法解码的消息直接忽略, 不影响后续消息
+                    if let Ok(event) = event {
+                        if tx.send(event).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(self.backoff.next_delay()) => {}
+                _ = tx.closed() => return,
+            }
+        }
+    }
+}
+
+/// 使用默认参数订阅 `profile` 对应内核的推送
+pub fn subscribe(profile: &Profile) -> (Receiver<KernelEvent>, JoinHandle<()>) {
+    Subscription::new(profile.clone()).spawn()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::SinkExt;
+    use serde_json::json;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_decode_events() {
+        let event = KernelEvent::decode(
+            r#"{"cmd":"transactions","code":0,"msg":"","data":[{"doOperations":[{"action":"update","id":"20250512161452-b4yltcf","data":"<div></div>"}],"undoOperations":null}]}"#,
+        )
+        .unwrap();
+        match event {
+            KernelEvent::Transactions(transactions) => {
+                assert_eq!(
+                    transactions[0].do_operations[0].id,
+                    "20250512161452-b4yltcf"
+                )
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        let event = KernelEvent::decode(
+            r#"{"cmd":"rename","data":{"box":"20210808180117-czj9bvb","id":"20200812220555-lj3enxa","path":"/20200812220555-lj3enxa.sy","title":"新标题"}}"#,
+        )
+        .unwrap();
+        assert!(
+            matches!(event, KernelEvent::Rename { ref title, ref box_id, .. } if title == "新标题" && box_id == "20210808180117-czj9bvb")
+        );
+
+        let event = KernelEvent::decode(r#"{"cmd":"msg","code":-1,"msg":"failed"}"#).unwrap();
+        assert!(matches!(event, KernelEvent::Message { code: -1, .. }));
+
+        let event = KernelEvent::decode(r#"{"cmd":"reloadui","data":null}"#).unwrap();
+        assert!(matches!(event, KernelEvent::Other { ref cmd, .. } if cmd == "reloadui"));
+        assert!(KernelEvent::decode("not json").is_err());
+    }
+
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_millis(350));
+        let delays: Vec<u64> = (0..4)
+            .map(|_| backoff.next_delay().as_millis() as u64)
+            .collect();
+        assert_eq!(delays, vec![100, 200, 350, 350]);
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_url() {
+        let profile = Profile {
+            base_url: "https://notes.example.com/".to_string(),
+            ..Profile::default()
+        };
+        let subscription = Subscription::new(profile).app("test");
+        assert!(subscription
+            .url()
+            .starts_with("wss://notes.example.com/ws?app=test&id="));
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_after_close() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            // 每个连接推送一条消息后立即关闭
+            for i in 0..2 {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                let message = json!({"cmd": "removeDoc", "data": {"ids": [format!("doc-{}", i)]}});
+                ws.send(Message::Text(message.to_string())).await.unwrap();
+                ws.close(None).await.unwrap();
+            }
+        });
+
+        let profile = Profile {
+            base_url,
+            ..Profile::default()
+        };
+        let (mut rx, handle) = Subscription::new(profile)
+            .backoff(Backoff::new(
+                Duration::from_millis(10),
+                Duration::from_millis(10),
+            ))
+            .spawn();
+        let mut removed = Vec::new();
+        let mut connected = 0;
+        while removed.len() < 2 {
+            match rx.recv().await.unwrap() {
+                KernelEvent::Connected => connected += 1,
+                KernelEvent::RemoveDoc { ids } => removed.extend(ids),
+                other => panic!("unexpected event: {:?}", other),
+            }
+        }
+        assert_eq!(connected, 2);
+        assert_eq!(removed, vec!["doc-0", "doc-1"]);
+        handle.abort();
+    }
+}
@@ -12,10 +12,10 @@ use std::time::Instant;
 
 pub trait Component: Any + AnyComponent {
     fn render(&mut self, f: &mut Frame, area: Rect, cx: &mut CompositorContext);
-    fn handle_event(&mut self, event: KeyEvent, context: &mut CompositorContext) -> EventResult {
+    fn handle_event(&mut self, _event: KeyEvent, _context: &mut CompositorContext) -> EventResult {
         EventResult::Ignored(None)
     }
-    fn cursor_position(&self, area: Rect) -> Option<(u16, u16)> {
+    fn cursor_position(&self, _area: Rect) -> Option<(u16, u16)> {
         None
     }
     fn render_loading(&self, frame: &mut Frame) {
@@ -1,6 +1,6 @@
 use ratatui::layout::Rect;
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, List, ListItem, Paragraph, Table, Wrap};
+use ratatui::widgets::{Block, Paragraph, Table, Wrap};
 use ratatui::Frame;
 
 pub mod doc;
@@ -1,11 +1,13 @@
 use crate::component::block::{BlockComponent, RenderedBlock};
 use crate::compositor::CompositorContext;
 use ratatui::layout::Alignment;
+use ratatui::style::{Modifier, Style};
 use ratatui::{
     text::{Line, Span},
     widgets::{Block, Borders, Paragraph},
 };
-use syservice::lute::node::{Node, NodeType};
+use syservice::lute::node::{Node, NodeType, TextMarks};
+use syservice::lute::{walk, WalkStatus};
 use unicode_width::UnicodeWidthStr;
 
 /// 渲染文档的统一入口
@@ -30,28 +32,33 @@ pub fn create_tui_element<'a>(
     cx: &'a CompositorContext,
     vec: &mut Vec<RenderedBlock<'a>>,
 ) {
-    let element: Option<RenderedBlock> = match node.node_type {
-        NodeType::Default => None,
-        // 返回标题组件
-        NodeType::NodeDocument => create_title(node, cx),
-        NodeType::NodeHeading => create_heading(node, cx),
-        NodeType::NodeParagraph => create_node_paragraph(node, cx),
-        NodeType::NodeText => create_node_text(node.data.clone().unwrap()),
-        NodeType::NodeTextMark => create_node_text_mark(node, cx),
-        NodeType::NodeList => create_list(node, cx),
-        NodeType::NodeListItem => create_list_item(node, cx),
-        _ => None,
-    };
-    if let Some(block) = element {
-        vec.push(block);
-    }
-    // 对于"文档块" 需要遍历子块来组成一个 "可渲染组件列表"
-    // 而对于其他的"非文档块",需要自己按需判断
-    if let NodeType::NodeDocument = node.node_type {
-        for child in node.children.iter() {
-            create_tui_element(child, cx, vec);
+    walk(node, &mut |node, entering| {
+        if !entering {
+            return WalkStatus::Continue;
         }
-    }
+        let element: Option<RenderedBlock> = match node.node_type {
+            NodeType::Default => None,
+            // 返回标题组件
+            NodeType::NodeDocument => create_title(node, cx),
+            NodeType::NodeHeading => create_heading(node, cx),
+            NodeType::NodeParagraph => create_node_paragraph(node, cx),
+            NodeType::NodeText => create_node_text(node.data.clone().unwrap()),
+            NodeType::NodeTextMark => create_node_text_mark(node, cx),
+            NodeType::NodeList => create_list(node, cx),
+            NodeType::NodeListItem => create_list_item(node, cx),
+            _ => None,
+        };
+        if let Some(block) = element {
+            vec.push(block);
+        }
+        // 对于"文档块" 需要遍历子块来组成一个 "可渲染组件列表"
+        // 而对于其他的"非文档块",需要自己按需判断
+        if let NodeType::NodeDocument = node.node_type {
+            WalkStatus::Continue
+        } else {
+            WalkStatus::SkipChildren
+        }
+    });
 }
 
 fn create_title<'a>(root: &'a Node, cx: &CompositorContext) -> Option<RenderedBlock<'a>> {
@@ -69,13 +76,10 @@ fn create_title<'a>(root: &'a Node, cx: &CompositorContext) -> Option<RenderedBl
             .block(Block::default().borders(Borders::LEFT | Borders::RIGHT))
         })
     });
-    match title {
-        None => None,
-        Some(title) => Some(RenderedBlock {
-            component: BlockComponent::Paragraph(title),
-            rendered_height: 3,
-        }),
-    }
+    title.map(|title| RenderedBlock {
+        component: BlockComponent::Paragraph(title),
+        rendered_height: 3,
+    })
 }
 fn create_node_paragraph<'a>(
     node: &'a Node,
@@ -119,18 +123,51 @@ fn create_node_text_mark<'a>(
     if node.node_type != NodeType::NodeTextMark {
         return None;
     };
-    if let Some(content) = &node.text_mark_text_content {
-        let span = ratatui::text::Span::from(content);
-        return Some(RenderedBlock {
-            component: BlockComponent::Span(span),
-            rendered_height: 1,
-        });
+    // 行级公式的文本为空, 显示公式内容
+    let content = node
+        .inline_math_content()
+        .or(node.text_mark_text_content.as_deref())?;
+    let mut style = text_mark_style(node.text_marks(), cx);
+    if node
+        .block_ref_id()
+        .is_some_and(|id| cx.dangling_refs.contains(id))
+    {
+        if let Some(dangling) = cx.theme.try_get("node.text.block-ref.dangling") {
+            style = style.patch(dangling);
+        }
     }
-    None
+    let span = Span::styled(content, style);
+    Some(RenderedBlock {
+        component: BlockComponent::Span(span),
+        rendered_height: 1,
+    })
 }
-fn create_text_strong(data: String, cx: CompositorContext) -> Span<'static> {
-    let style = cx.theme.get("node.text.strong");
-    Span::styled(data, style)
+
+/// 文本标记的样式: 先加上各类型对应的修饰, 再叠加主题中 `node.text.<类型>` 的样式
+fn text_mark_style(marks: TextMarks, cx: &CompositorContext) -> Style {
+    let modifiers = [
+        (TextMarks::STRONG, Modifier::BOLD),
+        (TextMarks::EM, Modifier::ITALIC),
+        (TextMarks::U, Modifier::UNDERLINED),
+        (TextMarks::S, Modifier::CROSSED_OUT),
+    ];
+    let mut style = Style::default();
+    for (mark, modifier) in modifiers {
+        if marks.contains(mark) {
+            style = style.add_modifier(modifier);
+        }
+    }
+    for name in marks.names() {
+        let scope = match name {
+            "a" => "link",
+            "code" => "codespan",
+            name => name,
+        };
+        if let Some(theme_style) = cx.theme.try_get(&format!("node.text.{scope}")) {
+            style = style.patch(theme_style);
+        }
+    }
+    style
 }
 
 /// 递归处理   NodeType::NodeHeading
@@ -154,7 +191,7 @@ fn create_heading<'a>(node: &'a Node, cx: &'a CompositorContext) -> Option<Rende
             .filter_map(|item| match item.component {
                 BlockComponent::Span(span) => {
                     let text: &str = &span.content.clone();
-                    Some((span, text.width().clone()))
+                    Some((span, text.width()))
                 }
                 _ => None,
             })
@@ -223,7 +260,7 @@ fn create_list<'a>(node: &'a Node, cx: &'a CompositorContext) -> Option<Rendered
                 // TOOD  Line 的部分看看可不可以直接删除掉.
                 // item 是 Vec<Line>. list_items 也是 Vec<Line>
                 // 把 items 中的元素放入
-                list_items.extend(items.into_iter());
+                list_items.extend(items);
             }
         }
     }
@@ -234,7 +271,7 @@ fn create_list<'a>(node: &'a Node, cx: &'a CompositorContext) -> Option<Rendered
     })
 }
 
-///
+/// 创建 ListItem 组件
 fn create_list_item<'a>(node: &'a Node, cx: &'a CompositorContext) -> Option<RenderedBlock<'a>> {
     let mut child_blocks = Vec::new();
 
@@ -1,17 +1,21 @@
 use super::*;
 use crate::component::block::{doc, BlockComponent, RenderedBlock};
-use crate::component::gutter::{render_gutter, GutterConfig, GutterType};
+use crate::component::gutter::{render_gutter, GutterConfig};
 use crate::component::search_box::SearchBox;
 use crate::compositor::{Compositor, CompositorContext, EventResult};
+use crate::job::dispatch;
 use crossterm::event::{KeyCode, KeyEvent};
-use ratatui::layout::{Position, Size};
+use ratatui::layout::Position;
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Style},
     widgets::{Block, Borders, Paragraph},
     Frame,
 };
+use syservice::backend::SharedBackend;
+use syservice::block_ref::{apply_refs, ref_ids};
 use syservice::lute::node::Node;
+use syservice::ws::KernelEvent;
 
 pub const ID: &str = "editor-view";
 pub struct EditorView<'a> {
@@ -19,12 +23,21 @@ pub struct EditorView<'a> {
     pub gutter_area: Rect,
     pub content_area: Rect,
     pub document: Option<Node>,
+    /// 当前文档的存储路径 `box_id/path`, 用于重新加载
+    pub document_path: Option<String>,
     pub doc_blocks: Vec<BlockComponent<'a>>,
     status_msg: Option<String>, // 状态消息
-    count: Option<u32>,         // 模拟按键计数
+    #[allow(dead_code)]
+    count: Option<u32>, // 模拟按键计数
     /// 侧边栏
     gutter: GutterConfig,
 }
+impl Default for EditorView<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'a> EditorView<'a> {
     pub fn new() -> Self {
         let status_msg = Some("status".to_string());
@@ -35,12 +48,17 @@ impl<'a> EditorView<'a> {
             gutter_area: Rect::default(),
             content_area: Rect::default(),
             document: None,
+            document_path: None,
             status_msg,
             count,
             gutter: GutterConfig::default(),
         }
     }
-    
+
+    pub fn set_status_msg(&mut self, msg: impl Into<String>) {
+        self.status_msg = Some(msg.into());
+    }
+
     fn cursor_move(&mut self, code: KeyCode) -> EventResult {
         let new_pos = match code {
             KeyCode::Down if self.cursor_position.y + 1 < self.content_area.height => Position {
@@ -65,12 +83,102 @@ impl<'a> EditorView<'a> {
         EventResult::Consumed(None)
     }
 
+    /// 根据内核推送的事件更新当前文档, 返回是否需要从磁盘重新加载
+    pub fn apply_kernel_event(&mut self, event: &KernelEvent) -> bool {
+        let Some(document) = &mut self.document else {
+            return false;
+        };
+        match event {
+            KernelEvent::Connected => true,
+            KernelEvent::Transactions(transactions) => transactions
+                .iter()
+                .flat_map(|tx| &tx.do_operations)
+                .any(|op| {
+                    contains_block(document, &op.id) || contains_block(document, &op.parent_id)
+                }),
+            KernelEvent::Rename { id, title, .. } if document.id.as_ref() == Some(id) => {
+                document
+                    .properties
+                    .get_or_insert_with(Default::default)
+                    .insert("title".to_string(), title.clone());
+                false
+            }
+            KernelEvent::RemoveDoc { ids }
+                if document.id.as_ref().is_some_and(|id| ids.contains(id)) =>
+            {
+                self.document = None;
+                self.document_path = None;
+                self.status_msg = Some("文档已被删除".to_string());
+                false
+            }
+            KernelEvent::Progress { msg } | KernelEvent::Message { msg, .. } => {
+                self.status_msg = Some(msg.clone());
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// 通过数据后端重新加载当前文档
+    pub fn reload_document(&self, backend: &SharedBackend) {
+        let Some(doc_path) = self.document_path.clone() else {
+            return;
+        };
+        let backend = backend.clone();
+        tokio::spawn(async move {
+            let node = backend.load_document(&doc_path).await;
+            dispatch(
+                move |compositor: &mut Compositor, cx: &mut CompositorContext| {
+                    if let Some(editor_view) = compositor.find::<EditorView>() {
+                        // 加载期间可能已经打开了其他文档
+                        if editor_view.document_path.as_deref() == Some(doc_path.as_str()) {
+                            if let Ok(node) = node {
+                                editor_view.document = Some(node);
+                                editor_view.resolve_refs(cx);
+                            }
+                        }
+                    }
+                },
+            )
+            .await
+        });
+    }
+
+    /// 异步解析当前文档中的块引用: 更新动态锚文本并记录失效的引用.
+    ///
+    /// 解析期间文档可能已被重新加载, 因此结果按引用 ID 应用到届时的文档上, 而不是替换文档
+    pub fn resolve_refs(&self, cx: &CompositorContext) {
+        let (Some(document), Some(doc_path)) = (&self.document, self.document_path.clone()) else {
+            return;
+        };
+        let ids = ref_ids(document);
+        let resolver = cx.ref_resolver.clone();
+        tokio::spawn(async move {
+            let Ok(blocks) = resolver.resolve(&ids).await else {
+                return;
+            };
+            dispatch(
+                move |compositor: &mut Compositor, cx: &mut CompositorContext| {
+                    if let Some(editor_view) = compositor.find::<EditorView>() {
+                        if editor_view.document_path.as_deref() != Some(doc_path.as_str()) {
+                            return;
+                        }
+                        if let Some(document) = &mut editor_view.document {
+                            cx.dangling_refs = apply_refs(document, &blocks);
+                        }
+                    }
+                },
+            )
+            .await
+        });
+    }
+
     pub fn render_document(
         // 使用不同的生命周期名称 'b
-        & mut self,
-        frame: & mut Frame,
+        &mut self,
+        frame: &mut Frame,
         content_area: Rect,
-        cx: & mut CompositorContext,
+        cx: &mut CompositorContext,
     ) {
         let mut vec: Vec<RenderedBlock> = Vec::new();
         if let Some(node) = &mut self.document {
@@ -102,7 +210,7 @@ impl<'a> EditorView<'a> {
 }
 
 impl Component for EditorView<'static> {
-    fn render(&mut self, frame: &mut Frame, area: Rect, cx: &mut CompositorContext) {
+    fn render(&mut self, frame: &mut Frame, _area: Rect, cx: &mut CompositorContext) {
         let area = frame.size();
 
         // 1. 清空背景
@@ -110,7 +218,7 @@ impl Component for EditorView<'static> {
         frame.render_widget(Block::default().style(*editor_bg), area);
 
         // 2. 计算编辑器区域（减去状态栏和可能的 BufferLine）
-        let mut editor_area = Layout::default()
+        let editor_area = Layout::default()
             .direction(Direction::Vertical)
             .constraints(vec![
                 Constraint::Length(1), // Buffer line
@@ -172,14 +280,14 @@ impl Component for EditorView<'static> {
         match event.code {
             KeyCode::Char(' ') => {
                 // 当按下空格键时，添加 SearchBox 组件
-                let search_box = SearchBox::new("Search", "Result");
+                let search_box = SearchBox::new("Search", "Result", context.backend.clone());
                 // TODO:  由于 Rust 默认不允许"多重借用???" Helix通过
                 //  单独的函数来将一个把 Compositor 作为参数的 fn 存入 callback
                 //  参考 compositor 中的 handle_event 函数
                 //  每一个组件实际上会返回一个 将 compositor 作为参数的函数.
                 //  然后这个函数在 Compositor.handle_event 中被执行
                 let callback: Callback = Box::new(
-                    move |compositor: &mut Compositor, cx: &mut CompositorContext| {
+                    move |compositor: &mut Compositor, _cx: &mut CompositorContext| {
                         compositor.push(Box::new(search_box));
                     },
                 );
@@ -193,7 +301,7 @@ impl Component for EditorView<'static> {
         }
     }
 
-    fn cursor_position(&self, area: Rect) -> Option<(u16, u16)> {
+    fn cursor_position(&self, _area: Rect) -> Option<(u16, u16)> {
         todo!()
     }
 
@@ -201,3 +309,10 @@ impl Component for EditorView<'static> {
         Some(ID)
     }
 }
+
+/// `node` 及其子孙节点中是否包含 id 为 `id` 的块
+fn contains_block(node: &Node, id: &str) -> bool {
+    !id.is_empty()
+        && (node.id.as_deref() == Some(id)
+            || node.children.iter().any(|child| contains_block(child, id)))
+}
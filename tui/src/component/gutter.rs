@@ -1,11 +1,10 @@
 use ratatui::layout::Rect;
-use ratatui::prelude::{Color, Span, Style};
-use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::prelude::{Span, Style};
+use ratatui::widgets::{Block, Paragraph};
 use ratatui::Frame;
 use unicode_width::UnicodeWidthStr;
 
 /// 编辑器侧边栏
-
 pub struct GutterConfig {
     /// 侧边栏展示顺序(从左到右)
     pub layout: Vec<GutterType>,
@@ -94,6 +93,7 @@ pub fn render_gutter(frame: &mut Frame, area: Rect, config: &GutterConfig, total
 }
 
 impl GutterType {
+    #[allow(dead_code)]
     fn width(&self, total_lines: usize) -> u16 {
         match self {
             GutterType::LineNumbers => total_lines.to_string().len().max(3) as u16 + 1,
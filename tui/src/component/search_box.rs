@@ -12,7 +12,10 @@ use ratatui::{
     style::{Modifier, Style},
     widgets::*,
 };
-use syservice;
+use syservice::backend::SharedBackend;
+use syservice::domain::OperationAction;
+use syservice::search::Highlighted;
+use syservice::ws::KernelEvent;
 use tokio::sync::mpsc::Sender;
 use unicode_width::UnicodeWidthStr;
 
@@ -30,40 +33,27 @@ pub struct SearchBox {
     selected_result: Option<usize>,
     /// 输入框标题
     title: String,
+    /// 结果列表标题
+    results_title: String,
 
     pub width: u16,
     pub height: u16,
 
     /// 延时搜索
     async_sender: Sender<String>,
+    backend: SharedBackend,
 }
 /// 摘取SiYuan数据库字段
+#[allow(dead_code)]
 #[derive(Debug)]
 struct SearchResultItem {
     pub id: String,
     pub box_id: String,
-    pub content: String,
+    /// 命中的块内容
+    pub content: Highlighted,
     pub path: String,
     pub hpath: String,
 }
-impl<'a> Default for SearchBox {
-    fn default() -> Self {
-        let search_debounce = SearchBoxDebounce::new();
-        // TODO 在这里手动设置 debounce 中的异步逻辑
-        let sender = search_debounce.spawn();
-        Self {
-            cursor_position: 0,
-            input: String::new(),
-            results: vec![],
-            list_state: ListState::default(),
-            selected_result: None,
-            title: "Search".to_string(),
-            width: 0,
-            height: 0,
-            async_sender: sender,
-        }
-    }
-}
 impl Component for SearchBox {
     fn render(&mut self, frame: &mut Frame, area: Rect, cx: &mut CompositorContext) {
         let inner_area = Rect {
@@ -101,12 +91,34 @@ impl Component for SearchBox {
         );
 
         // 渲染结果列表（保持不变）
-        let results_block = Block::default().borders(Borders::ALL);
+        let results_block = Block::default()
+            .title(self.results_title.clone())
+            .borders(Borders::ALL);
 
+        let path_style = cx.theme.get("ui.search.path");
+        let match_style = cx.theme.get("ui.search.match");
         let items: Vec<ListItem> = self
             .results
             .iter()
-            .map(|r| ListItem::new(r.hpath.as_str()))
+            .map(|r| {
+                let path = match cx.notebook_name(&r.box_id) {
+                    Some(notebook) => format!("{}{}", notebook, r.hpath),
+                    None => r.hpath.clone(),
+                };
+                let content: Vec<Span> = r
+                    .content
+                    .segments()
+                    .into_iter()
+                    .map(|(text, matched)| match matched {
+                        true => Span::styled(text, match_style),
+                        false => Span::raw(text),
+                    })
+                    .collect();
+                ListItem::new(vec![
+                    Line::from(Span::styled(path, path_style)),
+                    Line::from(content),
+                ])
+            })
             .collect();
 
         let list = List::new(items)
@@ -127,7 +139,7 @@ impl Component for SearchBox {
         frame.render_stateful_widget(list, chunks[1], &mut self.list_state);
     }
 
-    fn handle_event(&mut self, event: KeyEvent, context: &mut CompositorContext) -> EventResult {
+    fn handle_event(&mut self, event: KeyEvent, _context: &mut CompositorContext) -> EventResult {
         match event.code {
             crossterm::event::KeyCode::Char(c) => self.handle_search_input(c),
             crossterm::event::KeyCode::Backspace => self.handle_delete_char(),
@@ -162,7 +174,7 @@ impl Component for SearchBox {
             crossterm::event::KeyCode::Down => {
                 if !self.results.is_empty() {
                     self.selected_result = Some(match self.selected_result {
-                        Some(i) if i <= self.results.len() - 1 => i + 1,
+                        Some(i) if i < self.results.len() => i + 1,
                         None if !self.results.is_empty() => 0,
                         _ => self.results.len() - 1,
                     });
@@ -181,7 +193,7 @@ impl Component for SearchBox {
             }
             crossterm::event::KeyCode::Esc => {
                 let callback: crate::compositor::Callback = Box::new(
-                    move |compositor: &mut Compositor, cx: &mut CompositorContext| {
+                    move |compositor: &mut Compositor, _cx: &mut CompositorContext| {
                         compositor.pop();
                     },
                 );
@@ -195,13 +207,24 @@ impl Component for SearchBox {
     }
 }
 
-impl<'a> SearchBox {
+impl SearchBox {
     /// 创建新的SearchBox
-    pub fn new(title: &'a str, results_title: &'a str) -> Self {
-        let title = title.to_string();
+    pub fn new(title: &str, results_title: &str, backend: SharedBackend) -> Self {
+        let search_debounce = SearchBoxDebounce::new(backend.clone());
+        // TODO 在这里手动设置 debounce 中的异步逻辑
+        let sender = search_debounce.spawn();
         Self {
-            title,
-            ..Default::default()
+            cursor_position: 0,
+            input: String::new(),
+            results: vec![],
+            list_state: ListState::default(),
+            selected_result: None,
+            title: title.to_string(),
+            results_title: results_title.to_string(),
+            width: 0,
+            height: 0,
+            async_sender: sender,
+            backend,
         }
     }
     /// 获取当前输入内容
@@ -215,6 +238,47 @@ impl<'a> SearchBox {
             .and_then(|i| self.results.get(i))
             .map(|item| &item.hpath)
     }
+    /// 根据内核推送的事件更新搜索结果
+    pub fn apply_kernel_event(&mut self, event: &KernelEvent) {
+        match event {
+            KernelEvent::Transactions(transactions) => {
+                let deleted: Vec<&str> = transactions
+                    .iter()
+                    .flat_map(|tx| &tx.do_operations)
+                    .filter(|op| op.action == OperationAction::Delete)
+                    .map(|op| op.id.as_str())
+                    .collect();
+                self.results.retain(|r| !deleted.contains(&r.id.as_str()));
+            }
+            KernelEvent::Rename {
+                box_id,
+                path,
+                title,
+                ..
+            } => {
+                for item in self.results.iter_mut() {
+                    if item.box_id == *box_id && item.path == *path {
+                        if let Some((parent, _)) = item.hpath.rsplit_once('/') {
+                            item.hpath = format!("{}/{}", parent, title);
+                        }
+                    }
+                }
+            }
+            KernelEvent::RemoveDoc { ids } => {
+                // 子文档的路径中包含父文档 id
+                self.results
+                    .retain(|r| !ids.iter().any(|id| r.path.contains(id.as_str())));
+            }
+            _ => return,
+        }
+        if self
+            .selected_result
+            .is_some_and(|i| i >= self.results.len())
+        {
+            self.selected_result = None;
+        }
+    }
+
     /// 清除选中状态
     pub fn clear_selection(&mut self) {
         self.selected_result = None;
@@ -248,20 +312,24 @@ impl<'a> SearchBox {
             Some(doc_info) => format!("{}{}", doc_info.box_id, doc_info.path),
             None => return EventResult::Consumed(None), // 提前返回避免无效spawn
         };
+        let backend = self.backend.clone();
         tokio::spawn(async move {
-            let sy_nodes = syservice::file::load_json_node(&doc_path);
-            let open_document = move |compositor: &mut Compositor| {
+            let sy_nodes = backend.load_document(&doc_path).await;
+            let open_document = move |compositor: &mut Compositor, cx: &mut CompositorContext| {
                 let component = compositor.find::<EditorView>();
-                if let Some(editorView) = component {
+                if let Some(editor_view) = component {
                     match sy_nodes {
                         Ok(node) => {
-                            editorView.document = Some(node);
+                            editor_view.document = Some(node);
+                            editor_view.document_path = Some(doc_path);
+                            cx.dangling_refs.clear();
+                            editor_view.resolve_refs(cx);
                             compositor.pop();
                             // TODO 这里还需要进行计算操作
                             //  每个元素组件占据多少 offset.
                             //  以便处理窗口滑动
                         }
-                        Err(_) => editorView.document = None,
+                        Err(_) => editor_view.document = None,
                     }
                 }
             };
@@ -1,9 +1,10 @@
 use crate::component::search_box::{SearchBox, SearchResultItem};
-use crate::compositor::Compositor;
+use crate::compositor::{Compositor, CompositorContext};
 use crate::debounce::AsyncHook;
 use crate::job::dispatch;
 use std::time::Duration;
-use syservice::document;
+use syservice::backend::SharedBackend;
+use syservice::search::{FullTextSearch, OrderBy};
 use tokio::task::JoinHandle;
 use tokio::time::Instant;
 
@@ -13,14 +14,16 @@ pub struct SearchBoxDebounce {
     current_task: Option<JoinHandle<()>>, // 当前搜索任务句柄
     last_query: String,                   // 当前查询内容
     debounce: Duration,
+    backend: SharedBackend,
 }
 
 impl SearchBoxDebounce {
-    pub fn new() -> Self {
+    pub fn new(backend: SharedBackend) -> Self {
         Self {
             debounce: SEARCH_BOX_DEFAULT_DEBOUNCE,
             last_query: "".into(),
             current_task: None,
+            backend,
         }
     }
 }
@@ -31,45 +34,53 @@ impl AsyncHook for SearchBoxDebounce {
     fn handle_event(
         &mut self,
         input: Self::Event,
-        timeout: Option<tokio::time::Instant>,
+        _timeout: Option<tokio::time::Instant>,
     ) -> Option<Instant> {
         if self.last_query == *input {
             None
         } else {
             self.last_query = input;
-            Some(Instant::now() + SEARCH_BOX_DEFAULT_DEBOUNCE)
+            Some(Instant::now() + self.debounce)
         }
     }
 
     /// 防抖结束时,发起接口调用. 接口返回后返回 UI 更新.
     fn finish_debounce(&mut self) {
         let query = self.last_query.clone();
+        let backend = self.backend.clone();
 
         // TODO finish_debounce 的调用最好也是放入searchBox 中, debounce 本身
         //  只保留异步调用相关逻辑内容
-        tokio::spawn(async move {
-            let sy_blocks = document::search_doc_with_title(query).await;
+        // 新的查询开始后,上一次尚未返回的查询结果已经没有意义
+        if let Some(task) = self.current_task.take() {
+            task.abort();
+        }
+        let task = tokio::spawn(async move {
+            let request = FullTextSearch::new(query).order_by(OrderBy::RelevanceDesc);
+            let sy_blocks = backend.search(&request).await;
 
-            let update_search_result = move |compositor: &mut Compositor| {
-                let component = compositor.find::<SearchBox>();
-                if let Some(search_box) = component {
-                    if let Ok(resp) = sy_blocks {
-                        search_box.results = resp
-                            .data
-                            .iter()
-                            .map(move |it| SearchResultItem {
-                                id: it.id.clone(),
-                                box_id: it.box_id.clone(),
-                                content: it.content.clone(),
-                                path: it.path.clone(),
-                                hpath: it.hpath.clone(),
-                            })
-                            .collect();
-                        search_box.selected_result = None;
+            let update_search_result =
+                move |compositor: &mut Compositor, _cx: &mut CompositorContext| {
+                    let component = compositor.find::<SearchBox>();
+                    if let Some(search_box) = component {
+                        if let Ok(resp) = sy_blocks {
+                            search_box.results = resp
+                                .blocks()
+                                .iter()
+                                .map(move |it| SearchResultItem {
+                                    id: it.id.clone(),
+                                    box_id: it.box_id.clone(),
+                                    content: it.highlighted_content(),
+                                    path: it.path.clone(),
+                                    hpath: it.h_path.clone(),
+                                })
+                                .collect();
+                            search_box.selected_result = None;
+                        }
                     }
-                }
-            };
+                };
             dispatch(update_search_result).await
         });
+        self.current_task = Some(task);
     }
 }
@@ -1,10 +1,16 @@
-use std::any::Any;
 // 参考 Helix 实现的 UI 调度器
 use crate::component::editor::EditorView;
 use crate::component::Component;
+use crate::job::dispatch;
 use crate::uiconfig::theme::Theme;
-use crossterm::event::{Event, KeyEvent, KeyEventKind};
+use crossterm::event::KeyEvent;
 use ratatui::prelude::*;
+use std::collections::HashSet;
+use std::sync::Arc;
+use syservice::backend::{KernelBackend, SharedBackend};
+use syservice::block_ref::RefResolver;
+use syservice::client::SiyuanClient;
+use syservice::domain::Notebook;
 
 /// 回调
 pub type Callback = Box<dyn FnOnce(&mut Compositor, &mut CompositorContext)>;
@@ -23,9 +29,23 @@ pub struct Compositor {
 pub struct CompositorContext {
     pub theme: Theme,
     pub scroll: Option<usize>,
+    /// 当前 profile 对应的数据后端
+    pub backend: SharedBackend,
+    /// 笔记本列表缓存, 用于将 box id 显示为笔记本名称
+    pub notebooks: Vec<Notebook>,
+    /// 块引用解析, 缓存被引用块的内容
+    pub ref_resolver: Arc<RefResolver>,
+    /// 当前文档中失效的块引用
+    pub dangling_refs: HashSet<String>,
 }
 
-impl<'a> Compositor {
+impl Default for Compositor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compositor {
     pub fn new() -> Compositor {
         let editor: Box<dyn Component> = Box::new(EditorView::new());
         let layers = vec![editor];
@@ -34,9 +54,9 @@ impl<'a> Compositor {
     /// UI 组合器从下往上逐层绘制组件
     /// TODO 如果事件被顶层 Layer 消费,
     ///     并且不涉及异步更新 UI 的操作,就可以不重绘下层 UI
-    pub fn render(&mut self, frame: &mut Frame, surface: Rect, cx:&mut CompositorContext) {
+    pub fn render(&mut self, frame: &mut Frame, surface: Rect, cx: &mut CompositorContext) {
         for layer in &mut self.layers {
-            layer.render(frame, surface,cx);
+            layer.render(frame, surface, cx);
         }
     }
 
@@ -68,7 +88,7 @@ impl<'a> Compositor {
         None
     }
 
-    pub fn push(&mut self, mut layer: Box<dyn Component>) {
+    pub fn push(&mut self, layer: Box<dyn Component>) {
         self.layers.push(layer);
     }
 
@@ -85,11 +105,45 @@ impl<'a> Compositor {
     }
 }
 
+impl Default for CompositorContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CompositorContext {
     pub fn new() -> Self {
+        Self::with_backend(Arc::new(KernelBackend::new(SiyuanClient::default())))
+    }
+
+    pub fn with_backend(backend: SharedBackend) -> Self {
         Self {
             theme: Theme::default(),
             scroll: None,
+            ref_resolver: Arc::new(RefResolver::new(backend.clone())),
+            backend,
+            notebooks: Vec::new(),
+            dangling_refs: HashSet::new(),
         }
     }
+
+    pub fn notebook_name(&self, box_id: &str) -> Option<&str> {
+        self.notebooks
+            .iter()
+            .find(|notebook| notebook.id == box_id)
+            .map(|notebook| notebook.name.as_str())
+    }
+
+    /// 异步刷新笔记本缓存
+    pub fn refresh_notebooks(&self) {
+        let backend = self.backend.clone();
+        tokio::spawn(async move {
+            if let Ok(notebooks) = backend.ls_notebooks().await {
+                dispatch(move |_: &mut Compositor, cx: &mut CompositorContext| {
+                    cx.notebooks = notebooks;
+                })
+                .await
+            }
+        });
+    }
 }
@@ -1,11 +1,9 @@
-use std::future::Future;
-use crate::compositor::Compositor;
+use crate::compositor::{Compositor, CompositorContext};
 use once_cell::sync::OnceCell;
 use std::ops::Deref;
-use std::sync::OnceLock;
 use tokio::sync::mpsc::{self, Receiver, Sender};
 
-type Callback = Box<dyn FnOnce(&mut Compositor) + Send + 'static>;
+type Callback = Box<dyn FnOnce(&mut Compositor, &mut CompositorContext) + Send + 'static>;
 
 /// 全局任务队列
 pub struct JobQueue {
@@ -17,32 +15,26 @@ impl JobQueue {
     pub fn handle_callback(
         &self,
         compositor: &mut Compositor,
+        cx: &mut CompositorContext,
         call: anyhow::Result<Option<Callback>>,
     ) {
         match call {
             Ok(None) => {}
-            Ok(Some(call)) => call(compositor),
-            Err(e) => {
-                
-            }
+            Ok(Some(call)) => call(compositor, cx),
+            Err(_) => {}
         }
     }
 
     pub fn new() -> Self {
-        static INSTANCE: OnceLock<JobQueue> = OnceLock::new();
         let (tx, rx) = mpsc::channel(1024);
         let _ = JOB_QUEUE.set(tx);
         Self { callbacks: rx }
     }
+}
 
-    pub fn handle_callbacks(compositor: &mut Compositor) {
-        todo!()
-    }
-    fn process_callbacks(mut rx: Receiver<Callback>, compositor: &mut Compositor) {
-        // while let Some(callback) = rx.recv().await {
-        //     callback(compositor); // 执行回调
-        // }
-        todo!()
+impl Default for JobQueue {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -52,14 +44,11 @@ pub(crate) static JOB_QUEUE: RunTimeLocal<OnceCell<Sender<Callback>>> = {
     }
 };
 
-pub async fn dispatch(job: impl FnOnce(&mut Compositor) + Send + 'static) {
+pub async fn dispatch(job: impl FnOnce(&mut Compositor, &mut CompositorContext) + Send + 'static) {
     let _ = JOB_QUEUE.wait().send(Box::new(job)).await;
 }
-pub async fn dispatch_(job: impl FnOnce(&mut Compositor) + 'static) {
-    
-}
 
-///
+//
 // pub fn dispatch_blocking(job: impl FnOnce(&mut Compositor) + Send + 'static) {
 //     let jobs = JOB_QUEUE
 //         .wait().blocking_send(Box::new(job));
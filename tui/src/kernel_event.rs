@@ -0,0 +1,45 @@
+//! 将内核通过 WebSocket 推送的事件转发给 UI 组件
+use crate::component::editor::EditorView;
+use crate::component::search_box::SearchBox;
+use crate::compositor::{Compositor, CompositorContext};
+use crate::job::dispatch;
+use syservice::config::Profile;
+use syservice::ws::{self, KernelEvent};
+use tokio::task::JoinHandle;
+
+/// 订阅 `profile` 对应内核的推送, 每个事件都通过 [`dispatch`] 交给 UI 线程处理
+pub fn spawn(profile: &Profile) -> JoinHandle<()> {
+    let (mut events, _subscription) = ws::subscribe(profile);
+    tokio::spawn(async move {
+        while let Some(event) = events.recv().await {
+            dispatch(
+                move |compositor: &mut Compositor, cx: &mut CompositorContext| {
+                    handle_event(compositor, cx, event)
+                },
+            )
+            .await;
+        }
+    })
+}
+
+fn handle_event(compositor: &mut Compositor, cx: &mut CompositorContext, event: KernelEvent) {
+    // 断线期间笔记本可能发生了变化
+    if let KernelEvent::Connected = event {
+        cx.refresh_notebooks();
+    }
+    // 任何块的修改都可能改变其他文档中动态引用的锚文本
+    if let KernelEvent::Connected | KernelEvent::Transactions(_) = event {
+        cx.ref_resolver.clear();
+    }
+    if let Some(editor_view) = compositor.find::<EditorView>() {
+        if editor_view.apply_kernel_event(&event) {
+            editor_view.reload_document(&cx.backend);
+        } else if let KernelEvent::Transactions(_) = event {
+            // 当前文档未变, 但被引用的块可能改了内容或被删除
+            editor_view.resolve_refs(cx);
+        }
+    }
+    if let Some(search_box) = compositor.find::<SearchBox>() {
+        search_box.apply_kernel_event(&event);
+    }
+}
@@ -1,5 +1,6 @@
 pub mod compositor;
 pub mod job;
+pub mod kernel_event;
 pub mod status;
 pub mod component;
 mod uiconfig;
@@ -1,7 +1,7 @@
 use lazy_static::lazy_static;
 use ratatui::style::{Modifier, Style};
 
-/// TODO 这里还需要想一下,样式表什么时候生成,是否需要做成全局静态变量
+// TODO 这里还需要想一下,样式表什么时候生成,是否需要做成全局静态变量
 lazy_static! {
     static ref BOLD_STYLE: Style= Style::default().add_modifier(Modifier::BOLD);
     static ref ITALIC_STYLE: Style = Style::default().add_modifier(Modifier::ITALIC);
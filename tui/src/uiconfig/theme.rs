@@ -36,6 +36,7 @@ impl Theme {
             .find_map(|s| self.styles.get(s).copied())
     }
 }
+#[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct ThemeColorItem {
     /// 背景颜色（十六进制格式，示例："#282c34"）
@@ -163,7 +164,7 @@ fn process_styles(
             let modifier = style_table
                 .get("modifier")
                 .and_then(|v| v.as_str())
-                .map(|s| parse_modifier(s))
+                .map(parse_modifier)
                 .unwrap_or_default();
 
             style = style.add_modifier(modifier);
@@ -201,7 +202,7 @@ fn change_brightness(color: &str, amount: f32) -> Result<Color, ratatui::style::
     // 调整亮度分量（按比例增加）
     hsl[2] = (hsl[2] * amount).clamp(0.0, 1.0); // 确保亮度在 0-1 范围
     let hex_color =
-        csscolorparser::Color::from_hsla(hsl[0], hsl[1], hsl[2], hsl[3]).to_hex_string();
+        csscolorparser::Color::from_hsla(hsl[0], hsl[1], hsl[2], hsl[3]).to_css_hex();
     RatColor::from_str(&hex_color)
 }
 
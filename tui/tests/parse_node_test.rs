@@ -1,7 +1,7 @@
-use anyhow::{Context, Result};
+use anyhow::Context;
 use std::fs::File;
 use std::io::BufReader;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use syservice::lute;
 use tui::component::block::{doc, RenderedBlock};
 use tui::compositor::CompositorContext;
@@ -15,7 +15,7 @@ fn parse_node_test() {
     let mut vec: Vec<RenderedBlock> = Vec::new();
     root.set_node_type_for_tree();
     doc::create_tui_element(&root, &cx, &mut vec);
-    assert!(vec.len() > 0)
+    assert!(!vec.is_empty())
 }
 
 fn get_resource_path(filename: &str) -> PathBuf {
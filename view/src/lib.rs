@@ -1,5 +1,5 @@
-mod editor;
-mod config;
+pub mod editor;
+pub mod config;
 
 pub fn add(left: u64, right: u64) -> u64 {
     left + right